@@ -0,0 +1,200 @@
+use std::sync::Mutex;
+
+use crate::{scene::Scene, utils::vec3::Color};
+
+/// Filtered image and the number of samples of every pixel.
+type Render = (Vec<Vec<Color>>, Vec<Vec<u32>>);
+
+/// Settings for adaptive sampling. `Config::samples` becomes the average
+/// sample budget per pixel, spent preferentially on noisy pixels.
+pub struct AdaptiveConfig {
+    /// Pixels stop sampling once the standard error of their gamma corrected
+    /// luminance drops below this value.
+    pub noise_threshold: f64,
+    pub min_samples: u32,
+    pub max_samples: u32,
+    /// Also write `<name>_samples.ppm` showing the samples spent per pixel.
+    pub samples_map: bool,
+}
+
+impl AdaptiveConfig {
+    pub fn default() -> Self {
+        AdaptiveConfig {
+            noise_threshold: 0.01,
+            min_samples: 16,
+            max_samples: 1024,
+            samples_map: false,
+        }
+    }
+}
+
+/// Running luminance statistics of the samples of one pixel.
+pub struct PixelStats {
+    luminance_sum: f64,
+    luminance_sq_sum: f64,
+    samples: u32,
+    converged: bool,
+}
+
+impl PixelStats {
+    pub fn new() -> Self {
+        PixelStats {
+            luminance_sum: 0.,
+            luminance_sq_sum: 0.,
+            samples: 0,
+            converged: false,
+        }
+    }
+
+    /// Luminance sum, squared luminance sum and sample count.
+    pub fn to_raw(&self) -> (f64, f64, u32) {
+        (self.luminance_sum, self.luminance_sq_sum, self.samples)
+    }
+
+    pub fn from_raw((luminance_sum, luminance_sq_sum, samples): (f64, f64, u32)) -> Self {
+        PixelStats {
+            luminance_sum,
+            luminance_sq_sum,
+            samples,
+            converged: false,
+        }
+    }
+
+    pub fn add(&mut self, color: Color) {
+        let l = color.luminance();
+        self.luminance_sum += l;
+        self.luminance_sq_sum += l * l;
+        self.samples += 1;
+    }
+
+    /// Standard error of the mean luminance mapped through the gamma 2 curve
+    /// used for display (d sqrt(x) = dx / (2 sqrt(x))).
+    pub fn error(&self) -> f64 {
+        let n = self.samples as f64;
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        let mean = self.luminance_sum / n;
+        let variance = ((self.luminance_sq_sum - mean * self.luminance_sum) / (n - 1.)).max(0.);
+        let std_error = (variance / n).sqrt();
+        std_error / (2. * mean.max(1e-4).sqrt())
+    }
+}
+
+/// Renders with per-pixel convergence tests, returning the filtered image and
+/// the number of samples of every pixel.
+pub fn render(scene: &Scene, params: &AdaptiveConfig) -> Result<Render, String> {
+    let config = scene.config();
+    let height = config.height;
+    let width = config.width();
+    let min_samples = params.min_samples.max(2);
+    let max_samples = params.max_samples.max(min_samples);
+    let budget = config.samples as u64 * (width * height) as u64;
+
+    let film = scene.film();
+    let stats: Vec<Mutex<PixelStats>> = (0..width * height)
+        .map(|_| Mutex::new(PixelStats::new()))
+        .collect();
+    let mut step = min_samples;
+    loop {
+        scene.for_each_tile(|tile| {
+            let mut sampler = config.sampler.build(config.seed, max_samples);
+            let mut traced = 0;
+            for j in tile.y0..tile.y1 {
+                if scene.is_cancelled() {
+                    break;
+                }
+                for i in tile.x0..tile.x1 {
+                    let mut pixel = stats[j * width + i].lock().unwrap();
+                    if pixel.converged {
+                        continue;
+                    }
+                    let end = (pixel.samples + step).min(max_samples);
+                    traced += (end - pixel.samples) as u64;
+                    for s in pixel.samples..end {
+                        let (color, position) = scene.sample_pixel(&mut *sampler, i, j, s);
+                        pixel.add(color);
+                        film.add_sample(position, color);
+                    }
+                    pixel.converged =
+                        pixel.samples >= max_samples || pixel.error() < params.noise_threshold;
+                }
+            }
+            traced
+        })?;
+
+        let pixels: Vec<(u32, bool)> = stats
+            .iter()
+            .map(|p| {
+                let p = p.lock().unwrap();
+                (p.samples, p.converged)
+            })
+            .collect();
+        let spent = pixels.iter().map(|p| p.0 as u64).sum::<u64>();
+        let active = pixels.iter().filter(|p| !p.1).count() as u64;
+        if active == 0 || spent >= budget || scene.is_cancelled() {
+            break;
+        }
+        // the budget freed by converged pixels goes to the remaining ones
+        step = ((budget - spent) / active).clamp(1, min_samples as u64) as u32;
+    }
+
+    let samples = stats
+        .chunks(width)
+        .map(|row| row.iter().map(|p| p.lock().unwrap().samples).collect())
+        .collect();
+    Ok((film.image(), samples))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        materials::diffuse::Diffuse,
+        scene::{Config, Scene},
+        utils::vec3::Point3,
+    };
+
+    use super::{render, AdaptiveConfig};
+
+    #[test]
+    fn flat_background_converges_early() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -100.5, -1.),
+            100.,
+            Arc::new(Diffuse::new(0.1, 0.1, 0.1)),
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0.5, 0., -1.),
+            0.5,
+            Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+        )));
+        let mut scene = Scene::new();
+        scene.set_world(world);
+        scene.set_config(Config {
+            height: 18,
+            samples: 32,
+            ..Config::default()
+        });
+        let params = AdaptiveConfig {
+            noise_threshold: 0.001,
+            min_samples: 8,
+            max_samples: 256,
+            samples_map: false,
+        };
+
+        let (image, samples) = render(&scene, &params).unwrap();
+        let width = scene.config().width();
+        let background = samples[9][0];
+        let sphere = samples[9][width * 5 / 8];
+        let total: u32 = samples.iter().flatten().sum();
+
+        assert_eq!(background, 8);
+        assert!(sphere > 32, "sphere pixel took {} samples", sphere);
+        assert!(total as usize <= 32 * width * 18 + width * 18);
+        assert!((image[9][0] - scene.config().background).length() < 1e-6);
+    }
+}
@@ -0,0 +1,349 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    film::Film,
+    filter::FilterType,
+    geometries::HitRecord,
+    materials::{Material, Scatter},
+    ray::Ray,
+    utils::{exr::Exr, vec3::Color},
+};
+
+/// Arbitrary output variable rendered alongside the beauty image.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AovType {
+    /// Distance from the camera to the first hit.
+    Depth,
+    /// World space normal at the first hit, facing the camera.
+    Normal,
+    /// Reflectance of the first hit surface, clamped emission of lights and
+    /// the background.
+    Albedo,
+    Position,
+    Uv,
+    /// One-based index of the first hit object, 0 for the background.
+    ObjectId,
+    /// One-based index of the material in the scene's materials, 0 for the
+    /// background and materials the scene does not list.
+    MaterialId,
+    DiffuseDirect,
+    DiffuseIndirect,
+    SpecularDirect,
+    SpecularIndirect,
+    /// Lights and background seen directly by the camera.
+    Emission,
+}
+
+/// How AOV passes are stored next to the beauty image.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AovOutput {
+    /// One `<name>_<pass>.exr` per pass.
+    Files,
+    /// A single `<name>.exr` holding the beauty and one layer per pass.
+    MultiLayerExr,
+}
+
+pub struct AovConfig {
+    pub passes: Vec<AovType>,
+    pub output: AovOutput,
+}
+
+impl AovType {
+    pub fn all() -> Vec<AovType> {
+        vec![
+            AovType::Depth,
+            AovType::Normal,
+            AovType::Albedo,
+            AovType::Position,
+            AovType::Uv,
+            AovType::ObjectId,
+            AovType::MaterialId,
+            AovType::DiffuseDirect,
+            AovType::DiffuseIndirect,
+            AovType::SpecularDirect,
+            AovType::SpecularIndirect,
+            AovType::Emission,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AovType::Depth => "depth",
+            AovType::Normal => "normal",
+            AovType::Albedo => "albedo",
+            AovType::Position => "position",
+            AovType::Uv => "uv",
+            AovType::ObjectId => "object_id",
+            AovType::MaterialId => "material_id",
+            AovType::DiffuseDirect => "diffuse_direct",
+            AovType::DiffuseIndirect => "diffuse_indirect",
+            AovType::SpecularDirect => "specular_direct",
+            AovType::SpecularIndirect => "specular_indirect",
+            AovType::Emission => "emission",
+        }
+    }
+
+    /// EXR channel names of the pass, taken from the x, y and z components.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            AovType::Depth => &["Z"],
+            AovType::Normal | AovType::Position => &["X", "Y", "Z"],
+            AovType::Uv => &["U", "V"],
+            AovType::ObjectId | AovType::MaterialId => &["id"],
+            _ => &["R", "G", "B"],
+        }
+    }
+
+    /// IDs can not be averaged, so they come from the first sample of a pixel.
+    fn first_sample_only(&self) -> bool {
+        matches!(self, AovType::ObjectId | AovType::MaterialId)
+    }
+}
+
+fn clamp(color: Color) -> Color {
+    Color::from(color.x().min(1.), color.y().min(1.), color.z().min(1.))
+}
+
+/// AOV values gathered while tracing one camera path.
+pub struct AovSample {
+    pub depth: f64,
+    pub normal: Color,
+    pub albedo: Color,
+    pub position: Color,
+    pub uv: (f64, f64),
+    pub object: usize,
+    pub material: Option<Arc<dyn Material>>,
+    pub material_id: usize,
+    specular: bool,
+    pub diffuse_direct: Color,
+    pub diffuse_indirect: Color,
+    pub specular_direct: Color,
+    pub specular_indirect: Color,
+    pub emission: Color,
+}
+
+impl AovSample {
+    pub fn new() -> Self {
+        AovSample {
+            depth: 0.,
+            normal: Color::new(),
+            albedo: Color::new(),
+            position: Color::new(),
+            uv: (0., 0.),
+            object: 0,
+            material: None,
+            material_id: 0,
+            specular: false,
+            diffuse_direct: Color::new(),
+            diffuse_indirect: Color::new(),
+            specular_direct: Color::new(),
+            specular_indirect: Color::new(),
+            emission: Color::new(),
+        }
+    }
+
+    /// Records the geometry of the first hit and what its material did.
+    pub fn record_hit(&mut self, ray: &Ray, rec: &HitRecord, scatter: &Scatter) {
+        self.depth = rec.t * ray.dir().length();
+        self.normal = rec.normal;
+        self.position = rec.p;
+        self.uv = rec.uv;
+        self.object = rec.object;
+        self.material = Some(rec.material.clone());
+        self.specular = rec.material.is_specular();
+        self.albedo = match scatter {
+            Scatter::Scattered(attenuation, _) => *attenuation,
+            Scatter::Light(tint) => clamp(*tint),
+            Scatter::Absorbed => Color::new(),
+        };
+        if let Some(wavelengths) = ray.wavelengths() {
+            self.albedo = wavelengths.reflectance_to_rgb(self.albedo);
+        }
+    }
+
+    /// Camera rays that escape take the background as their albedo.
+    pub fn record_miss(&mut self, background: Color) {
+        self.albedo = clamp(background);
+    }
+
+    /// Adds radiance the path gathered after `bounce` scattering events.
+    pub fn record_light(&mut self, bounce: u32, radiance: Color) {
+        match (bounce, self.specular) {
+            (0, _) => self.emission += radiance,
+            (1, false) => self.diffuse_direct += radiance,
+            (1, true) => self.specular_direct += radiance,
+            (_, false) => self.diffuse_indirect += radiance,
+            (_, true) => self.specular_indirect += radiance,
+        }
+    }
+
+    fn value(&self, aov: AovType) -> Color {
+        let id = |id: usize| Color::from(id as f64, id as f64, id as f64);
+        match aov {
+            AovType::Depth => Color::from(self.depth, self.depth, self.depth),
+            AovType::Normal => self.normal,
+            AovType::Albedo => self.albedo,
+            AovType::Position => self.position,
+            AovType::Uv => Color::from(self.uv.0, self.uv.1, 0.),
+            AovType::ObjectId => id(self.object),
+            AovType::MaterialId => id(self.material_id),
+            AovType::DiffuseDirect => self.diffuse_direct,
+            AovType::DiffuseIndirect => self.diffuse_indirect,
+            AovType::SpecularDirect => self.specular_direct,
+            AovType::SpecularIndirect => self.specular_indirect,
+            AovType::Emission => self.emission,
+        }
+    }
+}
+
+/// One box filtered film per requested pass.
+pub struct Aovs {
+    films: Vec<(AovType, Film)>,
+}
+
+impl Aovs {
+    pub fn new(width: usize, height: usize, passes: &[AovType]) -> Self {
+        let films = passes
+            .iter()
+            .map(|&aov| (aov, Film::new(width, height, FilterType::Box.build(0.5))))
+            .collect();
+        Aovs { films }
+    }
+
+    /// Adds sample `index` of the pixel containing `position`.
+    pub fn add_sample(&self, position: (f64, f64), index: u32, sample: &AovSample) {
+        for (aov, film) in &self.films {
+            if index == 0 || !aov.first_sample_only() {
+                film.add_sample(position, sample.value(*aov));
+            }
+        }
+    }
+
+    /// Every pass with rows ordered bottom to top.
+    pub fn images(&self) -> Vec<(AovType, Vec<Vec<Color>>)> {
+        self.films
+            .iter()
+            .map(|(aov, film)| (*aov, film.image()))
+            .collect()
+    }
+}
+
+fn push_pass(exr: &mut Exr, prefix: &str, aov: AovType, image: &[Vec<Color>]) {
+    let components: [fn(&Color) -> f64; 3] = [Color::x, Color::y, Color::z];
+    for (channel, component) in aov.channels().iter().zip(components) {
+        let values = image
+            .iter()
+            .rev()
+            .flat_map(|row| row.iter().map(|p| component(p) as f32))
+            .collect();
+        exr.push_channel(&format!("{}{}", prefix, channel), values);
+    }
+}
+
+/// Writes the passes next to the beauty image of the render called `name`.
+pub fn write(
+    name: &str,
+    beauty: &[Vec<Color>],
+    passes: &[(AovType, Vec<Vec<Color>>)],
+    output: AovOutput,
+) -> std::io::Result<()> {
+    let width = beauty[0].len() as u32;
+    let height = beauty.len() as u32;
+    match output {
+        AovOutput::Files => {
+            for (aov, image) in passes {
+                let mut exr = Exr::from(width, height);
+                push_pass(&mut exr, "", *aov, image);
+                exr.write(&format!("{}_{}.exr", name, aov.name()))?;
+            }
+        }
+        AovOutput::MultiLayerExr => {
+            let mut exr = Exr::from(width, height);
+            exr.push_layer("", beauty);
+            for (aov, image) in passes {
+                push_pass(&mut exr, &format!("{}.", aov.name()), *aov, image);
+            }
+            exr.write(&format!("{}.exr", name))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        camera::Camera,
+        filter::FilterType,
+        geometries::{sphere::Sphere, HittableList},
+        materials::{diffuse::Diffuse, metal::Metal, Material},
+        scene::{Config, Scene},
+        utils::vec3::{Color, Point3},
+    };
+
+    use super::{AovType, Aovs};
+
+    #[test]
+    fn passes_split_beauty() {
+        let diffuse: Arc<dyn Material> = Arc::new(Diffuse::new(0.5, 0.4, 0.3));
+        let metal: Arc<dyn Material> = Arc::new(Metal::new(0.8, 0.8, 0.8, 0.2));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -100.5, -1.),
+            100.,
+            metal.clone(),
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., 0., -1.),
+            0.5,
+            diffuse.clone(),
+        )));
+        let config = Config {
+            height: 16,
+            samples: 8,
+            filter: FilterType::Box,
+            filter_radius: 0.5,
+            ..Config::default()
+        };
+        let scene = Scene::from(Camera::default(), world, vec![diffuse, metal], config);
+
+        let width = scene.config().width();
+        let aovs = Aovs::new(width, 16, &AovType::all());
+        let beauty = scene.render_path_with(Some(&aovs)).unwrap();
+        let passes = aovs.images();
+        let pass = |aov: AovType| &passes.iter().find(|p| p.0 == aov).unwrap().1;
+
+        // the light passes add up to the beauty image
+        for (j, row) in beauty.iter().enumerate() {
+            for (i, color) in row.iter().enumerate() {
+                let sum = [
+                    AovType::Emission,
+                    AovType::DiffuseDirect,
+                    AovType::DiffuseIndirect,
+                    AovType::SpecularDirect,
+                    AovType::SpecularIndirect,
+                ]
+                .iter()
+                .fold(Color::new(), |sum, &aov| sum + pass(aov)[j][i]);
+                assert!((sum - *color).length() < 1e-5);
+            }
+        }
+
+        let center = (8, width / 2);
+        assert_eq!(pass(AovType::ObjectId)[center.0][center.1].x(), 2.);
+        assert_eq!(pass(AovType::MaterialId)[center.0][center.1].x(), 1.);
+        assert_eq!(pass(AovType::ObjectId)[15][0].x(), 0.);
+        assert_eq!(pass(AovType::MaterialId)[0][center.1].x(), 2.);
+        assert!((pass(AovType::Depth)[center.0][center.1].x() - 0.5).abs() < 0.05);
+        assert!(pass(AovType::Normal)[center.0][center.1].z() > 0.9);
+        let albedo = pass(AovType::Albedo)[center.0][center.1];
+        assert!((albedo - Color::from(0.5, 0.4, 0.3)).length() < 1e-5);
+        assert!(pass(AovType::SpecularIndirect)[0][center.1].length() > 0.);
+        assert_eq!(pass(AovType::Emission)[center.0][center.1], Color::new());
+    }
+}
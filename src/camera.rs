@@ -31,7 +31,7 @@ impl Camera {
         let origin = look_from;
         let horizontal = u * viewport_width;
         let vertical = v * viewport_height;
-        let lower_left_corner = origin - horizontal / 2. - vertical / 2. - w;
+        let lower_left_corner = origin - horizontal / 2. - vertical / 2. - w * focal_length;
 
         Camera {
             origin,
@@ -0,0 +1,319 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::{
+    adaptive::PixelStats, description::MAX_PIXELS, film::Film, sampler::SamplerType, scene::Scene,
+};
+
+const MAGIC: &[u8; 8] = b"RTCKPT02";
+
+/// Periodic checkpointing of path traced renders. The image is rendered in
+/// progressive passes and the state after a pass is written to `path`.
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub interval: Duration,
+    /// Continue from the checkpoint at `path` if there is one.
+    pub resume: bool,
+}
+
+impl CheckpointConfig {
+    pub fn default() -> Self {
+        CheckpointConfig {
+            path: PathBuf::from("render.ckpt"),
+            interval: Duration::from_secs(300),
+            resume: false,
+        }
+    }
+}
+
+/// Render state at the end of a progressive pass.
+///
+/// The samplers are counter based, so the seed, the sampler type and the
+/// number of samples taken per pixel fully describe the random state: a
+/// resumed render draws exactly the samples the interrupted one would have.
+/// The other settings and the scene that shape the image are kept as a hash.
+pub struct Checkpoint {
+    pub seed: u64,
+    pub sampler: SamplerType,
+    pub width: usize,
+    pub height: usize,
+    pub fingerprint: u64,
+    pub samples_per_pixel: u32,
+    pub passes: u32,
+    pub film: Vec<[i64; 4]>,
+    pub stats: Vec<(f64, f64, u32)>,
+}
+
+fn sampler_id(sampler: SamplerType) -> u8 {
+    match sampler {
+        SamplerType::Independent => 0,
+        SamplerType::Stratified => 1,
+        SamplerType::Halton => 2,
+        SamplerType::Sobol => 3,
+        SamplerType::BlueNoise => 4,
+    }
+}
+
+fn sampler_from_id(id: u8) -> io::Result<SamplerType> {
+    match id {
+        0 => Ok(SamplerType::Independent),
+        1 => Ok(SamplerType::Stratified),
+        2 => Ok(SamplerType::Halton),
+        3 => Ok(SamplerType::Sobol),
+        4 => Ok(SamplerType::BlueNoise),
+        _ => Err(invalid("unknown sampler")),
+    }
+}
+
+/// FNV-1a hash of `bytes`, which unlike the standard hasher stays the same
+/// between builds.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Hash of the settings and scene that shape the image of a path traced
+/// render, besides those stored on their own.
+fn fingerprint(scene: &Scene) -> u64 {
+    let config = scene.config();
+    let settings = format!(
+        "{:?}",
+        (
+            config.samples,
+            config.depth,
+            config.background,
+            config.filter,
+            config.filter_radius,
+            config.light_sampling,
+            config.spectral,
+            scene.description_hash(),
+        )
+    );
+    hash(settings.as_bytes())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+impl Checkpoint {
+    pub fn capture(
+        scene: &Scene,
+        samples_per_pixel: u32,
+        passes: u32,
+        film: &Film,
+        stats: &[Mutex<PixelStats>],
+    ) -> Self {
+        let config = scene.config();
+        Checkpoint {
+            seed: config.seed,
+            sampler: config.sampler,
+            width: config.width(),
+            height: config.height,
+            fingerprint: fingerprint(scene),
+            samples_per_pixel,
+            passes,
+            film: film.accumulators(),
+            stats: stats.iter().map(|p| p.lock().unwrap().to_raw()).collect(),
+        }
+    }
+
+    /// Checks that the checkpoint was taken from a render of this scene with
+    /// the same settings.
+    pub fn matches(&self, scene: &Scene) -> bool {
+        let config = scene.config();
+        self.seed == config.seed
+            && self.sampler == config.sampler
+            && self.width == config.width()
+            && self.height == config.height
+            && self.fingerprint == fingerprint(scene)
+    }
+
+    /// Writes the checkpoint next to `path` first and then renames it, so a
+    /// crash while writing never destroys the previous checkpoint.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        {
+            let mut w = BufWriter::new(File::create(&tmp)?);
+            w.write_all(MAGIC)?;
+            w.write_all(&self.seed.to_le_bytes())?;
+            w.write_all(&[sampler_id(self.sampler)])?;
+            w.write_all(&(self.width as u64).to_le_bytes())?;
+            w.write_all(&(self.height as u64).to_le_bytes())?;
+            w.write_all(&self.fingerprint.to_le_bytes())?;
+            w.write_all(&self.samples_per_pixel.to_le_bytes())?;
+            w.write_all(&self.passes.to_le_bytes())?;
+            for pixel in &self.film {
+                for sum in pixel {
+                    w.write_all(&sum.to_le_bytes())?;
+                }
+            }
+            for (luminance, luminance_sq, samples) in &self.stats {
+                w.write_all(&luminance.to_le_bytes())?;
+                w.write_all(&luminance_sq.to_le_bytes())?;
+                w.write_all(&samples.to_le_bytes())?;
+            }
+            w.flush()?;
+        }
+        fs::rename(tmp, path)
+    }
+
+    /// Reads a checkpoint of a `width` by `height` image.
+    pub fn read(path: &Path, width: usize, height: usize) -> io::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a checkpoint file"));
+        }
+        let seed = read_u64(&mut r)?;
+        let mut id = [0];
+        r.read_exact(&mut id)?;
+        let sampler = sampler_from_id(id[0])?;
+        // the size is checked before the pixels are allocated
+        if (read_u64(&mut r)?, read_u64(&mut r)?) != (width as u64, height as u64) {
+            return Err(invalid("checkpoint has a different image size"));
+        }
+        let pixels = width
+            .checked_mul(height)
+            .filter(|&pixels| pixels <= MAX_PIXELS)
+            .ok_or_else(|| invalid("image size out of range"))?;
+        let fingerprint = read_u64(&mut r)?;
+        let samples_per_pixel = read_u32(&mut r)?;
+        let passes = read_u32(&mut r)?;
+
+        let mut film = Vec::with_capacity(pixels);
+        for _ in 0..pixels {
+            let mut pixel = [0; 4];
+            for sum in &mut pixel {
+                *sum = read_u64(&mut r)? as i64;
+            }
+            film.push(pixel);
+        }
+        let mut stats = Vec::with_capacity(pixels);
+        for _ in 0..pixels {
+            let luminance = f64::from_bits(read_u64(&mut r)?);
+            let luminance_sq = f64::from_bits(read_u64(&mut r)?);
+            let samples = read_u32(&mut r)?;
+            stats.push((luminance, luminance_sq, samples));
+        }
+
+        Ok(Checkpoint {
+            seed,
+            sampler,
+            width,
+            height,
+            fingerprint,
+            samples_per_pixel,
+            passes,
+            film,
+            stats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        materials::{diffuse::Diffuse, metal::Metal},
+        progressive::{self, ProgressiveConfig},
+        sampler::SamplerType,
+        scene::{Config, Scene},
+        utils::vec3::Point3,
+    };
+
+    use super::Checkpoint;
+
+    fn scene() -> Scene {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -100.5, -1.),
+            100.,
+            Arc::new(Diffuse::new(0.8, 0.8, 0.)),
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., 0., -1.),
+            0.5,
+            Arc::new(Metal::new(0.8, 0.6, 0.2, 0.3)),
+        )));
+        let mut scene = Scene::new();
+        scene.set_world(world);
+        scene.set_config(config());
+        scene
+    }
+
+    fn config() -> Config {
+        Config {
+            height: 9,
+            samples: 8,
+            seed: 3,
+            sampler: SamplerType::Sobol,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn resume_matches_uninterrupted_render() {
+        let params = ProgressiveConfig {
+            samples_per_pass: 2,
+            ..ProgressiveConfig::default()
+        };
+        let path = std::env::temp_dir().join("raytracing_resume_test.ckpt");
+
+        let scene = scene();
+        let uninterrupted = progressive::render(&scene, &params, None, |pass, film, stats| {
+            if pass.index == 1 {
+                let checkpoint =
+                    Checkpoint::capture(&scene, pass.samples_per_pixel, 2, film, stats);
+                checkpoint.write(&path).unwrap();
+            }
+        })
+        .unwrap();
+
+        let (width, height) = (scene.config().width(), scene.config().height);
+        assert!(Checkpoint::read(&path, width + 1, height).is_err());
+        let checkpoint = Checkpoint::read(&path, width, height).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint.samples_per_pixel, 4);
+        assert!(checkpoint.matches(&scene));
+        // a render with other settings or of another scene takes other samples
+        let mut other = self::scene();
+        other.set_config(Config {
+            depth: config().depth + 1,
+            ..config()
+        });
+        assert!(!checkpoint.matches(&other));
+        let mut other = self::scene();
+        other.set_description_hash(1);
+        assert!(!checkpoint.matches(&other));
+
+        let mut passes = vec![];
+        let resumed = progressive::render(&scene, &params, Some(checkpoint), |pass, _, _| {
+            passes.push(pass.index)
+        })
+        .unwrap();
+        assert_eq!(passes, vec![2, 3]);
+        assert_eq!(uninterrupted, resumed);
+    }
+}
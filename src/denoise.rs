@@ -0,0 +1,190 @@
+use rayon::prelude::*;
+
+use crate::utils::vec3::{Color, Vec3};
+
+/// Settings of the feature guided denoiser run on the final image.
+///
+/// Every pixel becomes a weighted average of its neighbours, where neighbours
+/// with a different albedo, normal or (tonemapped) color get less weight, so
+/// noise is smoothed away while edges and textures are kept.
+pub struct DenoiseConfig {
+    /// Half size in pixels of the filter window.
+    pub radius: usize,
+    pub sigma_spatial: f64,
+    pub sigma_albedo: f64,
+    pub sigma_normal: f64,
+    pub sigma_color: f64,
+    /// Samples per pixel of the pass rendering the albedo and normal features.
+    pub feature_samples: u32,
+    /// Also write the image before denoising as `<name>_noisy.ppm`.
+    pub keep_noisy: bool,
+}
+
+impl DenoiseConfig {
+    pub fn default() -> Self {
+        DenoiseConfig {
+            radius: 6,
+            sigma_spatial: 3.,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.3,
+            sigma_color: 0.3,
+            feature_samples: 8,
+            keep_noisy: false,
+        }
+    }
+}
+
+/// Albedo and normal of every pixel, rows ordered bottom to top.
+pub struct Features {
+    pub albedo: Vec<Vec<Color>>,
+    pub normal: Vec<Vec<Vec3>>,
+}
+
+/// Color with the albedo divided out, leaving the lighting to be filtered.
+fn demodulate(color: Color, albedo: Color) -> Color {
+    let channel = |c: f64, a: f64| if a > 1e-2 { c / a } else { c };
+    Color::from(
+        channel(color.x(), albedo.x()),
+        channel(color.y(), albedo.y()),
+        channel(color.z(), albedo.z()),
+    )
+}
+
+fn modulate(irradiance: Color, albedo: Color) -> Color {
+    let channel = |c: f64, a: f64| if a > 1e-2 { c * a } else { c };
+    Color::from(
+        channel(irradiance.x(), albedo.x()),
+        channel(irradiance.y(), albedo.y()),
+        channel(irradiance.z(), albedo.z()),
+    )
+}
+
+/// Gamma 2 mapping used for display, so color differences match what is seen.
+fn tonemap(color: Color) -> Color {
+    Color::from(
+        color.x().max(0.).sqrt(),
+        color.y().max(0.).sqrt(),
+        color.z().max(0.).sqrt(),
+    )
+}
+
+/// Joint bilateral filter of `image` guided by `features`.
+pub fn denoise(
+    image: &[Vec<Color>],
+    features: &Features,
+    params: &DenoiseConfig,
+) -> Vec<Vec<Color>> {
+    let height = image.len();
+    let width = image[0].len();
+    let irradiance: Vec<Vec<Color>> = image
+        .iter()
+        .zip(&features.albedo)
+        .map(|(row, albedo)| {
+            row.iter()
+                .zip(albedo)
+                .map(|(&c, &a)| demodulate(c, a))
+                .collect()
+        })
+        .collect();
+    // the color term compares slightly smoothed pixels, which are less noisy
+    let guide: Vec<Vec<Color>> = (0..height)
+        .map(|j| {
+            (0..width)
+                .map(|i| {
+                    let rows = &irradiance[j.saturating_sub(1)..(j + 2).min(height)];
+                    let window = rows
+                        .iter()
+                        .flat_map(|row| &row[i.saturating_sub(1)..(i + 2).min(width)]);
+                    let n = window.clone().count() as f64;
+                    window.fold(Color::new(), |sum, &c| sum + tonemap(c)) / n
+                })
+                .collect()
+        })
+        .collect();
+
+    let spatial = -0.5 / (params.sigma_spatial * params.sigma_spatial);
+    let albedo_weight = -0.5 / (params.sigma_albedo * params.sigma_albedo);
+    let normal_weight = -0.5 / (params.sigma_normal * params.sigma_normal);
+    let color_weight = -0.5 / (params.sigma_color * params.sigma_color);
+    let r = params.radius;
+
+    (0..height)
+        .into_par_iter()
+        .map(|j| {
+            (0..width)
+                .map(|i| {
+                    let albedo = features.albedo[j][i];
+                    let normal = features.normal[j][i];
+                    let color = guide[j][i];
+                    let mut sum = Color::new();
+                    let mut weights = 0.;
+                    for y in j.saturating_sub(r)..(j + r + 1).min(height) {
+                        for x in i.saturating_sub(r)..(i + r + 1).min(width) {
+                            let dx = x as f64 - i as f64;
+                            let dy = y as f64 - j as f64;
+                            let exponent = spatial * (dx * dx + dy * dy)
+                                + albedo_weight * (features.albedo[y][x] - albedo).length_squared()
+                                + normal_weight * (features.normal[y][x] - normal).length_squared()
+                                + color_weight * (guide[y][x] - color).length_squared();
+                            let w = exponent.exp();
+                            sum += irradiance[y][x] * w;
+                            weights += w;
+                        }
+                    }
+                    modulate(sum / weights, albedo)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::utils::vec3::{Color, Vec3};
+
+    use super::{denoise, DenoiseConfig, Features};
+
+    #[test]
+    fn keeps_albedo_edge() {
+        let (width, height) = (32, 16);
+        let mut rng = StdRng::seed_from_u64(3);
+        let albedo_at = |i: usize| {
+            if i < width / 2 {
+                Color::from(0.8, 0.2, 0.2)
+            } else {
+                Color::from(0.1, 0.1, 0.6)
+            }
+        };
+        let clean: Vec<Vec<Color>> = (0..height)
+            .map(|_| (0..width).map(|i| albedo_at(i) * 0.5).collect())
+            .collect();
+        let noisy: Vec<Vec<Color>> = clean
+            .iter()
+            .map(|row| row.iter().map(|&c| c * (2. * rng.gen::<f64>())).collect())
+            .collect();
+        let features = Features {
+            albedo: (0..height)
+                .map(|_| (0..width).map(albedo_at).collect())
+                .collect(),
+            normal: vec![vec![Vec3::from(0., 0., 1.); width]; height],
+        };
+
+        let denoised = denoise(&noisy, &features, &DenoiseConfig::default());
+        let error = |image: &Vec<Vec<Color>>| {
+            image
+                .iter()
+                .flatten()
+                .zip(clean.iter().flatten())
+                .map(|(a, b)| (*a - *b).length_squared())
+                .sum::<f64>()
+        };
+        assert!(error(&denoised) < error(&noisy) * 0.1);
+        // no color bleeds across the edge
+        for row in &denoised {
+            assert!(row[width / 2 - 1].z() < row[width / 2 - 1].x());
+            assert!(row[width / 2].z() > row[width / 2].x());
+        }
+    }
+}
@@ -0,0 +1,1240 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::Camera,
+    checkpoint,
+    filter::FilterType,
+    geometries::{disc::Disc, mesh::Mesh, quad::Quad, sphere::Sphere, HittableList, Shape},
+    integrators::IntegratorType,
+    lights::{
+        directional::DirectionalLight,
+        environment::EnvironmentMap,
+        ies::{IesProfile, Photometry},
+        point::PointLight,
+        sky::Sky,
+        spot::SpotLight,
+        LightSampling, LightSource,
+    },
+    materials::{
+        conductor::{Conductor, ConductorPreset},
+        dielectric::{Dielectric, Dispersion},
+        diffuse::Diffuse,
+        light::Light,
+        metal::Metal,
+        mirror::Mirror,
+        phase::PhaseFunction,
+        principled::Principled,
+        Material,
+    },
+    media::Medium,
+    sampler::SamplerType,
+    scene::{Config, Scene},
+    textures::ImageTexture,
+    tiles::TileOrder,
+    utils::{
+        color::blackbody_radiance,
+        obj,
+        spectrum::Blackbody,
+        vec3::{Color, Point3, Vec3},
+    },
+};
+
+/// Most pixels of an image, as in a 8192 by 8192 render.
+pub const MAX_PIXELS: usize = 1 << 26;
+/// Most samples per pixel.
+const MAX_SAMPLES: u32 = 1 << 16;
+
+/// Serializable description of a scene, from which a `Scene` is built.
+///
+/// Used to load scenes from JSON and to ship them to other processes.
+/// Missing settings fall back to `Config::default()`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub settings: Settings,
+    pub camera: CameraDescription,
+    pub materials: Vec<MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub models: Vec<ModelDescription>,
+    #[serde(default)]
+    pub media: Vec<MediumDescription>,
+}
+
+/// Wavefront OBJ file, its groups built with the principled material
+/// matching their MTL materials, or with `material` when given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelDescription {
+    pub path: String,
+    #[serde(default)]
+    pub material: Option<usize>,
+}
+
+/// Medium of constant `density` per meter inside the object `boundary`,
+/// which is then not rendered, or without one filling the scene up to
+/// `max_distance` from the origin. `g` is the Henyey–Greenstein asymmetry in
+/// `(-1, 1)`, 0 scattering evenly.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MediumDescription {
+    #[serde(default)]
+    pub boundary: Option<usize>,
+    pub density: f64,
+    #[serde(default = "white")]
+    pub albedo: Color,
+    #[serde(default)]
+    pub g: f64,
+    #[serde(default)]
+    pub max_distance: Option<f64>,
+}
+
+/// The part of `Config` that determines the rendered image.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub name: String,
+    pub height: usize,
+    pub aspect_ratio: f64,
+    pub samples: u32,
+    pub depth: u32,
+    pub background: Color,
+    /// Only local renders use it, the server and workers path trace.
+    pub integrator: IntegratorType,
+    pub sampler: SamplerType,
+    pub seed: u64,
+    pub filter: FilterType,
+    /// Defaults to the radius of the filter.
+    pub filter_radius: Option<f64>,
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Color temperature in Kelvin that appears white.
+    pub white_balance: Option<f64>,
+    pub light_sampling: LightSampling,
+    pub spectral: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub vup: Vec3,
+    pub vertical_fov: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDescription {
+    Diffuse {
+        albedo: Color,
+    },
+    Metal {
+        albedo: Color,
+        fuzz: f64,
+    },
+    /// Glass keeping `color` of white light crossing `distance` meters of
+    /// it. A `dispersion` overrides `refraction`.
+    Dielectric {
+        #[serde(default = "glass_ior")]
+        refraction: f64,
+        #[serde(default)]
+        roughness: f64,
+        #[serde(default)]
+        color: Option<Color>,
+        #[serde(default = "one")]
+        distance: f64,
+        #[serde(default)]
+        dispersion: Option<Dispersion>,
+    },
+    /// Metal given by a `preset` or by the complex index of refraction
+    /// `eta + i k` at the red, green and blue primaries.
+    Conductor {
+        #[serde(default)]
+        preset: Option<ConductorPreset>,
+        #[serde(default)]
+        eta: Option<Color>,
+        #[serde(default)]
+        k: Option<Color>,
+        #[serde(default)]
+        roughness: f64,
+    },
+    Mirror,
+    /// Disney style material layering every other kind, its parameters in
+    /// `[0, 1]` but the index of refraction `ior`.
+    Principled {
+        #[serde(default = "white")]
+        base_color: Color,
+        #[serde(default)]
+        metallic: f64,
+        #[serde(default = "half")]
+        roughness: f64,
+        #[serde(default = "half")]
+        specular: f64,
+        #[serde(default)]
+        specular_tint: f64,
+        #[serde(default)]
+        sheen: f64,
+        #[serde(default = "half")]
+        sheen_tint: f64,
+        #[serde(default)]
+        clearcoat: f64,
+        #[serde(default = "one")]
+        clearcoat_gloss: f64,
+        #[serde(default)]
+        transmission: f64,
+        #[serde(default = "glass_ior")]
+        ior: f64,
+        #[serde(default)]
+        subsurface: f64,
+    },
+    /// Emitter whose radiance is `emission`, or the `.hdr` image `texture`,
+    /// times `intensity`. A `power` in watts replaces the intensity and is
+    /// spread over the surface of every object using the material. An IES
+    /// `profile` shapes the emission around the surface normal.
+    ///
+    /// A `temperature` in Kelvin tints the emission with the color of a
+    /// blackbody, of luminance 1 or, if `absolute`, its physical radiance.
+    /// Image textures are not tinted.
+    Light {
+        #[serde(default = "white")]
+        emission: Color,
+        #[serde(default = "one")]
+        intensity: f64,
+        #[serde(default)]
+        power: Option<f64>,
+        #[serde(default)]
+        two_sided: bool,
+        #[serde(default)]
+        texture: Option<String>,
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        temperature: Option<f64>,
+        #[serde(default)]
+        absolute: bool,
+    },
+}
+
+/// Objects refer to their material by its index in `materials`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDescription {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        material: usize,
+    },
+    /// Parallelogram spanned by the edges `u` and `v` from `corner`.
+    Quad {
+        corner: Point3,
+        u: Vec3,
+        v: Vec3,
+        material: usize,
+    },
+    Disc {
+        center: Point3,
+        normal: Vec3,
+        radius: f64,
+        material: usize,
+    },
+    /// Triangles listed as counter-clockwise indices into `vertices`.
+    Mesh {
+        vertices: Vec<Point3>,
+        triangles: Vec<[usize; 3]>,
+        material: usize,
+    },
+}
+
+/// Light source sampled directly by the integrator. Intensities are in W/sr,
+/// irradiance in W/m² and angles in degrees. A `temperature` in Kelvin tints
+/// the color with that of a blackbody.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightDescription {
+    /// A `profile` aims its nadir along `direction`, and its peak reaches
+    /// `intensity`.
+    Point {
+        position: Point3,
+        #[serde(default = "white")]
+        color: Color,
+        intensity: f64,
+        #[serde(default = "down")]
+        direction: Vec3,
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        temperature: Option<f64>,
+    },
+    Spot {
+        position: Point3,
+        direction: Vec3,
+        #[serde(default = "white")]
+        color: Color,
+        intensity: f64,
+        inner_angle: f64,
+        outer_angle: f64,
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        temperature: Option<f64>,
+    },
+    Directional {
+        direction: Vec3,
+        #[serde(default = "white")]
+        color: Color,
+        irradiance: f64,
+        #[serde(default)]
+        temperature: Option<f64>,
+    },
+}
+
+/// Light surrounding the scene, replacing the background color.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnvironmentDescription {
+    /// Equirectangular Radiance `.hdr` image.
+    Map {
+        path: String,
+        /// Degrees around the y axis.
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "one")]
+        intensity: f64,
+        #[serde(default = "yes")]
+        visible_to_camera: bool,
+    },
+    /// Physical daylight sky with a sun disk in `sun_direction`.
+    Sky {
+        sun_direction: Vec3,
+        #[serde(default = "turbidity")]
+        turbidity: f64,
+        #[serde(default = "ground_albedo")]
+        ground_albedo: Color,
+        #[serde(default = "one")]
+        intensity: f64,
+        #[serde(default = "yes")]
+        visible_to_camera: bool,
+    },
+}
+
+fn down() -> Vec3 {
+    Vec3::from(0., -1., 0.)
+}
+
+fn white() -> Color {
+    Color::from(1., 1., 1.)
+}
+
+fn turbidity() -> f64 {
+    3.
+}
+
+fn ground_albedo() -> Color {
+    Color::from(0.3, 0.3, 0.3)
+}
+
+fn half() -> f64 {
+    0.5
+}
+
+fn glass_ior() -> f64 {
+    1.5
+}
+
+fn one() -> f64 {
+    1.
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let config = Config::default();
+        Settings {
+            name: config.name,
+            height: config.height,
+            aspect_ratio: config.aspect_ratio,
+            samples: config.samples,
+            depth: config.depth,
+            background: config.background,
+            integrator: IntegratorType::Path,
+            sampler: config.sampler,
+            seed: config.seed,
+            filter: config.filter,
+            filter_radius: None,
+            tile_size: config.tile_size,
+            tile_order: config.tile_order,
+            white_balance: config.white_balance,
+            light_sampling: config.light_sampling,
+            spectral: config.spectral,
+        }
+    }
+}
+
+impl Settings {
+    pub fn config(&self) -> Config {
+        Config {
+            name: self.name.clone(),
+            height: self.height,
+            aspect_ratio: self.aspect_ratio,
+            samples: self.samples,
+            depth: self.depth,
+            background: self.background,
+            integrator: self.integrator.build(),
+            sampler: self.sampler,
+            seed: self.seed,
+            filter: self.filter,
+            filter_radius: self
+                .filter_radius
+                .unwrap_or_else(|| self.filter.default_radius()),
+            tile_size: self.tile_size,
+            tile_order: self.tile_order,
+            white_balance: self.white_balance,
+            light_sampling: self.light_sampling,
+            spectral: self.spectral,
+            ..Config::default()
+        }
+    }
+}
+
+impl MaterialDescription {
+    pub fn build(&self) -> Result<Arc<dyn Material>, String> {
+        Ok(match self {
+            MaterialDescription::Diffuse { albedo } => Arc::new(Diffuse::from(*albedo)),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal::from(*albedo, *fuzz)),
+            MaterialDescription::Dielectric {
+                refraction,
+                roughness,
+                color,
+                distance,
+                dispersion,
+            } => {
+                let mut dielectric = Dielectric::rough(*refraction, *roughness);
+                if let Some(color) = color {
+                    dielectric.set_tint(*color, *distance);
+                }
+                if let Some(dispersion) = dispersion {
+                    dielectric.set_dispersion(*dispersion);
+                }
+                Arc::new(dielectric)
+            }
+            MaterialDescription::Conductor {
+                preset,
+                eta,
+                k,
+                roughness,
+            } => {
+                let (eta, k) = match (preset, eta, k) {
+                    (Some(preset), None, None) => preset.ior(),
+                    (None, Some(eta), Some(k)) => (*eta, *k),
+                    _ => {
+                        return Err(String::from(
+                            "a conductor needs either a preset or both eta and k",
+                        ))
+                    }
+                };
+                Arc::new(Conductor::from(eta, k, *roughness))
+            }
+            MaterialDescription::Mirror => Arc::new(Mirror::new()),
+            MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                sheen_tint,
+                clearcoat,
+                clearcoat_gloss,
+                transmission,
+                ior,
+                subsurface,
+            } => Arc::new(Principled {
+                base_color: *base_color,
+                metallic: *metallic,
+                roughness: *roughness,
+                specular: *specular,
+                specular_tint: *specular_tint,
+                sheen: *sheen,
+                sheen_tint: *sheen_tint,
+                clearcoat: *clearcoat,
+                clearcoat_gloss: *clearcoat_gloss,
+                transmission: *transmission,
+                ior: *ior,
+                subsurface: *subsurface,
+            }),
+            MaterialDescription::Light { .. } => Arc::new(self.light(None)?),
+        })
+    }
+
+    /// Emitter of a `Light` description, its power spread over `area`.
+    fn light(&self, area: Option<f64>) -> Result<Light, String> {
+        let MaterialDescription::Light {
+            emission,
+            intensity,
+            power,
+            two_sided,
+            texture,
+            profile,
+            temperature,
+            absolute,
+        } = self
+        else {
+            return Err(String::from("not a light"));
+        };
+        let blackbody = temperature.map(Blackbody::from);
+        let emission = match temperature {
+            Some(kelvin) if *absolute => *emission * blackbody_radiance(*kelvin),
+            _ => tint(*emission, blackbody),
+        };
+        let mut light = match texture {
+            Some(path) => Light::textured(Arc::new(
+                ImageTexture::load(Path::new(path))
+                    .map_err(|e| format!("could not load {}: {}", path, e))?,
+            )),
+            None => Light::from(emission),
+        };
+        light.intensity = *intensity;
+        light.two_sided = *two_sided;
+        if texture.is_none() {
+            light.blackbody = blackbody;
+        }
+        if let Some(path) = profile {
+            light.profile = Some(load_profile(path)?);
+        }
+        if let (Some(power), Some(area)) = (power, area) {
+            light.set_power(*power, area);
+        }
+        Ok(light)
+    }
+}
+
+impl ObjectDescription {
+    pub fn material(&self) -> usize {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::Quad { material, .. }
+            | ObjectDescription::Disc { material, .. }
+            | ObjectDescription::Mesh { material, .. } => *material,
+        }
+    }
+
+    pub fn build(&self, material: Arc<dyn Material>) -> Arc<dyn Shape> {
+        match self {
+            ObjectDescription::Sphere { center, radius, .. } => {
+                Arc::new(Sphere::from(*center, *radius, material))
+            }
+            ObjectDescription::Quad { corner, u, v, .. } => {
+                Arc::new(Quad::from(*corner, *u, *v, material))
+            }
+            ObjectDescription::Disc {
+                center,
+                normal,
+                radius,
+                ..
+            } => Arc::new(Disc::from(*center, *normal, *radius, material)),
+            ObjectDescription::Mesh {
+                vertices,
+                triangles,
+                ..
+            } => Arc::new(Mesh::from(vertices, triangles, material)),
+        }
+    }
+}
+
+fn tint(color: Color, blackbody: Option<Blackbody>) -> Color {
+    blackbody.map_or(color, |blackbody| color * blackbody.rgb())
+}
+
+fn load_profile(path: &str) -> Result<Arc<IesProfile>, String> {
+    IesProfile::load(Path::new(path))
+        .map(Arc::new)
+        .map_err(|e| format!("could not load {}: {}", path, e))
+}
+
+impl LightDescription {
+    pub fn build(&self) -> Result<Arc<dyn LightSource>, String> {
+        Ok(match self {
+            LightDescription::Point {
+                position,
+                color,
+                intensity,
+                direction,
+                profile,
+                temperature,
+            } => {
+                let blackbody = temperature.map(Blackbody::from);
+                let mut light = PointLight::from(*position, tint(*color, blackbody), *intensity);
+                if let Some(path) = profile {
+                    light.set_photometry(Photometry::from(load_profile(path)?, *direction));
+                }
+                if let Some(blackbody) = blackbody {
+                    light.set_blackbody(blackbody);
+                }
+                Arc::new(light)
+            }
+            LightDescription::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                inner_angle,
+                outer_angle,
+                profile,
+                temperature,
+            } => {
+                let blackbody = temperature.map(Blackbody::from);
+                let mut light = SpotLight::from(
+                    *position,
+                    *direction,
+                    tint(*color, blackbody),
+                    *intensity,
+                    *inner_angle,
+                    *outer_angle,
+                );
+                if let Some(path) = profile {
+                    light.set_photometry(Photometry::from(load_profile(path)?, *direction));
+                }
+                if let Some(blackbody) = blackbody {
+                    light.set_blackbody(blackbody);
+                }
+                Arc::new(light)
+            }
+            LightDescription::Directional {
+                direction,
+                color,
+                irradiance,
+                temperature,
+            } => {
+                let blackbody = temperature.map(Blackbody::from);
+                let mut light =
+                    DirectionalLight::from(*direction, tint(*color, blackbody), *irradiance);
+                if let Some(blackbody) = blackbody {
+                    light.set_blackbody(blackbody);
+                }
+                Arc::new(light)
+            }
+        })
+    }
+}
+
+/// Whether every edge of the triangles is shared by exactly two of them.
+fn closed(triangles: &[[usize; 3]]) -> bool {
+    let mut edges = HashMap::new();
+    for &[a, b, c] in triangles {
+        for (p, q) in [(a, b), (b, c), (c, a)] {
+            *edges.entry((p.min(q), p.max(q))).or_insert(0) += 1;
+        }
+    }
+    !edges.is_empty() && edges.values().all(|&count| count == 2)
+}
+
+impl SceneDescription {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let description: SceneDescription =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+        description.validate()?;
+        Ok(description)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Paths of the files loaded when building the scene.
+    pub fn files(&self) -> Vec<&str> {
+        let materials = self.materials.iter().flat_map(|material| match material {
+            MaterialDescription::Light {
+                texture, profile, ..
+            } => vec![texture, profile],
+            _ => vec![],
+        });
+        let lights = self.lights.iter().map(|light| match light {
+            LightDescription::Point { profile, .. } | LightDescription::Spot { profile, .. } => {
+                profile
+            }
+            LightDescription::Directional { .. } => &None,
+        });
+        let environment = match &self.environment {
+            Some(EnvironmentDescription::Map { path, .. }) => Some(path.as_str()),
+            _ => None,
+        };
+        materials
+            .chain(lights)
+            .filter_map(|path| path.as_deref())
+            .chain(environment)
+            .chain(self.models.iter().map(|model| model.path.as_str()))
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let config = self.settings.config();
+        if config.height < 2 || config.width() < 2 {
+            return Err(String::from(
+                "image must be at least 2 pixels high and wide",
+            ));
+        }
+        if config
+            .width()
+            .checked_mul(config.height)
+            .is_none_or(|pixels| pixels > MAX_PIXELS)
+        {
+            return Err(format!("image must have at most {} pixels", MAX_PIXELS));
+        }
+        if config.samples > MAX_SAMPLES {
+            return Err(format!(
+                "images take at most {} samples per pixel",
+                MAX_SAMPLES
+            ));
+        }
+        // blackbodies have no color at or below absolute zero
+        let temperatures = self
+            .materials
+            .iter()
+            .filter_map(|material| match material {
+                MaterialDescription::Light { temperature, .. } => *temperature,
+                _ => None,
+            })
+            .chain(self.lights.iter().filter_map(|light| match light {
+                LightDescription::Point { temperature, .. }
+                | LightDescription::Spot { temperature, .. }
+                | LightDescription::Directional { temperature, .. } => *temperature,
+            }))
+            .chain(self.settings.white_balance);
+        if temperatures
+            .into_iter()
+            .any(|kelvin| kelvin <= 0. || kelvin.is_nan())
+        {
+            return Err(String::from("temperatures must be positive"));
+        }
+        for material in &self.materials {
+            if let MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                sheen_tint,
+                clearcoat,
+                clearcoat_gloss,
+                transmission,
+                ior,
+                subsurface,
+            } = material
+            {
+                let weights = [
+                    metallic,
+                    roughness,
+                    specular,
+                    specular_tint,
+                    sheen,
+                    sheen_tint,
+                    clearcoat,
+                    clearcoat_gloss,
+                    transmission,
+                    subsurface,
+                ];
+                if weights.iter().any(|w| !(0. ..=1.).contains(*w)) {
+                    return Err(String::from("principled parameters must lie within [0, 1]"));
+                }
+                let color = [base_color.x(), base_color.y(), base_color.z()];
+                if color.iter().any(|c| *c < 0. || c.is_nan()) {
+                    return Err(String::from("principled base colors must not be negative"));
+                }
+                if !(*ior > 0. && ior.is_finite()) {
+                    return Err(String::from(
+                        "principled materials need a positive index of refraction",
+                    ));
+                }
+            }
+            if let MaterialDescription::Dielectric {
+                color: Some(_),
+                distance,
+                ..
+            } = material
+            {
+                if !(*distance > 0. && distance.is_finite()) {
+                    return Err(String::from("tinted glass needs a positive distance"));
+                }
+            }
+        }
+        for object in &self.objects {
+            let material = object.material();
+            if material >= self.materials.len() {
+                return Err(format!("unknown material {}", material));
+            }
+            if let ObjectDescription::Mesh {
+                vertices,
+                triangles,
+                ..
+            } = object
+            {
+                if triangles.is_empty() {
+                    return Err(String::from("meshes need at least one triangle"));
+                }
+                if triangles.iter().flatten().any(|&i| i >= vertices.len()) {
+                    return Err(String::from("mesh vertex index out of range"));
+                }
+            }
+        }
+        for model in &self.models {
+            if let Some(material) = model.material.filter(|&m| m >= self.materials.len()) {
+                return Err(format!("unknown material {}", material));
+            }
+        }
+        for medium in &self.media {
+            if let Some(object) = medium.boundary.filter(|&o| o >= self.objects.len()) {
+                return Err(format!("unknown object {}", object));
+            }
+            // rays alternately enter and leave a boundary at every crossing
+            let closed = match medium.boundary.map(|o| &self.objects[o]) {
+                Some(ObjectDescription::Quad { .. } | ObjectDescription::Disc { .. }) => false,
+                Some(ObjectDescription::Mesh { triangles, .. }) => closed(triangles),
+                _ => true,
+            };
+            if !closed {
+                return Err(String::from("media need a closed boundary"));
+            }
+            if !(medium.density > 0. && medium.density.is_finite()) || medium.g.abs() >= 1. {
+                return Err(String::from(
+                    "media need a positive density and an asymmetry within (-1, 1)",
+                ));
+            }
+            match (medium.boundary, medium.max_distance) {
+                (None, Some(d)) if d > 0. && d.is_finite() => {}
+                (Some(_), None) => {}
+                _ => {
+                    return Err(String::from(
+                        "media need either a boundary or a finite max_distance",
+                    ))
+                }
+            }
+        }
+        // profiles, spots and directional lights are aimed along a unit vector
+        if !self.lights.iter().all(|light| match light {
+            LightDescription::Point { direction, .. }
+            | LightDescription::Spot { direction, .. }
+            | LightDescription::Directional { direction, .. } => direction.is_direction(),
+        }) {
+            return Err(String::from("lights need a finite, non-zero direction"));
+        }
+        if let Some(EnvironmentDescription::Sky {
+            sun_direction,
+            turbidity,
+            ..
+        }) = &self.environment
+        {
+            if !sun_direction.is_direction() {
+                return Err(String::from("the sun needs a finite, non-zero direction"));
+            }
+            if !(2. ..=10.).contains(turbidity) {
+                return Err(String::from("turbidity must lie within [2, 10]"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the shape `build` makes of the material at `index`, sampled as
+    /// an area light if that is a light.
+    fn add_shape(
+        &self,
+        scene: &mut Scene,
+        materials: &[Arc<dyn Material>],
+        index: usize,
+        build: impl Fn(Arc<dyn Material>) -> Arc<dyn Shape>,
+    ) -> Result<(), String> {
+        let shape = build(materials[index].clone());
+        match &self.materials[index] {
+            MaterialDescription::Light { power: Some(_), .. } => {
+                let light = self.materials[index].light(Some(shape.area()))?;
+                scene.add_area_light(build(Arc::new(light)));
+            }
+            MaterialDescription::Light { .. } => scene.add_area_light(shape),
+            _ => scene.add_object(shape),
+        }
+        Ok(())
+    }
+
+    /// Builds the scene, loading the files it refers to.
+    pub fn build(&self) -> Result<Scene, String> {
+        let config = self.settings.config();
+        let camera = Camera::new(
+            self.camera.look_from,
+            self.camera.look_at,
+            self.camera.vup,
+            self.camera.vertical_fov,
+            config.aspect_ratio,
+        );
+        let materials = self
+            .materials
+            .iter()
+            .map(|m| m.build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut scene = Scene::from(camera, HittableList::new(), materials.clone(), config);
+        // the settings are compared through the config, the rest through a hash
+        let contents = serde_json::to_string(&(
+            &self.camera,
+            &self.materials,
+            &self.objects,
+            &self.environment,
+            &self.lights,
+            &self.models,
+            &self.media,
+        ))
+        .map_err(|e| e.to_string())?;
+        scene.set_description_hash(checkpoint::hash(contents.as_bytes()));
+        let boundaries: Vec<usize> = self.media.iter().filter_map(|m| m.boundary).collect();
+        for (i, object) in self.objects.iter().enumerate() {
+            if boundaries.contains(&i) {
+                continue;
+            }
+            self.add_shape(&mut scene, &materials, object.material(), |material| {
+                object.build(material)
+            })?;
+        }
+        for model in &self.models {
+            let obj = obj::read(Path::new(&model.path))
+                .map_err(|e| format!("could not load {}: {}", model.path, e))?;
+            for group in &obj.groups {
+                let mesh = |material| -> Arc<dyn Shape> {
+                    Arc::new(Mesh::from(&obj.vertices, &group.triangles, material))
+                };
+                if let Some(index) = model.material {
+                    self.add_shape(&mut scene, &materials, index, mesh)?;
+                    continue;
+                }
+                let mtl = group
+                    .material
+                    .as_deref()
+                    .and_then(|name| obj.material(name));
+                match mtl {
+                    Some(mtl) if mtl.emission.length() > 0. => {
+                        scene.add_area_light(mesh(Arc::new(Light::from(mtl.emission))))
+                    }
+                    Some(mtl) => scene.add_object(mesh(Arc::new(Principled::from_mtl(mtl)))),
+                    None => scene
+                        .add_object(mesh(Arc::new(Principled::from(Color::from(0.8, 0.8, 0.8))))),
+                }
+            }
+        }
+        for light in &self.lights {
+            scene.add_light(light.build()?);
+        }
+        for medium in &self.media {
+            let function = if medium.g == 0. {
+                PhaseFunction::Isotropic
+            } else {
+                PhaseFunction::HenyeyGreenstein(medium.g)
+            };
+            let medium = match medium.boundary {
+                Some(index) => {
+                    let object = &self.objects[index];
+                    let boundary = object.build(materials[object.material()].clone());
+                    Medium::from(boundary, medium.density, medium.albedo, function)
+                }
+                None => Medium::atmosphere(
+                    medium.density,
+                    medium.albedo,
+                    function,
+                    medium.max_distance.unwrap(),
+                ),
+            };
+            scene.add_medium(Arc::new(medium));
+        }
+
+        match &self.environment {
+            Some(EnvironmentDescription::Map {
+                path,
+                rotation,
+                intensity,
+                visible_to_camera,
+            }) => {
+                let mut map = EnvironmentMap::load(Path::new(path))
+                    .map_err(|e| format!("could not load {}: {}", path, e))?;
+                map.rotation = *rotation;
+                map.intensity = *intensity;
+                map.visible_to_camera = *visible_to_camera;
+                scene.set_environment(Arc::new(map));
+            }
+            Some(EnvironmentDescription::Sky {
+                sun_direction,
+                turbidity,
+                ground_albedo,
+                intensity,
+                visible_to_camera,
+            }) => {
+                let mut sky = Sky::new(*sun_direction, *turbidity, *ground_albedo);
+                sky.intensity = *intensity;
+                sky.visible_to_camera = *visible_to_camera;
+                scene.set_environment(Arc::new(sky));
+            }
+            None => {}
+        }
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{integrators::Integrator, lights::LightSampling};
+
+    use super::SceneDescription;
+
+    #[test]
+    fn parses_scene_with_defaults() {
+        let json = r#"{
+            "settings": { "height": 10, "sampler": "sobol" },
+            "camera": {
+                "look_from": [0, 0, 0],
+                "look_at": [0, 0, -1],
+                "vup": [0, 1, 0],
+                "vertical_fov": 90
+            },
+            "materials": [
+                { "type": "diffuse", "albedo": [0.5, 0.5, 0.5] },
+                { "type": "light", "emission": [4, 4, 4] }
+            ],
+            "objects": [
+                { "type": "sphere", "center": [0, -100.5, -1], "radius": 100, "material": 0 },
+                { "type": "sphere", "center": [0, 1, -1], "radius": 0.5, "material": 1 }
+            ]
+        }"#;
+        let description = SceneDescription::from_json(json).unwrap();
+        let scene = description.build().unwrap();
+        assert_eq!(scene.config().height, 10);
+        assert_eq!(scene.config().samples, 40);
+        assert!(matches!(scene.config().integrator, Integrator::Path));
+        let mlt = json.replace(r#""sampler": "sobol""#, r#""integrator": "mlt""#);
+        let scene = SceneDescription::from_json(&mlt).unwrap().build().unwrap();
+        assert!(matches!(scene.config().integrator, Integrator::Mlt(_)));
+
+        let copy = SceneDescription::from_json(&description.to_json()).unwrap();
+        assert_eq!(copy.to_json(), description.to_json());
+
+        let broken = json.replace(r#""material": 1"#, r#""material": 2"#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        for settings in [
+            r#""height": 4, "aspect_ratio": 0.1"#,
+            r#""height": 100000, "aspect_ratio": 1e9"#,
+            r#""height": 10, "samples": 100000000"#,
+        ] {
+            let broken = json.replace(r#""height": 10"#, settings);
+            assert!(
+                SceneDescription::from_json(&broken).is_err(),
+                "{}",
+                settings
+            );
+        }
+
+        let missing = json.replace(
+            r#""objects""#,
+            r#""environment": { "type": "map", "path": "missing.hdr" }, "objects""#,
+        );
+        let description = SceneDescription::from_json(&missing).unwrap();
+        assert_eq!(description.files(), vec!["missing.hdr"]);
+        assert!(description.build().is_err());
+
+        let sky = json.replace(
+            r#""objects""#,
+            r#""environment": { "type": "sky", "sun_direction": [1, 1, 0] }, "objects""#,
+        );
+        let description = SceneDescription::from_json(&sky).unwrap();
+        assert!(description
+            .build()
+            .unwrap()
+            .lighting()
+            .environment
+            .is_some());
+
+        let lit = json.replace(
+            r#""objects""#,
+            r#""lights": [
+                { "type": "point", "position": [0, 2, 0], "intensity": 10 },
+                { "type": "spot", "position": [0, 2, 0], "direction": [0, -1, 0],
+                  "intensity": 10, "inner_angle": 20, "outer_angle": 30 },
+                { "type": "directional", "direction": [0, -1, 0], "irradiance": 2 }
+            ], "objects""#,
+        );
+        let description = SceneDescription::from_json(&lit).unwrap();
+        // the emissive sphere is sampled as an area light
+        assert_eq!(description.build().unwrap().lighting().lights.len(), 4);
+        let pointless = lit.replace(r#""direction": [0, -1, 0]"#, r#""direction": [0, 0, 0]"#);
+        assert!(SceneDescription::from_json(&pointless).is_err());
+        for sun in [
+            r#""sun_direction": [0, 0, 0] }"#,
+            r#""sun_direction": [1, 1, 0], "turbidity": 20 }"#,
+        ] {
+            let broken = sky.replace(r#""sun_direction": [1, 1, 0] }"#, sun);
+            assert!(SceneDescription::from_json(&broken).is_err(), "{}", sun);
+        }
+
+        let profile = format!("{}/assets/ies/downlight.ies", env!("CARGO_MANIFEST_DIR"));
+        let measured = lit.replace(
+            r#""intensity": 10 }"#,
+            &format!(r#""intensity": 10, "profile": "{}" }}"#, profile),
+        );
+        assert!(SceneDescription::from_json(&measured)
+            .unwrap()
+            .build()
+            .is_ok());
+        let missing = lit.replace(
+            r#""intensity": 10 }"#,
+            r#""intensity": 10, "profile": "missing.ies" }"#,
+        );
+        assert!(SceneDescription::from_json(&missing)
+            .unwrap()
+            .build()
+            .is_err());
+
+        let shapes = json
+            .replace(
+                r#"{ "type": "light", "emission": [4, 4, 4] }"#,
+                r#"{ "type": "light", "power": 100, "two_sided": true }"#,
+            )
+            .replace(
+                r#""objects": ["#,
+                r#""objects": [
+                { "type": "quad", "corner": [0, 0, 0], "u": [1, 0, 0], "v": [0, 1, 0], "material": 1 },
+                { "type": "disc", "center": [0, 0, 0], "normal": [0, 1, 0], "radius": 1, "material": 0 },
+                { "type": "mesh", "vertices": [[0, 0, 0], [1, 0, 0], [0, 1, 0]],
+                  "triangles": [[0, 1, 2]], "material": 1 },"#,
+            );
+        let description = SceneDescription::from_json(&shapes).unwrap();
+        assert_eq!(description.build().unwrap().lighting().lights.len(), 3);
+        let broken = shapes.replace("[[0, 1, 2]]", "[[0, 1, 3]]");
+        assert!(SceneDescription::from_json(&broken).is_err());
+        // an empty mesh has no area to sample as a light
+        let broken = shapes.replace("[[0, 1, 2]]", "[]");
+        assert!(SceneDescription::from_json(&broken).is_err());
+
+        let warm = lit
+            .replace(
+                r#""height": 10"#,
+                r#""height": 10, "white_balance": 3200, "light_sampling": "power",
+                  "spectral": true"#,
+            )
+            .replace(
+                r#""emission": [4, 4, 4] }"#,
+                r#""emission": [4, 4, 4], "temperature": 2700, "absolute": true }"#,
+            )
+            .replace(
+                r#""irradiance": 2 }"#,
+                r#""irradiance": 2, "temperature": 5500 }"#,
+            );
+        let description = SceneDescription::from_json(&warm).unwrap();
+        let scene = description.build().unwrap();
+        assert_eq!(scene.config().white_balance, Some(3200.));
+        assert_eq!(scene.config().light_sampling, LightSampling::Power);
+        assert!(scene.config().spectral);
+        for broken in [
+            warm.replace("3200", "0"),
+            warm.replace("2700", "-2700"),
+            warm.replace("5500", "0"),
+        ] {
+            assert!(SceneDescription::from_json(&broken).is_err());
+        }
+
+        let metals = json.replace(
+            r#"{ "type": "diffuse", "albedo": [0.5, 0.5, 0.5] }"#,
+            r#"{ "type": "conductor", "preset": "gold", "roughness": 0.3 },
+                { "type": "conductor", "eta": [0.2, 0.9, 1.1], "k": [3.9, 2.4, 2.1] },
+                { "type": "dielectric", "refraction": 1.5, "roughness": 0.2 },
+                { "type": "dielectric", "color": [0.2, 0.6, 0.9], "distance": 0.1 },
+                { "type": "dielectric", "dispersion": { "type": "sellmeier",
+                  "b": [1.04, 0.232, 1.01], "c": [0.006, 0.02, 103.56] } },
+                { "type": "dielectric", "roughness": 0.1,
+                  "dispersion": { "type": "cauchy", "a": 1.5, "b": 0.004 } }"#,
+        );
+        assert!(SceneDescription::from_json(&metals)
+            .unwrap()
+            .build()
+            .is_ok());
+        let ambiguous = metals.replace(r#""eta": [0.2, 0.9, 1.1], "#, "");
+        assert!(SceneDescription::from_json(&ambiguous)
+            .unwrap()
+            .build()
+            .is_err());
+        let opaque = metals.replace(r#""distance": 0.1"#, r#""distance": 0"#);
+        assert!(SceneDescription::from_json(&opaque).is_err());
+        assert_eq!(scene.lighting().lights.len(), 4);
+
+        let cube = format!("{}/assets/models/cube.obj", env!("CARGO_MANIFEST_DIR"));
+        let models = json
+            .replace(
+                r#"{ "type": "diffuse", "albedo": [0.5, 0.5, 0.5] }"#,
+                r#"{ "type": "principled", "base_color": [0.9, 0.6, 0.2], "metallic": 1,
+                  "roughness": 0.3, "clearcoat": 1, "transmission": 0.5 }"#,
+            )
+            .replace(
+                r#""objects""#,
+                &format!(r#""models": [{{ "path": "{}" }}], "objects""#, cube),
+            );
+        let description = SceneDescription::from_json(&models).unwrap();
+        // the emissive top of the cube joins the emissive sphere
+        assert_eq!(description.build().unwrap().lighting().lights.len(), 2);
+        let lamp = models.replace(r#""path": "#, r#""material": 1, "path": "#);
+        let description = SceneDescription::from_json(&lamp).unwrap();
+        assert_eq!(description.build().unwrap().lighting().lights.len(), 4);
+        let broken = models.replace(r#""path": "#, r#""material": 2, "path": "#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        // lobe weights outside [0, 1] would emit negative radiance
+        let broken = models.replace(r#""clearcoat": 1"#, r#""clearcoat": -1"#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        for ior in ["0", "-1.5"] {
+            let broken = models.replace(r#""clearcoat": 1"#, &format!(r#""ior": {}"#, ior));
+            let error = SceneDescription::from_json(&broken).unwrap_err();
+            assert!(error.contains("index of refraction"), "{}", error);
+        }
+
+        let foggy = json.replace(
+            r#""objects""#,
+            r#""media": [
+                { "boundary": 1, "density": 2, "albedo": [0.8, 0.8, 0.8], "g": 0.6 },
+                { "density": 0.01, "max_distance": 50 }
+            ], "objects""#,
+        );
+        let description = SceneDescription::from_json(&foggy).unwrap();
+        // the emissive sphere bounds the smoke instead of lighting the scene
+        assert!(description.build().unwrap().lighting().lights.is_empty());
+        assert_eq!(description.build().unwrap().lighting().media.len(), 2);
+        let broken = foggy.replace(r#""g": 0.6"#, r#""g": 1"#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        let broken = foggy.replace(r#""boundary": 1"#, r#""boundary": 2"#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        let broken = foggy.replace(r#""density": 2"#, r#""density": 0"#);
+        assert!(SceneDescription::from_json(&broken).is_err());
+        // an atmosphere without an extent would hide the sky
+        let broken = foggy.replace(r#", "max_distance": 50"#, "");
+        assert!(SceneDescription::from_json(&broken).is_err());
+        // open shapes cannot bound a medium
+        for boundary in [0, 1, 2] {
+            let open = shapes.replace(
+                r#""objects""#,
+                &format!(
+                    r#""media": [{{ "boundary": {}, "density": 1 }}], "objects""#,
+                    boundary
+                ),
+            );
+            assert!(SceneDescription::from_json(&open).is_err());
+        }
+        let closed = shapes
+            .replace("[0, 1, 0]],", "[0, 1, 0], [0, 0, 1]],")
+            .replace(
+                "[[0, 1, 2]]",
+                "[[0, 2, 1], [0, 1, 3], [1, 2, 3], [2, 0, 3]]",
+            )
+            .replace(
+                r#""objects""#,
+                r#""media": [{ "boundary": 2, "density": 1 }], "objects""#,
+            );
+        assert!(SceneDescription::from_json(&closed).is_ok());
+    }
+}
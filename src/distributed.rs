@@ -0,0 +1,341 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    description::SceneDescription,
+    film::Film,
+    tiles::{self, Tile},
+    utils::vec3::Color,
+};
+
+// Messages are framed as a kind byte followed by a little endian u64 length.
+const SCENE: u8 = 1;
+const TILE: u8 = 2;
+const RESULT: u8 = 3;
+const SHUTDOWN: u8 = 4;
+
+/// Largest payload accepted, well above any scene or tile result.
+const MAX_FRAME: u64 = 1 << 30;
+
+/// Longest a worker may take to accept a tile or answer it before its tile
+/// goes back to the queue.
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+fn write_frame(stream: &mut TcpStream, kind: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(9 + payload.len());
+    frame.push(kind);
+    frame.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 9];
+    stream.read_exact(&mut header)?;
+    let len = u64::from_le_bytes(header[1..].try_into().unwrap());
+    if len > MAX_FRAME {
+        return Err(invalid("frame too large"));
+    }
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn encode_tile(id: usize, tile: &Tile) -> Vec<u8> {
+    [id, tile.x0, tile.y0, tile.x1, tile.y1]
+        .iter()
+        .flat_map(|v| (*v as u64).to_le_bytes())
+        .collect()
+}
+
+fn decode_tile(bytes: &[u8]) -> io::Result<(usize, Tile)> {
+    if bytes.len() < 40 {
+        return Err(invalid("truncated tile"));
+    }
+    let v: Vec<usize> = bytes[..40]
+        .chunks(8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+        .collect();
+    let tile = Tile {
+        x0: v[1],
+        y0: v[2],
+        x1: v[3],
+        y1: v[4],
+    };
+    Ok((v[0], tile))
+}
+
+/// Tiles waiting to be rendered and the number of tiles merged so far.
+struct Queue {
+    state: Mutex<(VecDeque<usize>, usize)>,
+    changed: Condvar,
+    total: usize,
+}
+
+impl Queue {
+    fn new(total: usize) -> Self {
+        Queue {
+            state: Mutex::new(((0..total).collect(), 0)),
+            changed: Condvar::new(),
+            total,
+        }
+    }
+
+    /// Waits for a pending tile, returning `None` once every tile is done.
+    fn next(&self) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(id) = state.0.pop_front() {
+                return Some(id);
+            }
+            if state.1 == self.total {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn requeue(&self, id: usize) {
+        self.state.lock().unwrap().0.push_front(id);
+        self.changed.notify_all();
+    }
+
+    fn complete(&self) {
+        self.state.lock().unwrap().1 += 1;
+        self.changed.notify_all();
+    }
+
+    fn is_done(&self) -> bool {
+        self.state.lock().unwrap().1 == self.total
+    }
+}
+
+/// Renders `description` on the workers that connect to `listener`.
+///
+/// Every worker receives the scene, then one tile at a time, and answers with
+/// the film sums around that tile, which are merged into the final image. The
+/// sums are fixed point, so the image matches a local render exactly. Tiles
+/// held by a worker that disconnects or stops answering go back to the queue.
+/// Workers may join at any time until the last tile is merged.
+pub fn coordinate(
+    description: &SceneDescription,
+    listener: TcpListener,
+) -> io::Result<Vec<Vec<Color>>> {
+    let scene = description.build().map_err(|e| invalid(&e))?;
+    let config = scene.config();
+    let tiles = tiles::tiles(
+        config.width(),
+        config.height,
+        config.tile_size,
+        config.tile_order,
+    );
+    let film = scene.film();
+    let queue = Queue::new(tiles.len());
+    let json = description.to_json();
+
+    listener.set_nonblocking(true)?;
+    thread::scope(|s| {
+        while !queue.is_done() {
+            match listener.accept() {
+                Ok((stream, addr)) => {
+                    println!("Worker {} connected", addr);
+                    let (tiles, film, queue, json) = (&tiles, &film, &queue, &json);
+                    s.spawn(move || {
+                        if let Err(e) = serve(stream, json, tiles, film, queue) {
+                            println!("Worker {} disconnected: {}", addr, e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    })?;
+    Ok(film.image())
+}
+
+fn serve(
+    mut stream: TcpStream,
+    json: &str,
+    tiles: &[Tile],
+    film: &Film,
+    queue: &Queue,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write_frame(&mut stream, SCENE, json.as_bytes())?;
+    while let Some(id) = queue.next() {
+        let region = match render_remote(&mut stream, id, &tiles[id], film) {
+            Ok(region) => region,
+            Err(e) => {
+                queue.requeue(id);
+                return Err(e);
+            }
+        };
+        film.add_region(&region.0, &region.1);
+        queue.complete();
+    }
+    write_frame(&mut stream, SHUTDOWN, &[])
+}
+
+/// Sends a tile to the worker and waits for its film sums.
+fn render_remote(
+    stream: &mut TcpStream,
+    id: usize,
+    tile: &Tile,
+    film: &Film,
+) -> io::Result<(Tile, Vec<[i64; 4]>)> {
+    write_frame(stream, TILE, &encode_tile(id, tile))?;
+    let (kind, payload) = read_frame(stream)?;
+    let (result_id, region) = decode_tile(&payload)?;
+    if kind != RESULT || result_id != id || region != film.footprint(tile) {
+        return Err(invalid("unexpected result"));
+    }
+    let sums: Vec<[i64; 4]> = payload[40..]
+        .chunks_exact(32)
+        .map(|pixel| {
+            std::array::from_fn(|k| i64::from_le_bytes(pixel[k * 8..k * 8 + 8].try_into().unwrap()))
+        })
+        .collect();
+    if sums.len() != region.pixels() {
+        return Err(invalid("truncated result"));
+    }
+    Ok((region, sums))
+}
+
+/// Connects to a coordinator and renders the tiles it hands out until told
+/// to stop. Rows of a tile are traced in parallel.
+pub fn run_worker(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let mut stream = TcpStream::connect(addr)?;
+    // tiles may not come for a while when the others are running late
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let (kind, payload) = read_frame(&mut stream)?;
+    if kind != SCENE {
+        return Err(invalid("expected a scene"));
+    }
+    let json = String::from_utf8(payload).map_err(|_| invalid("scene is not utf-8"))?;
+    let description = SceneDescription::from_json(&json).map_err(|e| invalid(&e))?;
+    let scene = description.build().map_err(|e| invalid(&e))?;
+    let film = scene.film();
+    let pool = scene.thread_pool().map_err(|e| invalid(&e))?;
+
+    loop {
+        let (kind, payload) = read_frame(&mut stream)?;
+        match kind {
+            TILE => {
+                let (id, tile) = decode_tile(&payload)?;
+                pool.install(|| {
+                    (tile.y0..tile.y1).into_par_iter().for_each(|j| {
+                        let row = Tile {
+                            y0: j,
+                            y1: j + 1,
+                            ..tile
+                        };
+                        scene.render_tile(&row, &film, None);
+                    })
+                });
+                let region = film.footprint(&tile);
+                let mut result = encode_tile(id, &region);
+                for pixel in film.take_region(&region) {
+                    result.extend(pixel.iter().flat_map(|v| v.to_le_bytes()));
+                }
+                write_frame(&mut stream, RESULT, &result)?;
+            }
+            SHUTDOWN => return Ok(()),
+            _ => return Err(invalid("unexpected message")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{ErrorKind, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use crate::description::SceneDescription;
+
+    use super::{coordinate, read_frame, run_worker, TILE};
+
+    fn scene() -> SceneDescription {
+        SceneDescription::from_json(
+            r#"{
+                "settings": { "height": 12, "samples": 3, "seed": 4, "tile_size": 5,
+                              "filter": "mitchell", "sampler": "sobol" },
+                "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1],
+                            "vup": [0, 1, 0], "vertical_fov": 90 },
+                "materials": [
+                    { "type": "diffuse", "albedo": [0.5, 0.5, 0.5] },
+                    { "type": "metal", "albedo": [0.8, 0.6, 0.2], "fuzz": 0.1 }
+                ],
+                "objects": [
+                    { "type": "sphere", "center": [0, -100.5, -1], "radius": 100, "material": 0 },
+                    { "type": "sphere", "center": [0, 0, -1], "radius": 0.5, "material": 1 }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn workers_match_local_render() {
+        let description = scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // a worker that takes a tile and disconnects without answering
+        let (got_tile, tile_received) = std::sync::mpsc::channel();
+        let flaky = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            read_frame(&mut stream).unwrap();
+            let (kind, _) = read_frame(&mut stream).unwrap();
+            assert_eq!(kind, TILE);
+            got_tile.send(()).unwrap();
+        });
+
+        let workers = thread::spawn(move || {
+            tile_received.recv().unwrap();
+            let workers: Vec<_> = (0..2)
+                .map(|_| thread::spawn(move || run_worker(addr).unwrap()))
+                .collect();
+            for worker in workers {
+                worker.join().unwrap();
+            }
+        });
+
+        let image = coordinate(&description, listener).unwrap();
+        flaky.join().unwrap();
+        workers.join().unwrap();
+        assert_eq!(image, description.build().unwrap().render_path().unwrap());
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut receiver, _) = listener.accept().unwrap();
+        let mut header = vec![TILE];
+        header.extend(u64::MAX.to_le_bytes());
+        sender.write_all(&header).unwrap();
+        let error = read_frame(&mut receiver).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
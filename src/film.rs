@@ -0,0 +1,223 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use crate::{
+    filter::Filter,
+    tiles::Tile,
+    utils::vec3::{Color, Vec3},
+};
+
+/// Fractional bits of the fixed point accumulators.
+const FIXED_POINT: f64 = (1u64 << 24) as f64;
+
+/// Framebuffer accumulating filtered samples.
+///
+/// Each sample is splatted with the filter weight into every pixel within
+/// the filter radius. Sums are kept as fixed point atomics, so any number of
+/// threads can splat concurrently and the result does not depend on the order
+/// in which the samples arrive.
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: Box<dyn Filter>,
+    // weighted r, g, b and the weight sum of every pixel
+    pixels: Vec<[AtomicI64; 4]>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Box<dyn Filter>) -> Self {
+        let pixels = (0..width * height)
+            .map(|_| std::array::from_fn(|_| AtomicI64::new(0)))
+            .collect();
+        Film {
+            width,
+            height,
+            filter,
+            pixels,
+        }
+    }
+
+    /// Adds a sample at the continuous film position (x, y), where pixel
+    /// (i, j) covers `[i, i + 1) x [j, j + 1)`. Samples that are not finite
+    /// are dropped.
+    pub fn add_sample(&self, (x, y): (f64, f64), color: Color) {
+        if ![color.x(), color.y(), color.z()]
+            .iter()
+            .all(|v| v.is_finite())
+        {
+            return;
+        }
+        let radius = self.filter.radius();
+        // pixels whose center lies in (x - r, x + r]
+        let x0 = ((x - 0.5 - radius).floor() + 1.).max(0.) as usize;
+        let x1 = (x - 0.5 + radius).floor().min(self.width as f64 - 1.);
+        let y0 = ((y - 0.5 - radius).floor() + 1.).max(0.) as usize;
+        let y1 = (y - 0.5 + radius).floor().min(self.height as f64 - 1.);
+        if x1 < 0. || y1 < 0. {
+            return;
+        }
+
+        for j in y0..=y1 as usize {
+            for i in x0..=x1 as usize {
+                let weight = self.filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+                if weight == 0. {
+                    continue;
+                }
+                let pixel = &self.pixels[j * self.width + i];
+                let values = [color.x(), color.y(), color.z(), 1.];
+                for (sum, value) in pixel.iter().zip(values) {
+                    accumulate(sum, (value * weight * FIXED_POINT).round() as i64);
+                }
+            }
+        }
+    }
+
+    /// Raw fixed point sums of every pixel, for checkpointing.
+    pub fn accumulators(&self) -> Vec<[i64; 4]> {
+        self.pixels
+            .iter()
+            .map(|pixel| pixel.each_ref().map(|v| v.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Replaces the sums of every pixel with ones saved by `accumulators`.
+    pub fn restore(&self, accumulators: &[[i64; 4]]) {
+        for (pixel, saved) in self.pixels.iter().zip(accumulators) {
+            for (sum, value) in pixel.iter().zip(saved) {
+                sum.store(*value, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Pixels that samples taken inside `tile` can splat into.
+    pub fn footprint(&self, tile: &Tile) -> Tile {
+        let margin = (self.filter.radius() + 0.5).ceil() as usize;
+        Tile {
+            x0: tile.x0.saturating_sub(margin),
+            y0: tile.y0.saturating_sub(margin),
+            x1: (tile.x1 + margin).min(self.width),
+            y1: (tile.y1 + margin).min(self.height),
+        }
+    }
+
+    /// Sums of the pixels in `region`, row by row, which are reset to zero.
+    pub fn take_region(&self, region: &Tile) -> Vec<[i64; 4]> {
+        (region.y0..region.y1)
+            .flat_map(|j| (region.x0..region.x1).map(move |i| j * self.width + i))
+            .map(|p| {
+                self.pixels[p]
+                    .each_ref()
+                    .map(|v| v.swap(0, Ordering::Relaxed))
+            })
+            .collect()
+    }
+
+    /// Adds sums taken from the same `region` of another film.
+    pub fn add_region(&self, region: &Tile, sums: &[[i64; 4]]) {
+        let pixels = (region.y0..region.y1)
+            .flat_map(|j| (region.x0..region.x1).map(move |i| j * self.width + i));
+        for (p, values) in pixels.zip(sums) {
+            for (sum, value) in self.pixels[p].iter().zip(values) {
+                accumulate(sum, *value);
+            }
+        }
+    }
+
+    /// Weighted average of every pixel, rows ordered bottom to top.
+    pub fn image(&self) -> Vec<Vec<Color>> {
+        self.pixels
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| {
+                        let [r, g, b, w] = pixel.each_ref().map(|v| v.load(Ordering::Relaxed));
+                        if w <= 0 {
+                            return Vec3::new();
+                        }
+                        Color::from(r as f64, g as f64, b as f64) / w as f64
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Adds `value` to `sum`, saturating instead of wrapping around when very
+/// bright samples overflow it.
+fn accumulate(sum: &AtomicI64, value: i64) {
+    let _ = sum.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+        Some(v.saturating_add(value))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{filter::FilterType, utils::vec3::Color};
+
+    use super::Film;
+
+    #[test]
+    fn box_filter_averages_own_pixel() {
+        let film = Film::new(4, 3, FilterType::Box.build(0.5));
+        film.add_sample((1., 1.), Color::from(1., 0., 0.));
+        film.add_sample((1.999, 1.5), Color::from(0., 0., 1.));
+
+        let image = film.image();
+        assert_eq!(image[1][1], Color::from(0.5, 0., 0.5));
+        assert_eq!(image[1][0], Color::new());
+        assert_eq!(image[1][2], Color::new());
+    }
+
+    #[test]
+    fn tent_filter_spreads_into_neighbours() {
+        let film = Film::new(3, 3, FilterType::Tent.build(1.));
+        film.add_sample((1.2, 1.5), Color::from(1., 1., 1.));
+        film.add_sample((0.3, 1.5), Color::from(0., 0., 0.));
+
+        let image = film.image();
+        assert!((image[1][0].x() - 0.3 / 1.1).abs() < 1e-6);
+        assert_eq!(image[1][1], Color::from(1., 1., 1.));
+        assert_eq!(image[1][2], Color::new());
+        assert_eq!(image[0][1], Color::new());
+    }
+
+    #[test]
+    fn filters_preserve_constant_color() {
+        for filter in [
+            FilterType::Box,
+            FilterType::Tent,
+            FilterType::Gaussian,
+            FilterType::Mitchell,
+            FilterType::Lanczos,
+        ] {
+            let film = Film::new(5, 4, filter.build(filter.default_radius()));
+            for s in 0..400 {
+                let x = (s % 20) as f64 * 0.25 + 0.1;
+                let y = (s / 20) as f64 * 0.2 + 0.05;
+                film.add_sample((x, y), Color::from(0.25, 0.5, 1.));
+            }
+            for pixel in film.image().iter().flatten() {
+                assert!(
+                    (*pixel - Color::from(0.25, 0.5, 1.)).length() < 1e-5,
+                    "{:?}: {}",
+                    filter,
+                    pixel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bright_samples_saturate() {
+        let film = Film::new(1, 1, FilterType::Box.build(0.5));
+        for _ in 0..1000 {
+            film.add_sample((0.5, 0.5), Color::from(1e12, 2e5, 1.));
+        }
+        film.add_sample((0.5, 0.5), Color::from(f64::NAN, f64::INFINITY, 1.));
+
+        let pixel = film.image()[0][0];
+        // the red sum is pinned at its limit rather than wrapped negative
+        assert!(pixel.x() > 1e8, "{}", pixel);
+        assert!((pixel.y() - 2e5).abs() < 1e-3, "{}", pixel);
+        assert_eq!(pixel.z(), 1.);
+    }
+}
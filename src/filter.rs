@@ -0,0 +1,184 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Pixel reconstruction filter, evaluated at an offset from the pixel center
+/// measured in pixels.
+pub trait Filter: Send + Sync {
+    fn radius(&self) -> f64;
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterType {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterType {
+    pub fn build(&self, radius: f64) -> Box<dyn Filter> {
+        match self {
+            FilterType::Box => Box::new(BoxFilter { radius }),
+            FilterType::Tent => Box::new(TentFilter { radius }),
+            FilterType::Gaussian => Box::new(GaussianFilter::new(radius, radius / 3.)),
+            FilterType::Mitchell => Box::new(MitchellFilter::new(radius, 1. / 3., 1. / 3.)),
+            FilterType::Lanczos => Box::new(LanczosFilter { radius }),
+        }
+    }
+
+    /// Radius the filter is usually used with.
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterType::Box => 0.5,
+            FilterType::Tent => 1.,
+            FilterType::Gaussian => 1.5,
+            FilterType::Mitchell => 2.,
+            FilterType::Lanczos => 3.,
+        }
+    }
+}
+
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.
+        } else {
+            0.
+        }
+    }
+}
+
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        (self.radius - x.abs()).max(0.) * (self.radius - y.abs()).max(0.)
+    }
+}
+
+/// Gaussian shifted down so it reaches zero at the radius.
+pub struct GaussianFilter {
+    radius: f64,
+    sigma: f64,
+    edge: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        let edge = gaussian(radius, sigma);
+        GaussianFilter {
+            radius,
+            sigma,
+            edge,
+        }
+    }
+}
+
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2. * sigma * sigma)).exp()
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let gx = (gaussian(x, self.sigma) - self.edge).max(0.);
+        let gy = (gaussian(y, self.sigma) - self.edge).max(0.);
+        gx * gy
+    }
+}
+
+/// Mitchell-Netravali cubic with the B and C parameters of the paper.
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> Self {
+        MitchellFilter { radius, b, c }
+    }
+
+    fn mitchell_1d(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = (2. * x / self.radius).abs();
+        if x > 2. {
+            0.
+        } else if x > 1. {
+            ((-b - 6. * c) * x * x * x
+                + (6. * b + 30. * c) * x * x
+                + (-12. * b - 48. * c) * x
+                + (8. * b + 24. * c))
+                / 6.
+        } else {
+            ((12. - 9. * b - 6. * c) * x * x * x
+                + (-18. + 12. * b + 6. * c) * x * x
+                + (6. - 2. * b))
+                / 6.
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell_1d(x) * self.mitchell_1d(y)
+    }
+}
+
+/// Sinc windowed by a sinc stretched over the filter radius.
+pub struct LanczosFilter {
+    radius: f64,
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl LanczosFilter {
+    fn lanczos_1d(&self, x: f64) -> f64 {
+        if x.abs() > self.radius {
+            0.
+        } else {
+            sinc(x) * sinc(x / self.radius)
+        }
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.lanczos_1d(x) * self.lanczos_1d(y)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::utils::vec3::{Point3, Vec3};
+
+/// Axis aligned box spanning `min` to `max`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+fn min(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::from(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()))
+}
+
+fn max(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::from(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()))
+}
+
+impl Bounds {
+    /// Smallest box holding both points.
+    pub fn from(a: Point3, b: Point3) -> Self {
+        Bounds {
+            min: min(a, b),
+            max: max(a, b),
+        }
+    }
+
+    pub fn point(p: Point3) -> Self {
+        Bounds { min: p, max: p }
+    }
+
+    /// Smallest box holding every point, `None` without points.
+    pub fn around(points: impl IntoIterator<Item = Point3>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(Bounds::point)
+            .reduce(|a, b| a.union(&b))
+    }
+
+    pub fn union(&self, other: &Bounds) -> Self {
+        Bounds {
+            min: min(self.min, other.min),
+            max: max(self.max, other.max),
+        }
+    }
+
+    pub fn center(&self) -> Point3 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2. * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Index of the longest axis.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Position of `p` relative to the box, 0 at `min` and 1 at `max` along
+    /// every axis of non-zero extent.
+    pub fn offset(&self, p: Point3) -> Vec3 {
+        let d = self.diagonal();
+        let o = p - self.min;
+        let relative = |o: f64, d: f64| if d > 0. { o / d } else { 0. };
+        Vec3::from(
+            relative(o.x(), d.x()),
+            relative(o.y(), d.y()),
+            relative(o.z(), d.z()),
+        )
+    }
+
+    pub fn contains(&self, p: Point3) -> bool {
+        p.x() >= self.min.x()
+            && p.x() <= self.max.x()
+            && p.y() >= self.min.y()
+            && p.y() <= self.max.y()
+            && p.z() >= self.min.z()
+            && p.z() <= self.max.z()
+    }
+}
+
+/// Component `axis` of `v`.
+pub fn axis(v: Vec3, axis: usize) -> f64 {
+    match axis {
+        0 => v.x(),
+        1 => v.y(),
+        _ => v.z(),
+    }
+}
@@ -0,0 +1,81 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use super::{bounds::Bounds, HitRecord, HitType, Hittable, Material, Point3, Ray, Shape, Vec3};
+
+/// Flat disc facing `normal`. Its u coordinate is the distance from the
+/// center relative to the radius and v the angle around the normal.
+pub struct Disc {
+    center: Point3,
+    normal: Vec3,
+    radius: f64,
+    /// Tangents spanning the plane of the disc.
+    s: Vec3,
+    t: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Disc {
+    pub fn from(center: Point3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
+        let normal = normal.unit_vector();
+        let (s, t) = normal.orthonormal_basis();
+        Disc {
+            center,
+            normal,
+            radius,
+            s,
+            t,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disc {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitType {
+        let denom = self.normal.dot(&ray.dir());
+        if denom.abs() < 1e-12 {
+            return HitType::NoHit;
+        }
+        let t = self.normal.dot(&(self.center - ray.origin())) / denom;
+        if t < t_min || t_max < t {
+            return HitType::NoHit;
+        }
+        let p = ray.at(t);
+        let offset = p - self.center;
+        let distance = offset.length();
+        if distance > self.radius {
+            return HitType::NoHit;
+        }
+        let phi = offset.dot(&self.t).atan2(offset.dot(&self.s));
+        let mut rec = HitRecord::from(ray, t, p, self.normal, self.material.clone());
+        rec.uv = (distance / self.radius, phi.rem_euclid(2. * PI) / (2. * PI));
+        HitType::Hit(rec)
+    }
+}
+
+impl Shape for Disc {
+    fn material(&self) -> Arc<dyn Material> {
+        self.material.clone()
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn bounds(&self) -> Bounds {
+        let n = self.normal;
+        let extent = |c: f64| self.radius * (1. - c * c).max(0.).sqrt();
+        let e = Vec3::from(extent(n.x()), extent(n.y()), extent(n.z()));
+        Bounds::from(self.center - e, self.center + e)
+    }
+
+    fn flat_normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn sample_area(&self, (a, b): (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+        let r = a.sqrt();
+        let phi = 2. * PI * b;
+        let p = self.center + (self.s * phi.cos() + self.t * phi.sin()) * (r * self.radius);
+        (p, self.normal, (r, b))
+    }
+}
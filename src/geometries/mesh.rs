@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::utils::distribution::Distribution1D;
+
+use super::{
+    bounds::Bounds, triangle::Triangle, HitType, Hittable, Material, Point3, Ray, Shape, Vec3,
+};
+
+/// Triangles sharing a material, sampled as a single surface.
+pub struct Mesh {
+    triangles: Vec<Triangle>,
+    /// Chooses triangles proportionally to their area.
+    distribution: Distribution1D,
+    area: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Mesh {
+    /// Mesh of the triangles listed as indices into `vertices`.
+    pub fn from(vertices: &[Point3], indices: &[[usize; 3]], material: Arc<dyn Material>) -> Self {
+        let triangles: Vec<Triangle> = indices
+            .iter()
+            .map(|[a, b, c]| {
+                Triangle::from(vertices[*a], vertices[*b], vertices[*c], material.clone())
+            })
+            .collect();
+        let areas: Vec<f64> = triangles.iter().map(|t| t.area()).collect();
+        Mesh {
+            distribution: Distribution1D::new(&areas),
+            area: areas.iter().sum(),
+            triangles,
+            material,
+        }
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitType {
+        let mut closest = t_max;
+        let mut rec = HitType::NoHit;
+        for triangle in &self.triangles {
+            if let HitType::Hit(tmp) = triangle.hit(ray, t_min, closest) {
+                closest = tmp.t;
+                rec = HitType::Hit(tmp);
+            }
+        }
+        rec
+    }
+}
+
+impl Shape for Mesh {
+    fn material(&self) -> Arc<dyn Material> {
+        self.material.clone()
+    }
+
+    fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.triangles
+            .iter()
+            .map(|t| t.bounds())
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Bounds::point(Point3::new()))
+    }
+
+    fn sample_area(&self, (a, b): (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+        let (x, _, index) = self.distribution.sample_continuous(a);
+        // reuse the position of `a` inside the chosen segment
+        let a = (x * self.triangles.len() as f64 - index as f64).clamp(0., 1.);
+        self.triangles[index].sample_area((a, b))
+    }
+}
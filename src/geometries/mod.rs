@@ -4,7 +4,14 @@ use crate::materials::Material;
 use crate::ray::Ray;
 use crate::utils::vec3::{Point3, Vec3};
 
+use self::bounds::Bounds;
+
+pub mod bounds;
+pub mod disc;
+pub mod mesh;
+pub mod quad;
 pub mod sphere;
+pub mod triangle;
 
 pub enum HitType {
     NoHit,
@@ -17,6 +24,10 @@ pub struct HitRecord {
     pub material: Arc<dyn Material>,
     pub t: f64,
     pub front_face: bool,
+    /// Surface parameterisation at `p`, both in `[0, 1]`.
+    pub uv: (f64, f64),
+    /// One-based index of the hit object in the outermost `HittableList`.
+    pub object: usize,
 }
 
 impl HitRecord {
@@ -40,6 +51,8 @@ impl HitRecord {
             t,
             front_face,
             material,
+            uv: (0., 0.),
+            object: 0,
         }
     }
 }
@@ -48,6 +61,65 @@ pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitType;
 }
 
+/// Point on a shape chosen by `Shape::sample`.
+pub struct ShapeSample {
+    pub p: Point3,
+    pub normal: Vec3,
+    pub uv: (f64, f64),
+    /// Solid angle density as seen from the point the shape was sampled for.
+    pub pdf: f64,
+}
+
+/// Surface that can be sampled, which lets it act as an area light.
+pub trait Shape: Hittable {
+    fn material(&self) -> Arc<dyn Material>;
+
+    fn area(&self) -> f64;
+
+    fn bounds(&self) -> Bounds;
+
+    /// Outward normal shared by the whole surface, for flat shapes.
+    fn flat_normal(&self) -> Option<Vec3> {
+        None
+    }
+
+    /// Uniformly distributed point on the surface with its outward normal
+    /// and surface parameterisation.
+    fn sample_area(&self, u: (f64, f64)) -> (Point3, Vec3, (f64, f64));
+
+    /// Samples a point on the surface seen from `p`.
+    fn sample(&self, p: Point3, u: (f64, f64)) -> Option<ShapeSample> {
+        let (point, normal, uv) = self.sample_area(u);
+        let pdf = self.pdf(p, point, normal);
+        if pdf == 0. || !pdf.is_finite() {
+            return None;
+        }
+        Some(ShapeSample {
+            p: point,
+            normal,
+            uv,
+            pdf,
+        })
+    }
+
+    /// Solid angle density of `sample` choosing `point` with `normal` as
+    /// seen from `p`.
+    fn pdf(&self, p: Point3, point: Point3, normal: Vec3) -> f64 {
+        area_to_solid_angle(p, point, normal, 1. / self.area())
+    }
+}
+
+/// Converts a density per unit area at `point` to one per solid angle at `p`.
+pub fn area_to_solid_angle(p: Point3, point: Point3, normal: Vec3, pdf: f64) -> f64 {
+    let to_point = point - p;
+    let distance_squared = to_point.length_squared();
+    let cos = normal.dot(&to_point).abs() / distance_squared.sqrt();
+    if cos == 0. {
+        return 0.;
+    }
+    pdf * distance_squared / cos
+}
+
 #[derive(Clone)]
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
@@ -81,12 +153,105 @@ impl Hittable for HittableList {
         let mut closest = t_max;
         let mut rec = HitType::NoHit;
 
-        for obj in &self.objects {
-            if let HitType::Hit(tmp) = obj.hit(ray, t_min, closest) {
+        for (index, obj) in self.objects.iter().enumerate() {
+            if let HitType::Hit(mut tmp) = obj.hit(ray, t_min, closest) {
                 closest = tmp.t;
+                tmp.object = index + 1;
                 rec = HitType::Hit(tmp);
             }
         }
         rec
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use crate::{
+        materials::{diffuse::Diffuse, Material},
+        ray::Ray,
+        utils::vec3::{Point3, Vec3},
+    };
+
+    use super::{
+        disc::Disc, mesh::Mesh, quad::Quad, sphere::Sphere, triangle::Triangle, HitType, Shape,
+    };
+
+    /// Solid angle of `shape` seen from `p`, once by sampling the shape and
+    /// once by counting the uniformly distributed directions hitting it.
+    fn solid_angles(shape: &dyn Shape, p: Point3) -> (f64, f64) {
+        let n = 400;
+        let (mut sampled, mut hits) = (0., 0);
+        for s in 0..n * n {
+            let u = ((s % n) as f64 + 0.5) / n as f64;
+            let v = ((s / n) as f64 + 0.5) / n as f64;
+            let sample = shape.sample(p, (u, v)).unwrap();
+            assert!((shape.pdf(p, sample.p, sample.normal) - sample.pdf).abs() < 1e-9 * sample.pdf);
+            sampled += 1. / sample.pdf;
+            let ray = Ray::from(p, Vec3::unit_from_sample(u, v));
+            if let HitType::Hit(_) = shape.hit(&ray, 1e-9, f64::INFINITY) {
+                hits += 1;
+            }
+        }
+        (
+            sampled / (n * n) as f64,
+            4. * PI * hits as f64 / (n * n) as f64,
+        )
+    }
+
+    #[test]
+    fn sampling_covers_solid_angle() {
+        let material: Arc<dyn Material> = Arc::new(Diffuse::new(0.5, 0.5, 0.5));
+        let p = Point3::from(0.2, 0.1, 0.);
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::from(
+                Point3::from(0., 0., -2.),
+                1.,
+                material.clone(),
+            )),
+            Box::new(Quad::from(
+                Point3::from(-1., -1., -1.5),
+                Vec3::from(2., 0., 0.),
+                Vec3::from(0., 1.5, -0.5),
+                material.clone(),
+            )),
+            Box::new(Disc::from(
+                Point3::from(0., 1., -1.),
+                Vec3::from(0.3, -1., 0.5),
+                1.2,
+                material.clone(),
+            )),
+            Box::new(Triangle::from(
+                Point3::from(-1., 0., -1.),
+                Point3::from(1., -0.5, -1.5),
+                Point3::from(0., 1., -1.2),
+                material.clone(),
+            )),
+            Box::new(Mesh::from(
+                &[
+                    Point3::from(-1., -1., -1.),
+                    Point3::from(1., -1., -1.),
+                    Point3::from(1., 1., -2.),
+                    Point3::from(-1., 1., -1.),
+                ],
+                &[[0, 1, 2], [0, 2, 3]],
+                material.clone(),
+            )),
+        ];
+        for shape in &shapes {
+            let (sampled, counted) = solid_angles(shape.as_ref(), p);
+            assert!(
+                (sampled - counted).abs() < 0.01 * counted,
+                "{} vs {}",
+                sampled,
+                counted
+            );
+        }
+
+        // from inside a sphere every direction hits it
+        let sphere = Sphere::from(Point3::from(0., 0., 0.5), 1., material);
+        let (sampled, _) = solid_angles(&sphere, p);
+        assert!((sampled - 4. * PI).abs() < 0.01 * 4. * PI, "{}", sampled);
+    }
+}
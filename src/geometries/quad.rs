@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use super::{bounds::Bounds, HitRecord, HitType, Hittable, Material, Point3, Ray, Shape, Vec3};
+
+/// Parallelogram spanned by the edges `u` and `v` from the corner `q`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    /// Maps points in the plane to their (u, v) coordinates.
+    w: Vec3,
+    area: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn from(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(&v);
+        Quad {
+            q,
+            u,
+            v,
+            normal: n.unit_vector(),
+            w: n / n.length_squared(),
+            area: n.length(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitType {
+        let denom = self.normal.dot(&ray.dir());
+        if denom.abs() < 1e-12 {
+            return HitType::NoHit;
+        }
+        let t = self.normal.dot(&(self.q - ray.origin())) / denom;
+        if t < t_min || t_max < t {
+            return HitType::NoHit;
+        }
+        let p = ray.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(&planar.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar));
+        if !(0. ..=1.).contains(&alpha) || !(0. ..=1.).contains(&beta) {
+            return HitType::NoHit;
+        }
+        let mut rec = HitRecord::from(ray, t, p, self.normal, self.material.clone());
+        rec.uv = (alpha, beta);
+        HitType::Hit(rec)
+    }
+}
+
+impl Shape for Quad {
+    fn material(&self) -> Arc<dyn Material> {
+        self.material.clone()
+    }
+
+    fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::from(self.q, self.q + self.u + self.v)
+            .union(&Bounds::from(self.q + self.u, self.q + self.v))
+    }
+
+    fn flat_normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn sample_area(&self, (a, b): (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+        (self.q + self.u * a + self.v * b, self.normal, (a, b))
+    }
+}
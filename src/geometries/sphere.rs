@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
+use super::area_to_solid_angle;
+use super::bounds::Bounds;
 use super::HitRecord;
 use super::HitType;
 use super::Hittable;
 use super::Material;
 use super::Point3;
 use super::Ray;
+use super::Shape;
+use super::ShapeSample;
+use super::Vec3;
 
 pub struct Sphere {
     center: Point3,
@@ -22,13 +27,33 @@ impl Sphere {
         }
     }
 
+    #[allow(dead_code)]
     pub fn center(&self) -> Point3 {
         self.center
     }
 
+    #[allow(dead_code)]
     pub fn radius(&self) -> f64 {
         self.radius
     }
+
+    /// Latitude and longitude, u going around the y axis from -x.
+    fn uv(outward_normal: Vec3) -> (f64, f64) {
+        let theta = (-outward_normal.y()).clamp(-1., 1.).acos();
+        let phi = (-outward_normal.z()).atan2(outward_normal.x()) + PI;
+        (phi / (2. * PI), theta / PI)
+    }
+
+    /// Cosine of the half angle of the cone the sphere covers seen from `p`,
+    /// `None` from inside.
+    fn cos_cone(&self, p: Point3) -> Option<f64> {
+        let distance_squared = (self.center - p).length_squared();
+        let r2 = self.radius * self.radius;
+        if distance_squared <= r2 {
+            return None;
+        }
+        Some((1. - r2 / distance_squared).max(0.).sqrt())
+    }
 }
 
 impl Hittable for Sphere {
@@ -55,8 +80,75 @@ impl Hittable for Sphere {
         let t = root;
         let p = ray.at(t);
         let outward_normal = (p - self.center) / self.radius;
-        let rec = HitRecord::from(ray, t, p, outward_normal, self.material.clone());
+        let mut rec = HitRecord::from(ray, t, p, outward_normal, self.material.clone());
+        rec.uv = Self::uv(outward_normal);
 
         HitType::Hit(rec)
     }
 }
+
+/// Seen from outside, points are sampled uniformly in the cone of directions
+/// towards the sphere, and uniformly over its area from inside.
+impl Shape for Sphere {
+    fn material(&self) -> Arc<dyn Material> {
+        self.material.clone()
+    }
+
+    fn area(&self) -> f64 {
+        4. * PI * self.radius * self.radius
+    }
+
+    fn bounds(&self) -> Bounds {
+        let r = Vec3::from(self.radius, self.radius, self.radius);
+        Bounds::from(self.center - r, self.center + r)
+    }
+
+    fn sample_area(&self, (a, b): (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+        let normal = Vec3::unit_from_sample(a, b);
+        (self.center + normal * self.radius, normal, Self::uv(normal))
+    }
+
+    fn sample(&self, p: Point3, (a, b): (f64, f64)) -> Option<ShapeSample> {
+        let Some(cos_max) = self.cos_cone(p) else {
+            let (point, normal, uv) = self.sample_area((a, b));
+            let pdf = area_to_solid_angle(p, point, normal, 1. / self.area());
+            return (pdf > 0. && pdf.is_finite()).then_some(ShapeSample {
+                p: point,
+                normal,
+                uv,
+                pdf,
+            });
+        };
+        let to_center = self.center - p;
+        let distance = to_center.length();
+        let w = to_center / distance;
+        let (s, t) = w.orthonormal_basis();
+
+        let cos_theta = 1. - a * (1. - cos_max);
+        let sin_theta_squared = 1. - cos_theta * cos_theta;
+        // distance along the sampled direction to the near side of the sphere
+        let ds = distance * cos_theta
+            - (self.radius * self.radius - distance * distance * sin_theta_squared)
+                .max(0.)
+                .sqrt();
+        let cos_alpha = ((distance * distance + self.radius * self.radius - ds * ds)
+            / (2. * distance * self.radius))
+            .clamp(-1., 1.);
+        let sin_alpha = (1. - cos_alpha * cos_alpha).max(0.).sqrt();
+        let phi = 2. * PI * b;
+        let normal = -(s * (sin_alpha * phi.cos()) + t * (sin_alpha * phi.sin()) + w * cos_alpha);
+        Some(ShapeSample {
+            p: self.center + normal * self.radius,
+            normal,
+            uv: Self::uv(normal),
+            pdf: 1. / (2. * PI * (1. - cos_max)),
+        })
+    }
+
+    fn pdf(&self, p: Point3, point: Point3, normal: Vec3) -> f64 {
+        match self.cos_cone(p) {
+            Some(cos_max) => 1. / (2. * PI * (1. - cos_max)),
+            None => area_to_solid_angle(p, point, normal, 1. / self.area()),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use super::{bounds::Bounds, HitRecord, HitType, Hittable, Material, Point3, Ray, Shape, Vec3};
+
+/// Triangle with counter-clockwise vertices around its normal. Its uv are the
+/// barycentric weights of the second and third vertex.
+pub struct Triangle {
+    a: Point3,
+    ab: Vec3,
+    ac: Vec3,
+    normal: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn from(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Self {
+        let (ab, ac) = (b - a, c - a);
+        Triangle {
+            a,
+            ab,
+            ac,
+            normal: ab.cross(&ac).unit_vector(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> HitType {
+        // Möller–Trumbore
+        let pvec = ray.dir().cross(&self.ac);
+        let det = self.ab.dot(&pvec);
+        if det.abs() < 1e-12 {
+            return HitType::NoHit;
+        }
+        let inv_det = 1. / det;
+        let tvec = ray.origin() - self.a;
+        let b1 = tvec.dot(&pvec) * inv_det;
+        if !(0. ..=1.).contains(&b1) {
+            return HitType::NoHit;
+        }
+        let qvec = tvec.cross(&self.ab);
+        let b2 = ray.dir().dot(&qvec) * inv_det;
+        if b2 < 0. || b1 + b2 > 1. {
+            return HitType::NoHit;
+        }
+        let t = self.ac.dot(&qvec) * inv_det;
+        if t < t_min || t_max < t {
+            return HitType::NoHit;
+        }
+        let mut rec = HitRecord::from(ray, t, ray.at(t), self.normal, self.material.clone());
+        rec.uv = (b1, b2);
+        HitType::Hit(rec)
+    }
+}
+
+impl Shape for Triangle {
+    fn material(&self) -> Arc<dyn Material> {
+        self.material.clone()
+    }
+
+    fn area(&self) -> f64 {
+        self.ab.cross(&self.ac).length() / 2.
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::from(self.a, self.a + self.ab).union(&Bounds::point(self.a + self.ac))
+    }
+
+    fn flat_normal(&self) -> Option<Vec3> {
+        Some(self.normal)
+    }
+
+    fn sample_area(&self, (a, b): (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+        let s = a.sqrt();
+        let (b1, b2) = (s * (1. - b), s * b);
+        (self.a + self.ab * b1 + self.ac * b2, self.normal, (b1, b2))
+    }
+}
@@ -0,0 +1,375 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{
+    camera::Camera,
+    geometries::Hittable,
+    lights::Lighting,
+    progress::CancellationToken,
+    sampler::{stream_seed, Sampler},
+    scene::Config,
+    utils::vec3::{Color, Vec3},
+};
+
+/// Parameters of the primary sample space Metropolis light transport integrator
+/// (Kelemen et al. 2002).
+pub struct MltConfig {
+    pub bootstrap_samples: usize,
+    pub chains: usize,
+    pub mutations_per_pixel: u32,
+    pub large_step_probability: f64,
+    pub sigma: f64,
+}
+
+impl MltConfig {
+    pub fn default() -> Self {
+        MltConfig {
+            bootstrap_samples: 100_000,
+            chains: 1000,
+            mutations_per_pixel: 100,
+            large_step_probability: 0.3,
+            sigma: 0.01,
+        }
+    }
+}
+
+struct PrimarySample {
+    value: f64,
+    last_modification: u64,
+    value_backup: f64,
+    modify_backup: u64,
+}
+
+/// Sampler whose values live in the primary sample space and are lazily
+/// mutated with either a large step (fresh uniform value) or a small step
+/// (gaussian perturbation) at the start of every iteration.
+pub struct MltSampler {
+    rng: StdRng,
+    sigma: f64,
+    large_step_probability: f64,
+    x: Vec<PrimarySample>,
+    current_iteration: u64,
+    large_step: bool,
+    last_large_step_iteration: u64,
+    sample_index: usize,
+}
+
+impl MltSampler {
+    pub fn new(seed: u64, sigma: f64, large_step_probability: f64) -> Self {
+        MltSampler {
+            rng: StdRng::seed_from_u64(seed),
+            sigma,
+            large_step_probability,
+            x: vec![],
+            current_iteration: 0,
+            large_step: true,
+            last_large_step_iteration: 0,
+            sample_index: 0,
+        }
+    }
+
+    pub fn start_iteration(&mut self) {
+        self.current_iteration += 1;
+        self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+        self.sample_index = 0;
+    }
+
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step_iteration = self.current_iteration;
+        }
+    }
+
+    pub fn reject(&mut self) {
+        for xi in &mut self.x {
+            if xi.last_modification == self.current_iteration {
+                xi.value = xi.value_backup;
+                xi.last_modification = xi.modify_backup;
+            }
+        }
+        self.current_iteration -= 1;
+    }
+
+    fn ensure_ready(&mut self, index: usize) {
+        while self.x.len() <= index {
+            self.x.push(PrimarySample {
+                value: 0.,
+                last_modification: 0,
+                value_backup: 0.,
+                modify_backup: 0,
+            });
+        }
+        let xi = &mut self.x[index];
+
+        // a large step since the last modification invalidates the old value
+        if xi.last_modification < self.last_large_step_iteration {
+            xi.value = self.rng.gen();
+            xi.last_modification = self.last_large_step_iteration;
+        }
+
+        xi.value_backup = xi.value;
+        xi.modify_backup = xi.last_modification;
+        if self.large_step {
+            xi.value = self.rng.gen();
+        } else {
+            // apply all the small steps skipped since the last modification at once
+            let small_steps = (self.current_iteration - xi.last_modification) as f64;
+            let sigma = self.sigma * small_steps.sqrt();
+            xi.value += normal(&mut self.rng) * sigma;
+            xi.value -= xi.value.floor();
+        }
+        xi.last_modification = self.current_iteration;
+    }
+}
+
+impl Sampler for MltSampler {
+    fn get_1d(&mut self) -> f64 {
+        let index = self.sample_index;
+        self.sample_index += 1;
+        self.ensure_ready(index);
+        self.x[index].value
+    }
+}
+
+/// Standard normal sample using the Box-Muller transform.
+fn normal(rng: &mut StdRng) -> f64 {
+    let u1 = 1. - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
+}
+
+struct Film<'a> {
+    camera: &'a Camera,
+    world: &'a dyn Hittable,
+    lighting: &'a Lighting<'a>,
+    config: &'a Config,
+    width: usize,
+    height: usize,
+}
+
+impl Film<'_> {
+    /// Traces the path described by the sampler's primary samples, the first
+    /// two of which choose the film position.
+    fn contribution(&self, sampler: &mut dyn Sampler) -> (Color, usize) {
+        let (u, v) = sampler.get_2d();
+        let x = u * self.width as f64;
+        let y = v * self.height as f64;
+        let i = (x as usize).min(self.width - 1);
+        let j = (y as usize).min(self.height - 1);
+
+        let r = self
+            .camera
+            .get_ray(x / (self.width as f64 - 1.), y / (self.height as f64 - 1.))
+            .with_wavelengths(self.config.wavelengths(sampler));
+        let color = r.color(self.world, self.lighting, self.config.depth, sampler);
+        (color, j * self.width + i)
+    }
+}
+
+fn run_chain(
+    film: &Film,
+    params: &MltConfig,
+    cdf: &[f64],
+    chain: usize,
+    mutations: u64,
+    cancel: &CancellationToken,
+) -> Vec<Color> {
+    let mut splats = vec![Vec3::new(); film.width * film.height];
+    let mut rng = StdRng::seed_from_u64(stream_seed(
+        film.config.seed,
+        (params.bootstrap_samples + chain) as u64,
+    ));
+
+    // pick the initial state proportionally to its bootstrap weight
+    let total = cdf[cdf.len() - 1];
+    let u = rng.gen::<f64>() * total;
+    let index = cdf.partition_point(|&c| c <= u).min(cdf.len() - 1);
+
+    let mut sampler = MltSampler::new(
+        stream_seed(film.config.seed, index as u64),
+        params.sigma,
+        params.large_step_probability,
+    );
+    let (mut l_current, mut p_current) = film.contribution(&mut sampler);
+
+    for m in 0..mutations {
+        if m % 1024 == 0 && cancel.is_cancelled() {
+            break;
+        }
+        sampler.start_iteration();
+        let (l_proposed, p_proposed) = film.contribution(&mut sampler);
+
+        let y_current = l_current.luminance();
+        let y_proposed = l_proposed.luminance();
+        let accept = if y_current > 0. {
+            (y_proposed / y_current).min(1.)
+        } else {
+            1.
+        };
+
+        if y_proposed > 0. {
+            splats[p_proposed] += l_proposed * (accept / y_proposed);
+        }
+        if y_current > 0. {
+            splats[p_current] += l_current * ((1. - accept) / y_current);
+        }
+
+        if rng.gen::<f64>() < accept {
+            l_current = l_proposed;
+            p_current = p_proposed;
+            sampler.accept();
+        } else {
+            sampler.reject();
+        }
+    }
+    splats
+}
+
+/// Renders the image with MLT, returning the average radiance of every pixel
+/// with rows ordered bottom to top.
+pub fn render(
+    camera: &Camera,
+    world: &dyn Hittable,
+    lighting: &Lighting,
+    config: &Config,
+    params: &MltConfig,
+    cancel: &CancellationToken,
+) -> Vec<Vec<Color>> {
+    let height = config.height;
+    let width = config.width();
+    let film = Film {
+        camera,
+        world,
+        lighting,
+        config,
+        width,
+        height,
+    };
+
+    // bootstrap: estimate the normalisation constant b from independent paths
+    let weights: Vec<f64> = (0..params.bootstrap_samples.max(1))
+        .into_par_iter()
+        .map(|i| {
+            if cancel.is_cancelled() {
+                return 0.;
+            }
+            let mut sampler = MltSampler::new(
+                stream_seed(film.config.seed, i as u64),
+                params.sigma,
+                params.large_step_probability,
+            );
+            film.contribution(&mut sampler).0.luminance()
+        })
+        .collect();
+    let cdf: Vec<f64> = weights
+        .iter()
+        .scan(0., |acc, w| {
+            *acc += w;
+            Some(*acc)
+        })
+        .collect();
+    let b = cdf[cdf.len() - 1] / weights.len() as f64;
+
+    let mut image = vec![Vec3::new(); width * height];
+    if b > 0. {
+        let chains = params.chains.max(1);
+        let total_mutations = params.mutations_per_pixel as u64 * (width * height) as u64;
+        let batch = rayon::current_num_threads();
+
+        // chains run in parallel batches but are merged in chain order, so the
+        // result does not depend on the number of threads
+        for first in (0..chains).step_by(batch) {
+            let splats: Vec<Vec<Color>> = (first..(first + batch).min(chains))
+                .into_par_iter()
+                .map(|c| {
+                    let mutations = total_mutations / chains as u64
+                        + ((c as u64) < total_mutations % chains as u64) as u64;
+                    run_chain(&film, params, &cdf, c, mutations, cancel)
+                })
+                .collect();
+            for chain in splats {
+                for (pixel, splat) in image.iter_mut().zip(chain) {
+                    *pixel += splat;
+                }
+            }
+        }
+    }
+
+    let scale = b / params.mutations_per_pixel.max(1) as f64;
+    image
+        .chunks(width)
+        .map(|row| row.iter().map(|&c| c * scale).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        camera::Camera,
+        geometries::{sphere::Sphere, HittableList},
+        lights::Lighting,
+        materials::{diffuse::Diffuse, light::Light},
+        progress::CancellationToken,
+        sampler::Sampler,
+        scene::Config,
+        utils::vec3::{Color, Point3},
+    };
+
+    use super::{render, MltConfig, MltSampler};
+
+    #[test]
+    fn rejected_mutation_restores_samples() {
+        let mut sampler = MltSampler::new(7, 0.01, 0.3);
+        let before: Vec<f64> = (0..8).map(|_| sampler.get_1d()).collect();
+
+        for _ in 0..10 {
+            sampler.start_iteration();
+            let mutated: Vec<f64> = (0..8).map(|_| sampler.get_1d()).collect();
+            assert_ne!(before, mutated);
+            sampler.reject();
+        }
+
+        sampler.start_iteration();
+        sampler.reject();
+        let restored: Vec<f64> = sampler.x.iter().map(|x| x.value).collect();
+        assert_eq!(before, restored);
+    }
+
+    #[test]
+    fn mlt_is_deterministic() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -100.5, -1.),
+            100.,
+            Arc::new(Diffuse::new(0.8, 0.8, 0.)),
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., 1., -1.),
+            0.5,
+            Arc::new(Light::new(1., 1., 1.)),
+        )));
+        let camera = Camera::default();
+        let config = Config {
+            height: 9,
+            seed: 42,
+            depth: 5,
+            background: Color::new(),
+            ..Config::default()
+        };
+        let params = MltConfig {
+            bootstrap_samples: 1000,
+            chains: 8,
+            mutations_per_pixel: 20,
+            ..MltConfig::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let lighting = Lighting::constant(config.background);
+        let a = render(&camera, &world, &lighting, &config, &params, &cancel);
+        let b = render(&camera, &world, &lighting, &config, &params, &cancel);
+        assert_eq!(a, b);
+        assert!(a.iter().flatten().any(|c| c.luminance() > 0.));
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use self::mlt::MltConfig;
+
+pub mod mlt;
+
+pub enum Integrator {
+    Path,
+    Mlt(MltConfig),
+}
+
+/// Integrator a scene description renders with.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegratorType {
+    Path,
+    /// Metropolis light transport with the default settings.
+    Mlt,
+}
+
+impl IntegratorType {
+    pub fn build(&self) -> Integrator {
+        match self {
+            IntegratorType::Path => Integrator::Path,
+            IntegratorType::Mlt => Integrator::Mlt(MltConfig::default()),
+        }
+    }
+}
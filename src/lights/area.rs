@@ -0,0 +1,168 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    geometries::{HitRecord, Shape},
+    materials::Material,
+    ray::Ray,
+    utils::vec3::{Color, Point3},
+};
+
+use super::{bvh::LightBounds, LightSample, LightSource};
+
+/// Emissive shape of the scene, sampled by solid angle.
+pub struct AreaLight {
+    shape: Arc<dyn Shape>,
+    material: Arc<dyn Material>,
+    object: usize,
+}
+
+impl AreaLight {
+    /// Light of `shape`, which is the `object`-th object of the world.
+    pub fn from(shape: Arc<dyn Shape>, object: usize) -> Self {
+        AreaLight {
+            material: shape.material(),
+            shape,
+            object,
+        }
+    }
+}
+
+impl LightSource for AreaLight {
+    fn sample(&self, p: Point3, u: (f64, f64)) -> Option<LightSample> {
+        let sample = self.shape.sample(p, u)?;
+        let to_light = sample.p - p;
+        let distance = to_light.length();
+        if distance == 0. {
+            return None;
+        }
+        let wi = to_light / distance;
+        let ray = Ray::from(p, wi);
+        let mut rec = HitRecord::from(
+            &ray,
+            distance,
+            sample.p,
+            sample.normal,
+            self.material.clone(),
+        );
+        rec.uv = sample.uv;
+        let radiance = self.material.emitted(&ray, &rec);
+        if radiance.near_zero() {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            distance,
+            radiance,
+            pdf: sample.pdf,
+        })
+    }
+
+    fn illuminant(&self, ray: &Ray, radiance: Color) -> Color {
+        self.material.illuminant(ray, radiance)
+    }
+
+    fn pdf(&self, p: Point3, rec: &HitRecord) -> f64 {
+        self.shape.pdf(p, rec.p, rec.normal)
+    }
+
+    fn object(&self) -> Option<usize> {
+        Some(self.object)
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let power = self.material.emitted_power(self.shape.area());
+        let bounds = self.shape.bounds();
+        Some(match self.shape.flat_normal() {
+            Some(normal) => LightBounds {
+                bounds,
+                power,
+                axis: normal,
+                theta_o: 0.,
+                theta_e: PI / 2.,
+                two_sided: self.material.emits_both_sides(),
+            },
+            None => LightBounds::omnidirectional(bounds, power),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use crate::{
+        geometries::{disc::Disc, quad::Quad, HittableList},
+        lights::{LightSource, Lighting},
+        materials::{diffuse::Diffuse, light::Light},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::AreaLight;
+
+    /// Radiance of a diffuse floor lit by a disc of the given power above it,
+    /// with and without sampling the disc as a light.
+    fn lit_floor(two_sided: bool, normal: Vec3) -> (f64, f64) {
+        let mut light = Light::from(Color::from(1., 1., 1.));
+        light.two_sided = two_sided;
+        light.set_power(10., PI);
+        let disc = Arc::new(Disc::from(
+            Point3::from(0., 2., 0.),
+            normal,
+            1.,
+            Arc::new(light),
+        ));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Quad::from(
+            Point3::from(-50., 0., 50.),
+            Vec3::from(100., 0., 0.),
+            Vec3::from(0., 0., -100.),
+            Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+        )));
+        world.add(disc.clone());
+        let lights: Vec<Arc<dyn LightSource>> = vec![Arc::new(AreaLight::from(disc, 2))];
+        let ray = Ray::from(Point3::from(0., 1., 1.), Vec3::from(0., -1., -1.));
+
+        let estimate = |lights: &[Arc<dyn LightSource>], n: u32| {
+            let lighting = Lighting {
+                lights,
+                ..Lighting::constant(Color::new())
+            };
+            let mut sampler = IndependentSampler::new(7);
+            let mut sum = 0.;
+            for index in 0..n {
+                sampler.start_pixel_sample((0, 0), index);
+                sum += ray.color(&world, &lighting, 2, &mut sampler).x();
+            }
+            sum / n as f64
+        };
+        (estimate(&lights, 4000), estimate(&[], 100000))
+    }
+
+    #[test]
+    fn disc_light_matches_form_factor() {
+        // irradiance under the center of a disc of radius R at height h is
+        // pi L R² / (h² + R²), with L = power / (pi area)
+        let radiance = 10. / (PI * PI);
+        let expected = 0.5 * radiance / 5.;
+        let (sampled, scattered) = lit_floor(false, Vec3::from(0., -1., 0.));
+        assert!((sampled - expected).abs() < 0.01 * expected, "{}", sampled);
+        assert!(
+            (scattered - expected).abs() < 0.05 * expected,
+            "{}",
+            scattered
+        );
+
+        // the back of a one-sided light is dark, a two-sided light emits
+        // half its power on each side
+        let (back, _) = lit_floor(false, Vec3::from(0., 1., 0.));
+        assert_eq!(back, 0.);
+        let (two_sided, _) = lit_floor(true, Vec3::from(0., 1., 0.));
+        assert!(
+            (two_sided - expected / 2.).abs() < 0.01 * expected,
+            "{}",
+            two_sided
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    geometries::bounds::{axis, Bounds},
+    utils::vec3::{Point3, Vec3},
+};
+
+use super::{unbounded_share, LightSelector, LightSource};
+
+/// Where a light, or a group of lights, emits and how much.
+///
+/// Emission leaves within `theta_e` of the surface normals, which lie in a
+/// cone of half angle `theta_o` around `axis`. Angles are in radians.
+#[derive(Clone, Copy, Debug)]
+pub struct LightBounds {
+    pub bounds: Bounds,
+    /// Estimated luminance of the emitted power in watts.
+    pub power: f64,
+    pub axis: Vec3,
+    pub theta_o: f64,
+    pub theta_e: f64,
+    /// Whether the surfaces emit from their back face as well.
+    pub two_sided: bool,
+}
+
+/// Rotates the unit vector `v` by `angle` around the unit axis `k` normal
+/// to it.
+fn rotate(v: Vec3, k: Vec3, angle: f64) -> Vec3 {
+    v * angle.cos() + k.cross(&v) * angle.sin()
+}
+
+fn angle_between(a: Vec3, b: Vec3) -> f64 {
+    a.dot(&b).clamp(-1., 1.).acos()
+}
+
+/// Smallest cone holding both cones, given by axis and half angle.
+fn cone_union(a: (Vec3, f64), b: (Vec3, f64)) -> (Vec3, f64) {
+    let theta_d = angle_between(a.0, b.0);
+    if (theta_d + b.1).min(PI) <= a.1 {
+        return a;
+    }
+    if (theta_d + a.1).min(PI) <= b.1 {
+        return b;
+    }
+    let theta_o = (a.1 + theta_d + b.1) / 2.;
+    if theta_o >= PI {
+        return (a.0, PI);
+    }
+    let k = a.0.cross(&b.0);
+    if k.length_squared() == 0. {
+        return (a.0, PI);
+    }
+    (rotate(a.0, k.unit_vector(), theta_o - a.1), theta_o)
+}
+
+impl LightBounds {
+    /// Bounds of a light emitting equally in every direction from `bounds`.
+    pub fn omnidirectional(bounds: Bounds, power: f64) -> Self {
+        LightBounds {
+            bounds,
+            power,
+            axis: Vec3::from(0., 0., 1.),
+            theta_o: PI,
+            theta_e: PI / 2.,
+            two_sided: false,
+        }
+    }
+
+    pub fn union(&self, other: &LightBounds) -> Self {
+        if self.power == 0. {
+            return *other;
+        }
+        if other.power == 0. {
+            return *self;
+        }
+        let (axis, theta_o) = cone_union((self.axis, self.theta_o), (other.axis, other.theta_o));
+        LightBounds {
+            bounds: self.bounds.union(&other.bounds),
+            power: self.power + other.power,
+            axis,
+            theta_o,
+            theta_e: self.theta_e.max(other.theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+
+    /// Conservative estimate of the light arriving at `p` on a surface with
+    /// normal `n`, a zero normal ignoring the orientation of the surface.
+    pub fn importance(&self, p: Point3, n: Vec3) -> f64 {
+        let center = self.bounds.center();
+        let to_p = p - center;
+        let distance = to_p.length();
+        let radius = self.bounds.diagonal().length() / 2.;
+        // the importance of points inside the bounds is capped
+        let distance_squared = (distance * distance).max(radius);
+
+        // angle the bounds subtend from p
+        let theta_b = if self.bounds.contains(p) || distance <= radius {
+            PI
+        } else {
+            (radius / distance).asin()
+        };
+        let wi = if distance > 0. {
+            to_p / distance
+        } else {
+            self.axis
+        };
+        let mut cos_w = self.axis.dot(&wi);
+        if self.two_sided {
+            cos_w = cos_w.abs();
+        }
+        let theta_w = cos_w.clamp(-1., 1.).acos();
+        let theta = (theta_w - self.theta_o - theta_b).max(0.);
+        if theta >= self.theta_e {
+            return 0.;
+        }
+        let mut importance = self.power * theta.cos() / distance_squared;
+        if n.length_squared() > 0. {
+            let theta_i = wi.dot(&n).abs().min(1.).acos();
+            importance *= (theta_i - theta_b).max(0.).cos();
+        }
+        importance.max(0.)
+    }
+
+    /// Cost of a node with these bounds when splitting along `dim` of the
+    /// parent bounds `parent`, after the surface area orientation heuristic.
+    fn cost(&self, parent: &Bounds, dim: usize) -> f64 {
+        let theta_w = (self.theta_o + self.theta_e).min(PI);
+        let (sin_o, cos_o) = self.theta_o.sin_cos();
+        let m_omega = 2. * PI * (1. - cos_o)
+            + PI / 2.
+                * (2. * theta_w * sin_o
+                    - (self.theta_o - 2. * theta_w).cos()
+                    - 2. * self.theta_o * sin_o
+                    + cos_o);
+        let d = parent.diagonal();
+        let longest = d.x().max(d.y()).max(d.z());
+        let extent = axis(d, dim);
+        let kr = if extent > 0. { longest / extent } else { 1. };
+        self.power * m_omega * kr * self.bounds.surface_area().max(f64::EPSILON)
+    }
+}
+
+enum Node {
+    Leaf {
+        light: usize,
+    },
+    /// The first child follows the node.
+    Interior {
+        second: usize,
+    },
+}
+
+const BUCKETS: usize = 12;
+
+/// Bounding volume hierarchy over the light sources of a scene, choosing
+/// lights proportionally to their estimated contribution at a point.
+///
+/// Lights without bounds, like directional lights, are kept outside the
+/// hierarchy and chosen uniformly.
+pub struct LightBvh {
+    nodes: Vec<Node>,
+    bounds: Vec<LightBounds>,
+    parents: Vec<usize>,
+    /// Leaf node of every light in the hierarchy.
+    leaves: Vec<Option<usize>>,
+    unbounded: Vec<usize>,
+}
+
+impl LightBvh {
+    pub fn new(lights: &[Arc<dyn LightSource>]) -> Self {
+        let mut bvh = LightBvh {
+            nodes: vec![],
+            bounds: vec![],
+            parents: vec![],
+            leaves: vec![None; lights.len()],
+            unbounded: vec![],
+        };
+        let mut bounded = vec![];
+        for (index, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(bounds) if bounds.power > 0. => bounded.push((index, bounds)),
+                Some(_) => {}
+                None => bvh.unbounded.push(index),
+            }
+        }
+        if !bounded.is_empty() {
+            bvh.build(&mut bounded, 0);
+        }
+        bvh
+    }
+
+    /// Builds the subtree of `lights` and returns its root.
+    fn build(&mut self, lights: &mut [(usize, LightBounds)], parent: usize) -> usize {
+        let node = self.nodes.len();
+        let total = lights
+            .iter()
+            .skip(1)
+            .fold(lights[0].1, |total, (_, b)| total.union(b));
+        self.bounds.push(total);
+        self.parents.push(parent);
+        if let [(light, _)] = lights {
+            self.nodes.push(Node::Leaf { light: *light });
+            self.leaves[*light] = Some(node);
+            return node;
+        }
+        self.nodes.push(Node::Interior { second: 0 });
+
+        let mid = split(lights, &total.bounds);
+        let (first, second) = lights.split_at_mut(mid);
+        self.build(first, node);
+        let second = self.build(second, node);
+        self.nodes[node] = Node::Interior { second };
+        node
+    }
+
+    /// Probability of choosing the first child of an interior node.
+    fn first_probability(&self, node: usize, second: usize, p: Point3, n: Vec3) -> Option<f64> {
+        let first = self.bounds[node + 1].importance(p, n);
+        let second = self.bounds[second].importance(p, n);
+        if first + second == 0. {
+            return None;
+        }
+        Some(first / (first + second))
+    }
+
+    fn unbounded_share(&self) -> f64 {
+        unbounded_share(self.unbounded.len(), !self.nodes.is_empty())
+    }
+}
+
+/// Partitions `lights` into the two children of a node with `bounds`, with
+/// the bucket split of lowest cost, and returns the size of the first.
+fn split(lights: &mut [(usize, LightBounds)], bounds: &Bounds) -> usize {
+    let centroids = Bounds::around(lights.iter().map(|(_, b)| b.bounds.center())).unwrap();
+    let bucket = |b: &LightBounds, dim: usize| {
+        let offset = axis(centroids.offset(b.bounds.center()), dim);
+        ((offset * BUCKETS as f64) as usize).min(BUCKETS - 1)
+    };
+
+    let mut best: Option<(f64, usize, usize)> = None;
+    for dim in 0..3 {
+        if axis(centroids.diagonal(), dim) == 0. {
+            continue;
+        }
+        let mut buckets: [Option<LightBounds>; BUCKETS] = [None; BUCKETS];
+        for (_, b) in lights.iter() {
+            let i = bucket(b, dim);
+            buckets[i] = Some(buckets[i].map_or(*b, |bucket| bucket.union(b)));
+        }
+        let merge = |range: &[Option<LightBounds>]| {
+            range.iter().flatten().copied().reduce(|a, b| a.union(&b))
+        };
+        for i in 1..BUCKETS {
+            let cost = [merge(&buckets[..i]), merge(&buckets[i..])]
+                .iter()
+                .flatten()
+                .map(|b| b.cost(bounds, dim))
+                .sum::<f64>();
+            if best.is_none_or(|(lowest, _, _)| cost < lowest) {
+                best = Some((cost, dim, i));
+            }
+        }
+    }
+
+    let mid = match best {
+        Some((_, dim, i)) => {
+            let mut mid = 0;
+            for j in 0..lights.len() {
+                if bucket(&lights[j].1, dim) < i {
+                    lights.swap(mid, j);
+                    mid += 1;
+                }
+            }
+            mid
+        }
+        None => 0,
+    };
+    if mid == 0 || mid == lights.len() {
+        // every centroid in one place
+        lights.len() / 2
+    } else {
+        mid
+    }
+}
+
+impl LightSelector for LightBvh {
+    fn select(&self, p: Point3, n: Vec3, u: f64) -> Option<(usize, f64)> {
+        let share = self.unbounded_share();
+        if u < share {
+            let count = self.unbounded.len();
+            let i = ((u / share * count as f64) as usize).min(count - 1);
+            return Some((self.unbounded[i], share / count as f64));
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut u = ((u - share) / (1. - share)).min(1. - f64::EPSILON);
+        let mut probability = 1. - share;
+        let mut node = 0;
+        loop {
+            match self.nodes[node] {
+                Node::Leaf { light } => {
+                    if node == 0 && self.bounds[0].importance(p, n) == 0. {
+                        return None;
+                    }
+                    return Some((light, probability));
+                }
+                Node::Interior { second } => {
+                    let first = self.first_probability(node, second, p, n)?;
+                    if u < first {
+                        u = (u / first).min(1. - f64::EPSILON);
+                        probability *= first;
+                        node += 1;
+                    } else {
+                        u = ((u - first) / (1. - first)).min(1. - f64::EPSILON);
+                        probability *= 1. - first;
+                        node = second;
+                    }
+                }
+            }
+        }
+    }
+
+    fn probability(&self, p: Point3, n: Vec3, light: usize) -> f64 {
+        let share = self.unbounded_share();
+        if self.unbounded.contains(&light) {
+            return share / self.unbounded.len() as f64;
+        }
+        let Some(Some(mut node)) = self.leaves.get(light).copied() else {
+            return 0.;
+        };
+        if node == 0 && self.bounds[0].importance(p, n) == 0. {
+            return 0.;
+        }
+        let mut probability = 1. - share;
+        while node != 0 {
+            let parent = self.parents[node];
+            let Node::Interior { second } = self.nodes[parent] else {
+                unreachable!("leaves have no children");
+            };
+            let Some(first) = self.first_probability(parent, second, p, n) else {
+                return 0.;
+            };
+            probability *= if node == second { 1. - first } else { first };
+            node = parent;
+        }
+        probability
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::{quad::Quad, sphere::Sphere, HittableList, Shape},
+        lights::{
+            area::AreaLight, directional::DirectionalLight, point::PointLight, power::PowerLights,
+            spot::SpotLight, LightSelector, LightSource, Lighting,
+        },
+        materials::{diffuse::Diffuse, light::Light},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::LightBvh;
+
+    /// Lights on a grid above the ground, adding the emissive shapes to
+    /// `world`.
+    fn lights(world: &mut HittableList) -> Vec<Arc<dyn LightSource>> {
+        let white = Color::from(1., 1., 1.);
+        let mut lights: Vec<Arc<dyn LightSource>> = vec![];
+        for i in 0..20 {
+            let x = (i % 5) as f64 * 4. - 8.;
+            let z = (i / 5) as f64 * 4. - 6.;
+            let light: Arc<dyn LightSource> = match i % 3 {
+                0 => Arc::new(PointLight::from(Point3::from(x, 3., z), white, 5.)),
+                1 => Arc::new(SpotLight::from(
+                    Point3::from(x, 3., z),
+                    Vec3::from(0., -1., 0.),
+                    white,
+                    20.,
+                    20.,
+                    40.,
+                )),
+                _ => {
+                    let shape: Arc<dyn Shape> = Arc::new(Quad::from(
+                        Point3::from(x, 3., z),
+                        Vec3::from(0.5, 0., 0.),
+                        Vec3::from(0., 0., 0.5),
+                        Arc::new(Light::new(4., 4., 4.)),
+                    ));
+                    world.add(shape.clone());
+                    Arc::new(AreaLight::from(shape, world.size()))
+                }
+            };
+            lights.push(light);
+        }
+        lights.push(Arc::new(DirectionalLight::from(
+            Vec3::from(1., -1., 0.),
+            white,
+            1.,
+        )));
+        lights
+    }
+
+    #[test]
+    fn probabilities_match_selection() {
+        let lights = lights(&mut HittableList::new());
+        let bvh = LightBvh::new(&lights);
+        let up = Vec3::from(0., 1., 0.);
+        for p in [
+            Point3::new(),
+            Point3::from(-7., 0., 5.),
+            Point3::from(3., 2.9, -1.),
+        ] {
+            let total: f64 = (0..lights.len()).map(|i| bvh.probability(p, up, i)).sum();
+            assert!((total - 1.).abs() < 1e-9, "{}", total);
+            for k in 0..200 {
+                let u = (k as f64 + 0.5) / 200.;
+                let (light, probability) = bvh.select(p, up, u).unwrap();
+                assert!((bvh.probability(p, up, light) - probability).abs() < 1e-12);
+            }
+        }
+        // nearby lights are favoured over distant ones of the same power
+        let p = Point3::from(-8., 0., -6.);
+        assert!(bvh.probability(p, up, 0) > 10. * bvh.probability(p, up, 12));
+        // lights facing away can not be chosen
+        assert_eq!(bvh.probability(Point3::from(0., 5., 0.), up, 2), 0.);
+    }
+
+    #[test]
+    fn selection_is_unbiased() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -1000., 0.),
+            1000.,
+            Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+        )));
+        let lights = lights(&mut world);
+        let ray = Ray::from(Point3::from(-3., 1., -1.), Vec3::from(0., -1., -1.));
+        let estimate = |selector: Option<&dyn LightSelector>, n: u32| {
+            let lighting = Lighting {
+                lights: &lights,
+                selector,
+                ..Lighting::constant(Color::new())
+            };
+            let mut sampler = IndependentSampler::new(3);
+            let mut sum = 0.;
+            for index in 0..n {
+                sampler.start_pixel_sample((0, 0), index);
+                sum += ray.color(&world, &lighting, 2, &mut sampler).x();
+            }
+            sum / n as f64
+        };
+        let all = estimate(None, 2000);
+        let bvh = estimate(Some(&LightBvh::new(&lights)), 40000);
+        assert!((bvh - all).abs() < 0.02 * all, "{} {}", bvh, all);
+        let power = estimate(Some(&PowerLights::new(&lights)), 40000);
+        assert!((power - all).abs() < 0.02 * all, "{} {}", power, all);
+    }
+}
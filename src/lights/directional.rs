@@ -0,0 +1,55 @@
+use crate::{
+    ray::Ray,
+    utils::{
+        spectrum::Blackbody,
+        vec3::{Color, Point3, Vec3},
+    },
+};
+
+use super::{LightSample, LightSource};
+
+/// Light from infinitely far away arriving from a single direction, like
+/// sunlight.
+pub struct DirectionalLight {
+    /// Unit direction the light travels in.
+    direction: Vec3,
+    /// Irradiance on a surface facing the light in W/m².
+    irradiance: Color,
+    blackbody: Option<Blackbody>,
+}
+
+impl DirectionalLight {
+    /// Light travelling along `direction` with an irradiance in W/m².
+    pub fn from(direction: Vec3, color: Color, irradiance: f64) -> Self {
+        assert!(
+            direction.is_direction(),
+            "a directional light needs a direction"
+        );
+        DirectionalLight {
+            direction: direction.unit_vector(),
+            irradiance: color * irradiance,
+            blackbody: None,
+        }
+    }
+
+    /// Follows the spectrum of `blackbody` in spectral rendering, the color
+    /// being a multiple of its color.
+    pub fn set_blackbody(&mut self, blackbody: Blackbody) {
+        self.blackbody = Some(blackbody);
+    }
+}
+
+impl LightSource for DirectionalLight {
+    fn sample(&self, _p: Point3, _u: (f64, f64)) -> Option<LightSample> {
+        Some(LightSample {
+            wi: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.,
+        })
+    }
+
+    fn illuminant(&self, ray: &Ray, radiance: Color) -> Color {
+        ray.emission(radiance, self.blackbody.as_ref())
+    }
+}
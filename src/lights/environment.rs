@@ -0,0 +1,198 @@
+use std::{f64::consts::PI, io, path::Path};
+
+use crate::utils::{
+    distribution::Distribution2D,
+    hdr,
+    vec3::{Color, Vec3},
+};
+
+use super::Environment;
+
+/// Equirectangular HDR environment. The top row of the image looks up (+y)
+/// and the horizontal center looks down -z before rotation.
+///
+/// Directions are importance sampled proportionally to the luminance of the
+/// pixels, weighted by the solid angle they cover.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    distribution: Distribution2D,
+    /// Rotation around the y axis in degrees.
+    pub rotation: f64,
+    /// Scale of the radiance of every pixel.
+    pub intensity: f64,
+    pub visible_to_camera: bool,
+}
+
+impl EnvironmentMap {
+    /// Map from pixels listed row by row from the top.
+    pub fn from(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        let func: Vec<f64> = pixels
+            .chunks(width)
+            .enumerate()
+            .flat_map(|(j, row)| {
+                let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+                row.iter().map(move |p| p.luminance().max(0.) * sin_theta)
+            })
+            .collect();
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            distribution: Distribution2D::new(&func, width, height),
+            rotation: 0.,
+            intensity: 1.,
+            visible_to_camera: true,
+        }
+    }
+
+    /// Loads a Radiance `.hdr` image.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let (width, height, pixels) = hdr::read(path)?;
+        Ok(Self::from(width, height, pixels))
+    }
+
+    fn direction(&self, uv: (f64, f64)) -> Vec3 {
+        equirect_direction(uv, self.rotation)
+    }
+
+    fn uv(&self, dir: Vec3) -> (f64, f64) {
+        let theta = dir.y().clamp(-1., 1.).acos();
+        let phi = (-dir.x()).atan2(-dir.z()) - self.rotation.to_radians();
+        let u = (phi / (2. * PI) + 0.5).rem_euclid(1.);
+        (u, theta / PI)
+    }
+
+    fn lookup(&self, (u, v): (f64, f64)) -> Color {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i] * self.intensity
+    }
+}
+
+/// Unit direction of the equirectangular image position (u, v) rotated by
+/// `rotation` degrees around the y axis.
+pub fn equirect_direction((u, v): (f64, f64), rotation: f64) -> Vec3 {
+    let theta = v * PI;
+    let phi = (u - 0.5) * 2. * PI + rotation.to_radians();
+    Vec3::from(
+        -theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, dir: Vec3) -> Color {
+        self.lookup(self.uv(dir))
+    }
+
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Color, f64)> {
+        let (uv, pdf) = self.distribution.sample(u);
+        let sin_theta = (uv.1 * PI).sin();
+        if pdf == 0. || sin_theta == 0. {
+            return None;
+        }
+        let pdf = pdf / (2. * PI * PI * sin_theta);
+        Some((self.direction(uv), self.lookup(uv), pdf))
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let uv = self.uv(dir);
+        let sin_theta = (uv.1 * PI).sin();
+        if sin_theta == 0. {
+            return 0.;
+        }
+        self.distribution.pdf(uv) / (2. * PI * PI * sin_theta)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.visible_to_camera
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        lights::Environment,
+        materials::diffuse::Diffuse,
+        scene::{Config, Scene},
+        utils::vec3::{Color, Point3},
+    };
+
+    use super::EnvironmentMap;
+
+    #[test]
+    fn sampling_integrates_radiance() {
+        let (width, height) = (8, 4);
+        let mut rng = StdRng::seed_from_u64(1);
+        let pixels: Vec<Color> = (0..width * height)
+            .map(|_| Color::from(1., 1., 1.) * rng.gen::<f64>().powi(4))
+            .collect();
+        // exact integral over the sphere of directions
+        let exact: f64 = pixels
+            .iter()
+            .enumerate()
+            .map(|(p, c)| {
+                let j = (p / width) as f64;
+                let band = (PI * j / height as f64).cos() - (PI * (j + 1.) / height as f64).cos();
+                c.luminance() * band * 2. * PI / width as f64
+            })
+            .sum();
+
+        let mut map = EnvironmentMap::from(width, height, pixels);
+        map.rotation = 30.;
+        let n = 400;
+        let mut estimate = 0.;
+        for s in 0..n * n {
+            let u = ((s % n) as f64 + 0.5) / n as f64;
+            let v = ((s / n) as f64 + 0.5) / n as f64;
+            let (dir, radiance, pdf) = map.sample((u, v)).unwrap();
+            assert!((map.pdf(dir) - pdf).abs() < 1e-6 * pdf);
+            assert_eq!(map.radiance(dir), radiance);
+            estimate += radiance.luminance() / pdf;
+        }
+        estimate /= (n * n) as f64;
+        assert!(
+            (estimate - exact).abs() < exact * 1e-3,
+            "{} vs {}",
+            estimate,
+            exact
+        );
+    }
+
+    #[test]
+    fn white_furnace() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., 0., -1.),
+            0.5,
+            Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+        )));
+        let mut scene = Scene::new();
+        scene.set_world(world);
+        scene.set_config(Config {
+            height: 9,
+            samples: 64,
+            ..Config::default()
+        });
+        let mut map = EnvironmentMap::from(4, 2, vec![Color::from(1., 1., 1.); 8]);
+        map.visible_to_camera = false;
+        scene.set_environment(Arc::new(map));
+
+        let image = scene.render_path().unwrap();
+        let center = image[4][scene.config().width() / 2];
+        assert!(
+            (center - Color::from(0.5, 0.5, 0.5)).length() < 0.02,
+            "{:?}",
+            center
+        );
+        assert!((image[8][0] - scene.config().background).length() < 1e-6);
+    }
+}
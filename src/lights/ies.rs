@@ -0,0 +1,304 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use crate::utils::vec3::Vec3;
+
+/// Measured angular intensity distribution of a luminaire, read from an IES
+/// LM-63 photometric file.
+///
+/// Only type C photometry is supported. Vertical angles are measured from
+/// the nadir of the luminaire and horizontal angles around it, in degrees.
+/// Lamp tilt data is skipped, assuming the lamp is used as measured.
+pub struct IesProfile {
+    vertical: Vec<f64>,
+    horizontal: Vec<f64>,
+    /// Candela of every vertical angle, per horizontal angle.
+    candela: Vec<Vec<f64>>,
+    max: f64,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Index `i` such that `angle` lies between `angles[i]` and `angles[i + 1]`,
+/// and the position between the two.
+fn locate(angles: &[f64], angle: f64) -> (usize, f64) {
+    if angles.len() == 1 {
+        return (0, 0.);
+    }
+    let i = angles
+        .partition_point(|&a| a <= angle)
+        .clamp(1, angles.len() - 1)
+        - 1;
+    let span = angles[i + 1] - angles[i];
+    let t = if span > 0. {
+        ((angle - angles[i]) / span).clamp(0., 1.)
+    } else {
+        0.
+    };
+    (i, t)
+}
+
+impl IesProfile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find(|line| line.starts_with("TILT="))
+            .ok_or_else(|| invalid("missing TILT line"))?;
+        let rest: Vec<&str> = lines.collect();
+        let mut values = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<f64>().map_err(|_| invalid("invalid number")));
+        let mut next = || {
+            values
+                .next()
+                .unwrap_or_else(|| Err(invalid("truncated file")))
+        };
+
+        if tilt == "TILT=INCLUDE" {
+            next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()?;
+        for _ in 0..4 {
+            // units and luminous opening dimensions
+            next()?;
+        }
+        let ballast = next()?;
+        let ballast_lamp = next()?;
+        let _watts = next()?;
+        if photometric_type != 1. {
+            return Err(invalid("only type C photometry is supported"));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("no angles"));
+        }
+
+        let vertical = (0..vertical_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        let horizontal = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        let scale = multiplier * ballast * ballast_lamp;
+        let candela = (0..horizontal_count)
+            .map(|_| {
+                (0..vertical_count)
+                    .map(|_| next().map(|c| c * scale))
+                    .collect::<io::Result<Vec<f64>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        if vertical.windows(2).any(|w| w[0] > w[1]) || horizontal.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid("angles must be increasing"));
+        }
+        let max = candela.iter().flatten().fold(0., |m: f64, &c| m.max(c));
+        Ok(IesProfile {
+            vertical,
+            horizontal,
+            candela,
+            max,
+        })
+    }
+
+    pub fn max_candela(&self) -> f64 {
+        self.max
+    }
+
+    /// Maps a horizontal angle into the measured range using the symmetry
+    /// implied by the range.
+    fn fold(&self, phi: f64) -> f64 {
+        let phi = phi.rem_euclid(360.);
+        let first = self.horizontal[0];
+        let last = self.horizontal[self.horizontal.len() - 1];
+        if last == 90. {
+            // symmetric in each quadrant
+            let phi = phi % 180.;
+            if phi > 90. {
+                180. - phi
+            } else {
+                phi
+            }
+        } else if last == 180. && phi > 180. {
+            // symmetric about the 0-180 plane
+            360. - phi
+        } else if first == 90. && last == 270. && phi < 90. {
+            // symmetric about the 90-270 plane
+            180. - phi
+        } else if first == 90. && last == 270. && phi > 270. {
+            540. - phi
+        } else {
+            phi
+        }
+    }
+
+    /// Candela at the vertical angle `theta` and horizontal angle `phi`,
+    /// interpolated bilinearly between the measured angles.
+    pub fn candela(&self, theta: f64, phi: f64) -> f64 {
+        let first = self.vertical[0];
+        let last = self.vertical[self.vertical.len() - 1];
+        if theta < first || theta > last {
+            return 0.;
+        }
+        let (j, tv) = locate(&self.vertical, theta);
+        let (i, th) = locate(&self.horizontal, self.fold(phi));
+        let at = |i: usize| {
+            let row = &self.candela[i];
+            let next = row.get(j + 1).copied().unwrap_or(row[j]);
+            row[j] * (1. - tv) + next * tv
+        };
+        let next = if i + 1 < self.horizontal.len() {
+            at(i + 1)
+        } else {
+            at(i)
+        };
+        at(i) * (1. - th) + next * th
+    }
+}
+
+/// Profile oriented in the scene, its nadir along `nadir`.
+pub struct Photometry {
+    profile: Arc<IesProfile>,
+    nadir: Vec3,
+    /// Directions of the horizontal angles 0 and 90.
+    s: Vec3,
+    t: Vec3,
+}
+
+impl Photometry {
+    pub fn from(profile: Arc<IesProfile>, nadir: Vec3) -> Self {
+        let nadir = nadir.unit_vector();
+        let (s, t) = nadir.orthonormal_basis();
+        Photometry {
+            profile,
+            nadir,
+            s,
+            t,
+        }
+    }
+
+    /// Intensity leaving the luminaire in the unit direction `dir`, relative
+    /// to its peak intensity.
+    pub fn factor(&self, dir: Vec3) -> f64 {
+        let max = self.profile.max_candela();
+        if max == 0. {
+            return 0.;
+        }
+        let theta = dir.dot(&self.nadir).clamp(-1., 1.).acos().to_degrees();
+        let phi = dir.dot(&self.t).atan2(dir.dot(&self.s)).to_degrees();
+        self.profile.candela(theta, phi) / max
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, sync::Arc};
+
+    use crate::{
+        geometries::{quad::Quad, HitType, Hittable},
+        lights::{point::PointLight, LightSource},
+        materials::light::Light,
+        ray::Ray,
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::{IesProfile, Photometry};
+
+    fn sample(name: &str) -> IesProfile {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets/ies")
+            .join(name);
+        IesProfile::load(&path).unwrap()
+    }
+
+    #[test]
+    fn parses_rotationally_symmetric_profile() {
+        let profile = sample("downlight.ies");
+        assert_eq!(profile.max_candela(), 800.);
+        assert_eq!(profile.candela(0., 0.), 800.);
+        assert_eq!(profile.candela(30., 123.), 620.);
+        assert!((profile.candela(35., 300.) - 550.).abs() < 1e-9);
+        // nothing measured above the horizon
+        assert_eq!(profile.candela(120., 0.), 0.);
+    }
+
+    #[test]
+    fn parses_tilt_and_quadrant_symmetry() {
+        let profile = sample("wallwasher.ies");
+        // multiplier of 2, candela values wrapped over several lines
+        assert_eq!(profile.candela(0., 0.), 200.);
+        assert_eq!(profile.candela(45., 45.), 300.);
+        assert_eq!(profile.candela(90., 90.), 180.);
+        assert_eq!(profile.max_candela(), 600.);
+        // mirrored into the first quadrant
+        for phi in [135., 225., 315., -45.] {
+            assert!((profile.candela(45., phi) - 300.).abs() < 1e-9);
+        }
+        assert!((profile.candela(90., 67.5) - 150.).abs() < 1e-9);
+        assert!((profile.candela(90., 112.5) - 150.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        let text = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/ies/downlight.ies"),
+        )
+        .unwrap();
+        let type_b = text.replace("1 1000 1 10 1 1 2", "1 1000 1 10 1 2 2");
+        assert!(IesProfile::parse(&type_b).is_err());
+        assert!(IesProfile::parse(&text[..text.len() - 20]).is_err());
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n").is_err());
+    }
+
+    #[test]
+    fn shapes_point_light() {
+        let profile = Arc::new(sample("downlight.ies"));
+        let mut light = PointLight::from(Point3::from(0., 2., 0.), Color::from(1., 1., 1.), 8.);
+        light.set_photometry(Photometry::from(profile, Vec3::from(0., -1., 0.)));
+        let below = light.sample(Point3::new(), (0.5, 0.5)).unwrap();
+        assert!((below.radiance.x() - 2.).abs() < 1e-9);
+        let side = light.sample(Point3::from(2., 0., 0.), (0.5, 0.5)).unwrap();
+        assert!((side.radiance.x() - 8. * 400. / 800. / 8.).abs() < 1e-6);
+        assert!(light.sample(Point3::from(0., 3., 0.), (0.5, 0.5)).is_none());
+    }
+
+    #[test]
+    fn shapes_emissive_surface() {
+        let mut light = Light::from(Color::from(1., 1., 1.));
+        light.profile = Some(Arc::new(sample("downlight.ies")));
+        // facing down
+        let quad = Quad::from(
+            Point3::from(-0.5, 1., -0.5),
+            Vec3::from(1., 0., 0.),
+            Vec3::from(0., 0., 1.),
+            Arc::new(light),
+        );
+        let radiance = |dir: Vec3| {
+            let ray = Ray::from(Point3::from(0., 1., 0.) - dir * 2., dir);
+            let HitType::Hit(rec) = quad.hit(&ray, 0.001, f64::INFINITY) else {
+                panic!("missed the light");
+            };
+            rec.material.emitted(&ray, &rec).x()
+        };
+        assert!((radiance(Vec3::from(0., 1., 0.)) - 1.).abs() < 1e-9);
+        // the intensity of the surface follows the profile
+        let dir = Vec3::from(0.5, 3f64.sqrt() / 2., 0.);
+        let expected = 620. / 800. / 30f64.to_radians().cos();
+        assert!((radiance(dir) - expected).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometries::HitRecord,
+    media::Medium,
+    ray::Ray,
+    utils::vec3::{Color, Point3, Vec3},
+};
+
+use self::{
+    bvh::{LightBounds, LightBvh},
+    power::PowerLights,
+};
+
+pub mod area;
+pub mod bvh;
+pub mod directional;
+pub mod environment;
+pub mod ies;
+pub mod point;
+pub mod power;
+pub mod sky;
+pub mod spot;
+
+/// Light arriving from infinitely far away, such as an environment map.
+pub trait Environment: Send + Sync {
+    /// Radiance arriving from the unit direction `dir`.
+    fn radiance(&self, dir: Vec3) -> Color;
+
+    /// Samples a unit direction towards the environment, returning it with
+    /// its radiance and solid angle density.
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Color, f64)>;
+
+    /// Solid angle density of `sample` choosing the unit direction `dir`.
+    fn pdf(&self, dir: Vec3) -> f64;
+
+    fn visible_to_camera(&self) -> bool {
+        true
+    }
+}
+
+/// Light arriving at a point from a sampled position on a light source.
+pub struct LightSample {
+    /// Unit direction from the lit point towards the light.
+    pub wi: Vec3,
+    /// Distance to the light, infinite for lights far away.
+    pub distance: f64,
+    /// Radiance arriving from `wi`, or irradiance for lights that can only
+    /// arrive from a single direction.
+    pub radiance: Color,
+    /// Density of choosing `wi`, 1 for lights of a single direction.
+    pub pdf: f64,
+}
+
+/// Light source sampled directly by the integrator.
+///
+/// Intensities are in radiometric units: watts per steradian for lights at a
+/// position and watts per square meter for directional lights.
+pub trait LightSource: Send + Sync {
+    /// Samples the light arriving at `p`, `None` if none does.
+    fn sample(&self, p: Point3, u: (f64, f64)) -> Option<LightSample>;
+
+    /// Radiance of a sample in linear sRGB, as carried by `ray`.
+    fn illuminant(&self, ray: &Ray, radiance: Color) -> Color {
+        ray.illuminant(radiance)
+    }
+
+    /// Solid angle density of `sample` choosing the point of `rec` seen from
+    /// `p`. Lights that rays can not hit have no density.
+    fn pdf(&self, _p: Point3, _rec: &HitRecord) -> f64 {
+        0.
+    }
+
+    /// One-based index of the world object emitting the light, for lights
+    /// that rays can hit.
+    fn object(&self) -> Option<usize> {
+        None
+    }
+
+    /// Where the light emits and how much, `None` for lights infinitely far
+    /// away.
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+}
+
+/// Chooses one of the lights of a scene to sample at a shading point.
+pub trait LightSelector: Send + Sync {
+    /// Index of the light to sample at `p` on a surface with normal `n`, and
+    /// the probability of choosing it. `None` if no light reaches `p`.
+    fn select(&self, p: Point3, n: Vec3, u: f64) -> Option<(usize, f64)>;
+
+    /// Probability of `select` choosing the light at index `light`.
+    fn probability(&self, p: Point3, n: Vec3, light: usize) -> f64;
+}
+
+/// How the integrator picks the light sources to sample at every bounce.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightSampling {
+    /// Samples every light.
+    All,
+    /// Samples a single light chosen by power.
+    Power,
+    /// Samples a single light chosen by its estimated contribution through
+    /// a light hierarchy.
+    Bvh,
+}
+
+impl LightSampling {
+    pub fn selector(&self, lights: &[Arc<dyn LightSource>]) -> Option<Box<dyn LightSelector>> {
+        match self {
+            LightSampling::All => None,
+            LightSampling::Power => Some(Box::new(PowerLights::new(lights))),
+            LightSampling::Bvh => Some(Box::new(LightBvh::new(lights))),
+        }
+    }
+}
+
+/// Probability of choosing one of `unbounded` lights without bounds, each
+/// as likely as all the lights with bounds together if there are any.
+fn unbounded_share(unbounded: usize, bounded: bool) -> f64 {
+    if unbounded == 0 {
+        return 0.;
+    }
+    let groups = unbounded + usize::from(bounded);
+    unbounded as f64 / groups as f64
+}
+
+/// Light sources of a scene and the media their light crosses, as seen by
+/// the integrator.
+#[derive(Clone, Copy)]
+pub struct Lighting<'a> {
+    /// Radiance of rays leaving the scene without an environment.
+    pub background: Color,
+    pub environment: Option<&'a dyn Environment>,
+    pub lights: &'a [Arc<dyn LightSource>],
+    /// Chooses a single light to sample, every light is sampled without.
+    pub selector: Option<&'a dyn LightSelector>,
+    pub media: &'a [Arc<Medium>],
+}
+
+impl Lighting<'_> {
+    pub fn constant(background: Color) -> Self {
+        Lighting {
+            background,
+            environment: None,
+            lights: &[],
+            selector: None,
+            media: &[],
+        }
+    }
+
+    /// Radiance carried by a ray that hit nothing. Camera rays see the
+    /// background instead of an environment hidden from the camera.
+    pub fn escaped(&self, ray: &Ray, camera_ray: bool) -> Color {
+        match self.environment {
+            Some(env) if !camera_ray || env.visible_to_camera() => {
+                env.radiance(ray.dir().unit_vector())
+            }
+            _ => self.background,
+        }
+    }
+}
+
+/// Multiple importance sampling weight of a sample with density `pdf` drawn
+/// from a strategy competing with one of density `other`.
+pub fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0. {
+        return 0.;
+    }
+    a / (a + b)
+}
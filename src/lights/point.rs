@@ -0,0 +1,126 @@
+use std::f64::consts::PI;
+
+use crate::utils::{
+    spectrum::Blackbody,
+    vec3::{Color, Point3},
+};
+
+use crate::{geometries::bounds::Bounds, ray::Ray};
+
+use super::{bvh::LightBounds, ies::Photometry, LightSample, LightSource};
+
+/// Light emitted from a single point, equally in all directions unless it
+/// follows a measured distribution.
+pub struct PointLight {
+    position: Point3,
+    /// Radiant intensity in W/sr, the peak intensity with a photometry.
+    intensity: Color,
+    photometry: Option<Photometry>,
+    blackbody: Option<Blackbody>,
+}
+
+impl PointLight {
+    /// Light of the given color and intensity in W/sr.
+    pub fn from(position: Point3, color: Color, intensity: f64) -> Self {
+        PointLight {
+            position,
+            intensity: color * intensity,
+            photometry: None,
+            blackbody: None,
+        }
+    }
+
+    /// Shapes the emission by a measured distribution, keeping the intensity
+    /// as its peak.
+    pub fn set_photometry(&mut self, photometry: Photometry) {
+        self.photometry = Some(photometry);
+    }
+
+    /// Follows the spectrum of `blackbody` in spectral rendering, the color
+    /// being a multiple of its color.
+    pub fn set_blackbody(&mut self, blackbody: Blackbody) {
+        self.blackbody = Some(blackbody);
+    }
+
+    /// Light of the given color emitting `power` watts in total.
+    #[allow(dead_code)]
+    pub fn with_power(position: Point3, color: Color, power: f64) -> Self {
+        Self::from(position, color, power / (4. * PI))
+    }
+}
+
+impl LightSource for PointLight {
+    fn sample(&self, p: Point3, _u: (f64, f64)) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        if distance == 0. {
+            return None;
+        }
+        let wi = to_light / distance;
+        let factor = self.photometry.as_ref().map_or(1., |p| p.factor(-wi));
+        if factor == 0. {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            distance,
+            radiance: self.intensity * (factor / (distance * distance)),
+            pdf: 1.,
+        })
+    }
+
+    fn illuminant(&self, ray: &Ray, radiance: Color) -> Color {
+        ray.emission(radiance, self.blackbody.as_ref())
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let power = 4. * PI * self.intensity.luminance();
+        Some(LightBounds::omnidirectional(
+            Bounds::point(self.position),
+            power,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        lights::{LightSource, Lighting},
+        materials::diffuse::Diffuse,
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::PointLight;
+
+    #[test]
+    fn lights_diffuse_surface() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0., -1000., 0.),
+            1000.,
+            Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+        )));
+        let lights: Vec<Arc<dyn LightSource>> = vec![Arc::new(PointLight::with_power(
+            Point3::from(1., 2., 0.),
+            Color::from(1., 1., 1.),
+            4. * PI * 10.,
+        ))];
+        let lighting = Lighting {
+            lights: &lights,
+            ..Lighting::constant(Color::new())
+        };
+
+        let mut sampler = IndependentSampler::new(1);
+        sampler.start_pixel_sample((0, 0), 0);
+        let ray = Ray::from(Point3::from(0., 1., 1.), Vec3::from(0., -1., -1.));
+        let color = ray.color(&world, &lighting, 2, &mut sampler);
+        // 10 W/sr at a distance of sqrt(5), arriving at cos = 2 / sqrt(5)
+        let expected = 0.5 / PI * 10. / 5. * 2. / 5f64.sqrt();
+        assert!((color.x() - expected).abs() < 1e-6, "{}", color.x());
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use crate::utils::{
+    distribution::Distribution1D,
+    vec3::{Point3, Vec3},
+};
+
+use super::{unbounded_share, LightSelector, LightSource};
+
+/// Chooses lights proportionally to their power, wherever they are.
+///
+/// Lights without bounds, like directional lights, have no power to compare
+/// and are chosen uniformly.
+pub struct PowerLights {
+    bounded: Vec<usize>,
+    /// Chooses among `bounded` by power.
+    distribution: Option<Distribution1D>,
+    /// Position of every light in `bounded`.
+    positions: Vec<Option<usize>>,
+    unbounded: Vec<usize>,
+}
+
+impl PowerLights {
+    pub fn new(lights: &[Arc<dyn LightSource>]) -> Self {
+        let (mut bounded, mut powers, mut unbounded) = (vec![], vec![], vec![]);
+        let mut positions = vec![None; lights.len()];
+        for (index, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(bounds) if bounds.power > 0. => {
+                    positions[index] = Some(bounded.len());
+                    bounded.push(index);
+                    powers.push(bounds.power);
+                }
+                Some(_) => {}
+                None => unbounded.push(index),
+            }
+        }
+        PowerLights {
+            bounded,
+            distribution: (!powers.is_empty()).then(|| Distribution1D::new(&powers)),
+            positions,
+            unbounded,
+        }
+    }
+
+    fn unbounded_share(&self) -> f64 {
+        unbounded_share(self.unbounded.len(), self.distribution.is_some())
+    }
+}
+
+impl LightSelector for PowerLights {
+    fn select(&self, _p: Point3, _n: Vec3, u: f64) -> Option<(usize, f64)> {
+        let share = self.unbounded_share();
+        if u < share {
+            let count = self.unbounded.len();
+            let i = ((u / share * count as f64) as usize).min(count - 1);
+            return Some((self.unbounded[i], share / count as f64));
+        }
+        let distribution = self.distribution.as_ref()?;
+        let u = ((u - share) / (1. - share)).min(1. - f64::EPSILON);
+        let (i, probability) = distribution.sample_discrete(u);
+        Some((self.bounded[i], probability * (1. - share)))
+    }
+
+    fn probability(&self, _p: Point3, _n: Vec3, light: usize) -> f64 {
+        let share = self.unbounded_share();
+        if self.unbounded.contains(&light) {
+            return share / self.unbounded.len() as f64;
+        }
+        match (&self.distribution, self.positions.get(light)) {
+            (Some(distribution), Some(Some(i))) => distribution.probability(*i) * (1. - share),
+            _ => 0.,
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::f64::consts::PI;
+
+use crate::utils::{
+    color::xyz_to_rgb,
+    vec3::{Color, Vec3},
+};
+
+use super::{
+    environment::{equirect_direction, EnvironmentMap},
+    Environment,
+};
+
+/// Radiance units per cd/m², which puts a clear midday sky around 1.
+const LUMINANCE_SCALE: f64 = 1e-4;
+/// Luminance of the sun outside the atmosphere in cd/m².
+const SUN_LUMINANCE: f64 = 2.1e9;
+/// Angular radius of the sun disk in degrees.
+const SUN_RADIUS: f64 = 0.2666;
+/// Wavelengths in micrometers standing in for the red, green and blue channels
+/// when attenuating the sun.
+const WAVELENGTHS: [f64; 3] = [0.61, 0.55, 0.465];
+/// Size of the image the sky is baked into for importance sampling.
+const BAKE_WIDTH: usize = 128;
+const BAKE_HEIGHT: usize = 64;
+
+/// Coefficients A to E of the Perez luminance distribution.
+type Perez = [f64; 5];
+
+fn perez(p: &Perez, cos_theta: f64, gamma: f64) -> f64 {
+    (1. + p[0] * (p[1] / cos_theta).exp())
+        * (1. + p[2] * (p[3] * gamma).exp() + p[4] * gamma.cos() * gamma.cos())
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    xyz_to_rgb(Vec3::from(x / y, 1., (1. - x - y) / y) * luminance)
+}
+
+/// Daylight sky of the Preetham et al. analytic model with a sun disk.
+///
+/// The sky is lit by the sun in `sun_direction` through an atmosphere of the
+/// given turbidity (2 is very clear, 10 hazy). Below the horizon lies a
+/// diffuse ground of albedo `ground_albedo` lit by the sky and the sun.
+/// Directions are sampled from the sun disk and from the sky baked into an
+/// equirectangular map, so both are found by next event estimation.
+pub struct Sky {
+    sun: Vec3,
+    cos_sun_radius: f64,
+    sun_radiance: Color,
+    /// Perez coefficients and zenith values of Y, x and y.
+    coefficients: [Perez; 3],
+    zenith: [f64; 3],
+    sun_theta: f64,
+    ground: Color,
+    map: EnvironmentMap,
+    sun_probability: f64,
+    /// Scale of the radiance of the sky and the sun.
+    pub intensity: f64,
+    pub visible_to_camera: bool,
+}
+
+impl Sky {
+    pub fn new(sun_direction: Vec3, turbidity: f64, ground_albedo: Color) -> Self {
+        assert!(sun_direction.is_direction(), "the sun needs a direction");
+        // Preetham et al. fitted the model to turbidities from 2 to 10
+        assert!(
+            (2. ..=10.).contains(&turbidity),
+            "turbidity must lie within [2, 10]"
+        );
+        let sun = sun_direction.unit_vector();
+        let t = turbidity;
+        // the model only covers suns above the horizon
+        let sun_theta = sun.y().clamp(-1., 1.).acos().min(PI / 2.);
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * sun_theta);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * 1000.;
+        let (s1, s2, s3) = (sun_theta, sun_theta * sun_theta, sun_theta.powi(3));
+        let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s1)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s1 + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s1 + 0.25886);
+        let zenith_y = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s1)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s1 + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s1 + 0.26688);
+
+        let sun_radiance = if sun.y() > 0. {
+            Self::sun_transmittance(sun_theta, t) * SUN_LUMINANCE * LUMINANCE_SCALE
+        } else {
+            Color::new()
+        };
+        let cos_sun_radius = SUN_RADIUS.to_radians().cos();
+        let mut sky = Sky {
+            sun,
+            cos_sun_radius,
+            sun_radiance,
+            coefficients,
+            zenith: [zenith_luminance.max(0.), zenith_x, zenith_y],
+            sun_theta,
+            ground: Color::new(),
+            map: EnvironmentMap::from(1, 1, vec![Color::new()]),
+            sun_probability: 0.,
+            intensity: 1.,
+            visible_to_camera: true,
+        };
+
+        let solid_angle = |j: usize| {
+            let band = (PI * j as f64 / BAKE_HEIGHT as f64).cos()
+                - (PI * (j + 1) as f64 / BAKE_HEIGHT as f64).cos();
+            band * 2. * PI / BAKE_WIDTH as f64
+        };
+        let directions: Vec<Vec3> = (0..BAKE_WIDTH * BAKE_HEIGHT)
+            .map(|p| {
+                let u = ((p % BAKE_WIDTH) as f64 + 0.5) / BAKE_WIDTH as f64;
+                let v = ((p / BAKE_WIDTH) as f64 + 0.5) / BAKE_HEIGHT as f64;
+                equirect_direction((u, v), 0.)
+            })
+            .collect();
+        // irradiance of the ground from the sky above and the sun
+        let sun_solid_angle = 2. * PI * (1. - cos_sun_radius);
+        let mut irradiance = sun_radiance * sun_solid_angle * sun.y().max(0.);
+        for (p, dir) in directions.iter().enumerate() {
+            if dir.y() > 0. {
+                irradiance += sky.sky(*dir) * dir.y() * solid_angle(p / BAKE_WIDTH);
+            }
+        }
+        sky.ground = ground_albedo * irradiance / PI;
+
+        let pixels: Vec<Color> = directions.iter().map(|dir| sky.sky(*dir)).collect();
+        let sky_power: f64 = pixels
+            .iter()
+            .enumerate()
+            .map(|(p, c)| c.luminance() * solid_angle(p / BAKE_WIDTH))
+            .sum();
+        let sun_power = sun_radiance.luminance() * sun_solid_angle;
+        if sun_power > 0. {
+            sky.sun_probability = (sun_power / (sun_power + sky_power)).min(0.9);
+        }
+        sky.map = EnvironmentMap::from(BAKE_WIDTH, BAKE_HEIGHT, pixels);
+        sky
+    }
+
+    /// Fraction of sunlight left after Rayleigh and aerosol scattering along
+    /// the path through the atmosphere, per color channel.
+    fn sun_transmittance(sun_theta: f64, turbidity: f64) -> Color {
+        let air_mass =
+            1. / (sun_theta.cos() + 0.15 * (93.885 - sun_theta.to_degrees()).powf(-1.253));
+        let beta = 0.04608365 * turbidity - 0.04586025;
+        let channel = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+            rayleigh * aerosol
+        };
+        Color::from(
+            channel(WAVELENGTHS[0]),
+            channel(WAVELENGTHS[1]),
+            channel(WAVELENGTHS[2]),
+        )
+    }
+
+    /// Radiance of the sky or ground in `dir`, without the sun disk.
+    fn sky(&self, dir: Vec3) -> Color {
+        if dir.y() < 0. {
+            return self.ground;
+        }
+        let cos_theta = dir.y().max(1e-3);
+        let gamma = dir.dot(&self.sun).clamp(-1., 1.).acos();
+        let value = |k: usize| {
+            let c = &self.coefficients[k];
+            self.zenith[k] * perez(c, cos_theta, gamma) / perez(c, 1., self.sun_theta)
+        };
+        let color = xyy_to_rgb(value(1), value(2), value(0)) * LUMINANCE_SCALE;
+        Color::from(color.x().max(0.), color.y().max(0.), color.z().max(0.))
+    }
+
+    fn in_sun(&self, dir: Vec3) -> bool {
+        dir.dot(&self.sun) >= self.cos_sun_radius
+    }
+
+    fn sun_pdf(&self) -> f64 {
+        1. / (2. * PI * (1. - self.cos_sun_radius))
+    }
+
+    /// Uniformly samples a direction in the cone of the sun disk.
+    fn sample_sun(&self, (u, v): (f64, f64)) -> Vec3 {
+        let cos_theta = 1. - u * (1. - self.cos_sun_radius);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * v;
+        let w = self.sun;
+        let (s, t) = w.orthonormal_basis();
+        (s * phi.cos() * sin_theta + t * phi.sin() * sin_theta + w * cos_theta).unit_vector()
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, dir: Vec3) -> Color {
+        let mut radiance = self.sky(dir);
+        if self.in_sun(dir) {
+            radiance += self.sun_radiance;
+        }
+        radiance * self.intensity
+    }
+
+    fn sample(&self, (u, v): (f64, f64)) -> Option<(Vec3, Color, f64)> {
+        let p = self.sun_probability;
+        let dir = if u < p {
+            self.sample_sun((u / p, v))
+        } else {
+            self.map.sample(((u - p) / (1. - p), v))?.0
+        };
+        let pdf = self.pdf(dir);
+        if pdf == 0. {
+            return None;
+        }
+        Some((dir, self.radiance(dir), pdf))
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let p = self.sun_probability;
+        let sun = if self.in_sun(dir) { self.sun_pdf() } else { 0. };
+        p * sun + (1. - p) * self.map.pdf(dir)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.visible_to_camera
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::{
+        lights::Environment,
+        utils::vec3::{Color, Vec3},
+    };
+
+    use super::Sky;
+
+    #[test]
+    fn sampling_integrates_radiance() {
+        let sky = Sky::new(Vec3::from(1., 0.8, -0.5), 3., Color::from(0.3, 0.3, 0.3));
+        assert!(sky.sun_probability > 0.);
+        let n = 400;
+        let mut estimate = 0.;
+        let mut reference = 0.;
+        for s in 0..n * n {
+            let u = ((s % n) as f64 + 0.5) / n as f64;
+            let v = ((s / n) as f64 + 0.5) / n as f64;
+            let (dir, radiance, pdf) = sky.sample((u, v)).unwrap();
+            assert!((sky.pdf(dir) - pdf).abs() < 1e-6 * pdf);
+            assert_eq!(sky.radiance(dir), radiance);
+            estimate += radiance.luminance() / pdf;
+
+            // uniform directions see the sky, the sun disk is added exactly
+            let cos_theta = 1. - 2. * u;
+            let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+            let phi = 2. * PI * v;
+            let dir = Vec3::from(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+            reference += sky.sky(dir).luminance() * 4. * PI;
+        }
+        estimate /= (n * n) as f64;
+        reference /= (n * n) as f64;
+        reference += sky.sun_radiance.luminance() / sky.sun_pdf();
+        assert!(
+            (estimate - reference).abs() < reference * 1e-2,
+            "{} vs {}",
+            estimate,
+            reference
+        );
+    }
+
+    #[test]
+    fn daylight_colors() {
+        let noon = Sky::new(Vec3::from(0., 1., -0.3), 3., Color::from(0.3, 0.3, 0.3));
+        let sunset = Sky::new(Vec3::from(0., 0.05, -1.), 3., Color::from(0.3, 0.3, 0.3));
+        // blue sky, reddened setting sun
+        let zenith = noon.radiance(Vec3::from(0., 1., 0.1).unit_vector());
+        assert!(zenith.z() > zenith.x(), "{:?}", zenith);
+        let ratio = |c: Color| c.x() / c.z();
+        assert!(ratio(sunset.sun_radiance) > 2. * ratio(noon.sun_radiance));
+        assert!(sunset.sun_radiance.luminance() < noon.sun_radiance.luminance());
+
+        // the ground reflects the light falling on it
+        let down = Vec3::from(0., -1., 0.);
+        let dark = Sky::new(Vec3::from(0., 1., -0.3), 3., Color::from(0.1, 0.1, 0.1));
+        assert!((noon.radiance(down) - dark.radiance(down) * 3.).length() < 1e-9);
+        assert!(noon.radiance(down).luminance() > sunset.radiance(down).luminance());
+
+        let below = Sky::new(Vec3::from(0., -0.2, -1.), 3., Color::from(0.3, 0.3, 0.3));
+        assert_eq!(below.sun_probability, 0.);
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::{
+    geometries::bounds::Bounds,
+    ray::Ray,
+    utils::{
+        spectrum::Blackbody,
+        vec3::{Color, Point3, Vec3},
+    },
+};
+
+use super::{bvh::LightBounds, ies::Photometry, LightSample, LightSource};
+
+/// Point light emitting into a cone around `direction`.
+///
+/// The intensity is full inside the inner angle and falls off smoothly to
+/// zero at the outer angle. A photometry further shapes it within the cone.
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    /// Radiant intensity along the axis in W/sr.
+    intensity: Color,
+    cos_inner: f64,
+    cos_outer: f64,
+    photometry: Option<Photometry>,
+    blackbody: Option<Blackbody>,
+}
+
+impl SpotLight {
+    /// Spot light with an intensity in W/sr and cone half angles in degrees.
+    pub fn from(
+        position: Point3,
+        direction: Vec3,
+        color: Color,
+        intensity: f64,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> Self {
+        assert!(direction.is_direction(), "a spot light needs a direction");
+        let outer_angle = outer_angle.max(inner_angle);
+        SpotLight {
+            position,
+            direction: direction.unit_vector(),
+            intensity: color * intensity,
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+            photometry: None,
+            blackbody: None,
+        }
+    }
+
+    pub fn set_photometry(&mut self, photometry: Photometry) {
+        self.photometry = Some(photometry);
+    }
+
+    /// Follows the spectrum of `blackbody` in spectral rendering, the color
+    /// being a multiple of its color.
+    pub fn set_blackbody(&mut self, blackbody: Blackbody) {
+        self.blackbody = Some(blackbody);
+    }
+
+    /// Fraction of the intensity emitted at an angle with cosine `cos_theta`
+    /// to the axis.
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_inner {
+            return 1.;
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.;
+        }
+        let x = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        x * x * (3. - 2. * x)
+    }
+}
+
+impl LightSource for SpotLight {
+    fn sample(&self, p: Point3, _u: (f64, f64)) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        if distance == 0. {
+            return None;
+        }
+        let wi = to_light / distance;
+        let falloff = self.falloff(-wi.dot(&self.direction))
+            * self.photometry.as_ref().map_or(1., |p| p.factor(-wi));
+        if falloff == 0. {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            distance,
+            radiance: self.intensity * (falloff / (distance * distance)),
+            pdf: 1.,
+        })
+    }
+
+    fn illuminant(&self, ray: &Ray, radiance: Color) -> Color {
+        ray.emission(radiance, self.blackbody.as_ref())
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        // the falloff emits about as much as a cone halfway between the angles
+        let solid_angle = 2. * PI * (1. - (self.cos_inner + self.cos_outer) / 2.);
+        let theta_o = self.cos_inner.acos();
+        Some(LightBounds {
+            bounds: Bounds::point(self.position),
+            power: self.intensity.luminance() * solid_angle,
+            axis: self.direction,
+            theta_o,
+            theta_e: self.cos_outer.acos() - theta_o,
+            two_sided: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lights::LightSource,
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::SpotLight;
+
+    #[test]
+    fn cone_falloff() {
+        let spot = SpotLight::from(
+            Point3::from(0., 2., 0.),
+            Vec3::from(0., -1., 0.),
+            Color::from(1., 1., 1.),
+            8.,
+            20.,
+            40.,
+        );
+        let at = |x: f64| {
+            spot.sample(Point3::from(x, 0., 0.), (0.5, 0.5))
+                .map_or(0., |s| s.radiance.x())
+        };
+        // full intensity on the axis, falling with the squared distance
+        assert!((at(0.) - 2.).abs() < 1e-12);
+        let inner = 2. * 20f64.to_radians().tan();
+        let outer = 2. * 40f64.to_radians().tan();
+        assert!((at(inner * 0.99) - 8. / (4. + inner * inner * 0.9801)).abs() < 1e-9);
+        assert!(at(inner * 1.1) > at(inner * 1.3));
+        assert!(at(inner * 1.3) > at(outer * 0.99));
+        assert!(spot
+            .sample(Point3::from(outer * 1.01, 0., 0.), (0.5, 0.5))
+            .is_none());
+        // nothing behind the light
+        assert!(spot.sample(Point3::from(0., 3., 0.), (0.5, 0.5)).is_none());
+    }
+}
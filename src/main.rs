@@ -1,20 +1,90 @@
-use std::sync::Arc;
+use std::{env, fs, net::TcpListener, process, sync::Arc};
 
 use camera::Camera;
-use geometries::{sphere::Sphere, HittableList};
+use description::SceneDescription;
+use filter::FilterType;
+use geometries::sphere::Sphere;
+use integrators::Integrator;
+use lights::LightSampling;
 use materials::{dielectric::Dielectric, diffuse::Diffuse, light::Light, metal::Metal, Material};
+use progress::ProgressBar;
+use sampler::SamplerType;
 use scene::{Config, Scene};
+use tiles::TileOrder;
 use utils::vec3::*;
 
+mod adaptive;
+mod aov;
 mod camera;
+mod checkpoint;
+mod denoise;
+mod description;
+mod distributed;
+mod film;
+mod filter;
 mod geometries;
+mod integrators;
+mod lights;
 mod materials;
+mod media;
+#[allow(dead_code)]
 mod mt;
+mod progress;
+mod progressive;
 mod ray;
+mod sampler;
 mod scene;
+mod server;
+mod textures;
+mod tiles;
 mod utils;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        None => render_builtin(),
+        Some("render") if args.len() == 3 => {
+            let mut scene = load_scene(&args[2]).build().unwrap_or_else(|e| exit(e));
+            scene.set_observer(Arc::new(ProgressBar::new()));
+            scene.render().unwrap_or_else(|e| exit(e));
+        }
+        Some("coordinator") if args.len() == 4 => {
+            let description = load_scene(&args[3]);
+            let listener = TcpListener::bind(&args[2]).unwrap_or_else(|e| exit(e));
+            println!("Waiting for workers on {}", args[2]);
+            let scene = description.build().unwrap_or_else(|e| exit(e));
+            let image = distributed::coordinate(&description, listener).unwrap_or_else(|e| exit(e));
+            scene.save(image, 1);
+        }
+        Some("server") if args.len() == 3 => {
+            let listener = TcpListener::bind(&args[2]).unwrap_or_else(|e| exit(e));
+            println!("Serving render jobs on {}", args[2]);
+            server::serve(listener).unwrap_or_else(|e| exit(e));
+        }
+        Some("worker") if args.len() == 3 => {
+            distributed::run_worker(&args[2]).unwrap_or_else(|e| exit(e));
+        }
+        _ => {
+            eprintln!(
+                "usage: {0}\n       {0} render <scene.json>\n       {0} coordinator <address> <scene.json>\n       {0} worker <address>\n       {0} server <address>",
+                args[0]
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn exit(error: impl std::fmt::Display) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn load_scene(path: &str) -> SceneDescription {
+    let json = fs::read_to_string(path).unwrap_or_else(|e| exit(e));
+    SceneDescription::from_json(&json).unwrap_or_else(|e| exit(e))
+}
+
+fn render_builtin() {
     let mut scene = Scene::new();
 
     let from = Point3::from(-2., 1.5, 2.);
@@ -22,8 +92,6 @@ fn main() {
     let vup = Vec3::from(0., 1., 0.);
     let camera = Camera::new(from, at, vup, 90., 16. / 9.);
 
-    let world = small_scene();
-
     let config = Config {
         name: String::from("dio"),
         height: 1440,
@@ -31,39 +99,56 @@ fn main() {
         depth: 20,
         aspect_ratio: 16. / 9.,
         background: Color::from(0.05, 0.05, 0.05),
+        integrator: Integrator::Path,
+        sampler: SamplerType::Sobol,
+        seed: 0,
+        adaptive: None,
+        filter: FilterType::Mitchell,
+        filter_radius: 2.,
+        tile_size: 32,
+        tile_order: TileOrder::Spiral,
+        threads: 0,
+        progressive: None,
+        checkpoint: None,
+        aov: None,
+        denoise: None,
+        white_balance: None,
+        light_sampling: LightSampling::All,
+        spectral: false,
     };
 
     scene.set_config(config);
-    scene.set_world(world);
     scene.set_camera(camera);
+    small_scene(&mut scene);
+    scene.set_observer(Arc::new(ProgressBar::new()));
 
-    scene.render();
+    scene.render().unwrap_or_else(|e| exit(e));
 }
 
-pub fn random_scene() -> HittableList {
-    let mut world = HittableList::new();
+/// Emissive spheres are sampled as area lights.
+pub fn random_scene(scene: &mut Scene) {
     let ground = Arc::new(Diffuse::new(0.5, 0.5, 0.5));
     let light = Arc::new(Light::new(1., 1., 0.8));
     let metal = Arc::new(Metal::from(Color::from(0.5, 0.5, 0.5), 0.05));
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(0., -1000., 0.),
         1000.,
         ground.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_area_light(Arc::new(Sphere::from(
         Point3::from(8., 2., -0.6),
         0.3,
         light.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_area_light(Arc::new(Sphere::from(
         Point3::from(0., 2., -0.4),
         0.3,
         light.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(4., 1., 0.),
         1.,
         metal.clone(),
@@ -89,80 +174,75 @@ pub fn random_scene() -> HittableList {
                     Arc::new(Dielectric::new(1.5))
                 };
                 let sphere = Arc::new(Sphere::from(center, 0.2, material));
-                world.add(sphere);
+                scene.add_object(sphere);
             }
         }
     }
-    world
 }
 
-fn small_scene() -> HittableList {
+fn small_scene(scene: &mut Scene) {
     // let from = Point3::from(-2., 1.5, 2.);
     // let at = Point3::from(0., 0., -1.);
     // let vup = Vec3::from(0., 1., 0.);
     // let camera = Camera::new(from, at, vup, 90., aspect_ratio);
 
-    let mut world = HittableList::new();
     let ground = Arc::new(Diffuse::new(0.8, 0.8, 0.0));
     let light = Arc::new(Light::new(1., 1., 0.8));
     let red = Arc::new(Diffuse::new(0.8, 0.2, 0.2));
     let blue = Arc::new(Metal::new(0.2, 0.2, 0.8, 0.5));
     let glass = Arc::new(Dielectric::new(1.5));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(0., -100.5, -1.),
         100.,
         ground.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(-1., 0., -1.),
         0.5,
         red.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(1., 0., -1.),
         0.5,
         blue.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_area_light(Arc::new(Sphere::from(
         Point3::from(-0., 1.5, -1.),
         0.5,
         light.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(-0., 1.4, -0.5),
         0.5,
         blue.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(0.1, -0.2, -0.6),
         0.3,
         glass.clone(),
     )));
-
-    world
 }
 
-fn only_light() -> HittableList {
-    let mut world = HittableList::new();
+#[allow(dead_code)]
+fn only_light(scene: &mut Scene) {
     let light = Arc::new(Light::new(1., 1., 1.));
     let ground = Arc::new(Diffuse::new(0.8, 0.8, 0.0));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_object(Arc::new(Sphere::from(
         Point3::from(0., -100.5, -1.),
         100.,
         ground.clone(),
     )));
 
-    world.add(Arc::new(Sphere::from(
+    scene.add_area_light(Arc::new(Sphere::from(
         Point3::from(-0., 1.5, -1.),
         0.5,
         light.clone(),
     )));
-    world
 }
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use super::microfacet::{fresnel_conductor, reflect, Frame, TrowbridgeReitz};
+use super::{Color, HitRecord, Material, Ray, Sampler, Scatter, Vec3};
+use crate::utils::spectrum::between_primaries;
+
+/// Metals with a measured complex index of refraction, given at the red,
+/// green and blue primaries.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+}
+
+impl ConductorPreset {
+    /// Real and imaginary parts of the index of refraction.
+    pub fn ior(&self) -> (Color, Color) {
+        match self {
+            ConductorPreset::Gold => (
+                Color::from(0.143, 0.374, 1.442),
+                Color::from(3.983, 2.385, 1.603),
+            ),
+            ConductorPreset::Copper => (
+                Color::from(0.200, 0.924, 1.102),
+                Color::from(3.912, 2.452, 2.142),
+            ),
+            ConductorPreset::Aluminium => (
+                Color::from(1.657, 0.880, 0.521),
+                Color::from(9.224, 6.270, 4.837),
+            ),
+            ConductorPreset::Silver => (
+                Color::from(0.155, 0.117, 0.138),
+                Color::from(4.828, 3.122, 2.147),
+            ),
+        }
+    }
+}
+
+/// Metal reflecting by the Fresnel equations of its complex index of
+/// refraction `eta + i k`, off GGX microfacets of the given roughness.
+pub struct Conductor {
+    eta: Color,
+    k: Color,
+    distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    pub fn from(eta: Color, k: Color, roughness: f64) -> Self {
+        Conductor {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::from(roughness),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn preset(preset: ConductorPreset, roughness: f64) -> Self {
+        let (eta, k) = preset.ior();
+        Self::from(eta, k, roughness)
+    }
+
+    /// Reflectance at the primaries, or at the wavelengths the ray carries
+    /// with the index of refraction interpolated between the primaries.
+    fn fresnel(&self, ray: &Ray, cos: f64) -> Color {
+        match ray.wavelengths() {
+            Some(wavelengths) => wavelengths.evaluate(|lambda| {
+                let eta = between_primaries(self.eta, lambda);
+                fresnel_conductor(cos, eta, between_primaries(self.k, lambda))
+            }),
+            None => Color::from(
+                fresnel_conductor(cos, self.eta.x(), self.k.x()),
+                fresnel_conductor(cos, self.eta.y(), self.k.y()),
+                fresnel_conductor(cos, self.eta.z(), self.k.z()),
+            ),
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        if wo.z() <= 0. {
+            return Scatter::Absorbed;
+        }
+        if self.distribution.is_smooth() {
+            let wi = Vec3::from(-wo.x(), -wo.y(), wo.z());
+            return Scatter::Scattered(
+                self.fresnel(ray, wo.z()),
+                Ray::from(rec.p, frame.to_world(wi)),
+            );
+        }
+        let wm = self.distribution.sample_wm(wo, sampler.get_2d());
+        let wi = reflect(wo, wm);
+        if wi.z() <= 0. {
+            return Scatter::Absorbed;
+        }
+        // the sampled visible normals leave the masking of wi
+        let weight = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+        Scatter::Scattered(
+            self.fresnel(ray, wo.dot(&wm)) * weight,
+            Ray::from(rec.p, frame.to_world(wi)),
+        )
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, wi: Vec3) -> Option<(Color, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        let wi = frame.to_local(wi);
+        match self.distribution.eval_reflection(wo, wi) {
+            Some((f, pdf, wm)) => Some((self.fresnel(ray, wo.dot(&wm)) * f, pdf)),
+            None => Some((Color::new(), 0.)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::HitRecord,
+        materials::{Material, Scatter},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::{Conductor, ConductorPreset};
+
+    #[test]
+    fn sampling_matches_evaluation() {
+        let material = Arc::new(Conductor::preset(ConductorPreset::Gold, 0.5));
+        let ray = Ray::from(Point3::from(-1., 1., 0.), Vec3::from(1., -1., 0.));
+        let rec = HitRecord::from(
+            &ray,
+            1.,
+            Point3::new(),
+            Vec3::from(0., 1., 0.),
+            material.clone(),
+        );
+        let mut sampler = IndependentSampler::new(2);
+        sampler.start_pixel_sample((0, 0), 0);
+        let n = 100000;
+        // the scattering weight is the evaluation over the density, and the
+        // mean weight is the albedo, below one
+        let mut albedo = Color::new();
+        for _ in 0..n {
+            match material.scatter(&ray, &rec, &mut sampler) {
+                Scatter::Scattered(weight, scattered) => {
+                    let (f, pdf) = material.eval(&ray, &rec, scattered.dir()).unwrap();
+                    assert!((f / pdf - weight).length() < 1e-6 * weight.length().max(1.));
+                    albedo += weight;
+                }
+                Scatter::Absorbed => {}
+                Scatter::Light(_) => unreachable!(),
+            }
+        }
+        let albedo = albedo / n as f64;
+        assert!(
+            albedo.x() < 1. && albedo.x() > albedo.z() && albedo.z() > 0.2,
+            "{}",
+            albedo
+        );
+
+        // the density integrates to at most one over the hemisphere
+        let m = 400;
+        let mut total = 0.;
+        for s in 0..m * m {
+            let u = ((s % m) as f64 + 0.5) / m as f64;
+            let v = ((s / m) as f64 + 0.5) / m as f64;
+            let wi = Vec3::unit_from_sample(u, v);
+            total += material.eval(&ray, &rec, wi).unwrap().1;
+        }
+        let integral = total * 4. * std::f64::consts::PI / (m * m) as f64;
+        assert!(integral <= 1.01 && integral > 0.9, "{}", integral);
+    }
+}
@@ -1,16 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use super::microfacet::{fresnel_dielectric, Frame, TrowbridgeReitz};
 use super::Color;
 use super::HitRecord;
 use super::Material;
 use super::Ray;
+use super::Sampler;
 use super::Scatter;
+use super::Vec3;
+use crate::utils::spectrum::PRIMARIES;
+
+/// Index of refraction varying with the wavelength, given in micrometers
+/// to the formulas.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Dispersion {
+    /// `n = a + b / λ²`
+    Cauchy { a: f64, b: f64 },
+    /// `n² = 1 + Σ b λ² / (λ² - c)`
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// Index of refraction at a wavelength in nanometers.
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l2 = (wavelength / 1000.).powi(2);
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let n2 = 1. + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.max(1.).sqrt()
+            }
+        }
+    }
+}
+
+fn primary(channel: usize, value: f64) -> Color {
+    match channel {
+        0 => Color::from(value, 0., 0.),
+        1 => Color::from(0., value, 0.),
+        _ => Color::from(0., 0., value),
+    }
+}
 
 pub struct Dielectric {
     refraction: f64,
+    /// Rough surfaces scatter through GGX microfacets.
+    distribution: TrowbridgeReitz,
+    /// Color of white light after crossing `tint_distance` meters inside.
+    tint: Color,
+    tint_distance: f64,
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     pub fn new(refraction: f64) -> Self {
-        Dielectric { refraction }
+        Self::rough(refraction, 0.)
+    }
+
+    /// Frosted dielectric with a perceptual `roughness` in `[0, 1]`.
+    pub fn rough(refraction: f64, roughness: f64) -> Self {
+        Dielectric {
+            refraction,
+            distribution: TrowbridgeReitz::from(roughness),
+            tint: Color::from(1., 1., 1.),
+            tint_distance: 1.,
+            dispersion: None,
+        }
+    }
+
+    /// Absorbs light inside the object so that `color` is what remains of
+    /// white light after crossing `distance` meters of it.
+    pub fn set_tint(&mut self, color: Color, distance: f64) {
+        assert!(
+            distance > 0. && distance.is_finite(),
+            "tinted glass needs a positive distance"
+        );
+        self.tint = color;
+        self.tint_distance = distance;
+    }
+
+    /// Replaces the index of refraction by one that splits white light.
+    pub fn set_dispersion(&mut self, dispersion: Dispersion) {
+        self.refraction = dispersion.ior(PRIMARIES[1]);
+        self.dispersion = Some(dispersion);
     }
 
     pub fn reflectance(cosine: f64, ref_ratio: f64) -> f64 {
@@ -18,18 +91,45 @@ impl Dielectric {
         let r0 = r0 * r0;
         r0 + (1. - r0) * (1. - cosine).powf(5.)
     }
-}
 
-unsafe impl Sync for Dielectric {}
-unsafe impl Send for Dielectric {}
-
-impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Scatter {
-        let refraction_ratio = if rec.front_face {
-            1. / self.refraction
+    /// Ratio of the index of refraction behind the surface to the one in
+    /// front of it.
+    fn eta(&self, rec: &HitRecord, refraction: f64) -> f64 {
+        if rec.front_face {
+            refraction
         } else {
-            self.refraction
+            1. / refraction
+        }
+    }
+
+    /// `eta` seen by each of the primaries, or of the wavelengths the ray
+    /// carries.
+    fn etas(&self, ray: &Ray, rec: &HitRecord) -> [f64; 3] {
+        let Some(dispersion) = self.dispersion else {
+            return [self.eta(rec, self.refraction); 3];
         };
+        let lambda = ray.wavelengths().map_or(PRIMARIES, |w| w.lambda());
+        lambda.map(|l| self.eta(rec, dispersion.ior(l)))
+    }
+
+    /// Fraction of the light left by the absorption along `ray` when it
+    /// leaves the object, having crossed it since its origin.
+    fn transmittance(&self, ray: &Ray, rec: &HitRecord) -> Color {
+        if rec.front_face {
+            return Color::from(1., 1., 1.);
+        }
+        // Beer-Lambert: the tint applies once per tint distance crossed
+        let crossings = rec.t * ray.dir().length() / self.tint_distance;
+        let tint = ray.reflectance(self.tint);
+        Color::from(
+            tint.x().powf(crossings),
+            tint.y().powf(crossings),
+            tint.z().powf(crossings),
+        )
+    }
+
+    fn scatter_smooth(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let refraction_ratio = 1. / self.eta(rec, self.refraction);
         let unit_dir = ray.dir().unit_vector();
         let cos_theta = rec.normal.dot(&(-unit_dir)).min(1.);
         let sin_theta = (1. - cos_theta * cos_theta).sqrt();
@@ -37,12 +137,253 @@ impl Material for Dielectric {
         let cannot_refract = refraction_ratio * sin_theta > 1.;
         let attenuation = Color::from(1., 1., 1.);
 
-        if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > rand::random() {
+        let u = sampler.get_1d();
+        if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > u {
             let dir = ray.reflect(&rec.normal);
-            return Scatter::Scattered(attenuation, Ray::from(rec.p, dir));
+            Scatter::Scattered(attenuation, Ray::from(rec.p, dir))
         } else {
             let dir = ray.refract(&rec.normal, refraction_ratio);
-            return Scatter::Scattered(attenuation, Ray::from(rec.p, dir));
+            Scatter::Scattered(attenuation, Ray::from(rec.p, dir))
+        }
+    }
+
+    /// Smooth surface whose primaries, or wavelengths, all reflect the same
+    /// way but refract apart, so that a refracted ray carries a single one.
+    fn scatter_dispersive(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let cos_theta = rec.normal.dot(&(-ray.dir().unit_vector())).min(1.);
+        let etas = self.etas(ray, rec);
+        let reflectance = etas.map(|eta| fresnel_dielectric(cos_theta, eta));
+        let mean = reflectance.iter().sum::<f64>() / 3.;
+
+        let mut u = sampler.get_1d();
+        if u < mean {
+            let weight = Color::from(reflectance[0], reflectance[1], reflectance[2]) / mean;
+            return Scatter::Scattered(weight, Ray::from(rec.p, ray.reflect(&rec.normal)));
+        }
+        u -= mean;
+        for (channel, (eta, reflectance)) in etas.into_iter().zip(reflectance).enumerate() {
+            let share = (1. - reflectance) / 3.;
+            if reflectance < 1. && (u < share || channel == 2) {
+                let dir = ray.refract(&rec.normal, 1. / eta);
+                return Scatter::Scattered(primary(channel, 3.), Ray::from(rec.p, dir));
+            }
+            u -= share;
+        }
+        Scatter::Absorbed
+    }
+
+    /// BSDF times cosine of the rough surface for each primary, and the
+    /// density of sampling `wi` with any of them.
+    fn eval_rough(&self, wo: Vec3, wi: Vec3, etas: [f64; 3]) -> (Color, f64) {
+        if self.dispersion.is_none() {
+            let (f, pdf) = self.distribution.eval_dielectric(wo, wi, etas[0]);
+            return (Color::from(f, f, f), pdf);
+        }
+        let [(r, pr), (g, pg), (b, pb)] =
+            etas.map(|eta| self.distribution.eval_dielectric(wo, wi, eta));
+        (Color::from(r, g, b), (pr + pg + pb) / 3.)
+    }
+
+    fn scatter_rough(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        let etas = self.etas(ray, rec);
+        let eta = match self.dispersion {
+            Some(_) => etas[((sampler.get_1d() * 3.) as usize).min(2)],
+            None => etas[0],
+        };
+        let u = sampler.get_1d();
+        let Some(wi) = self
+            .distribution
+            .sample_dielectric(wo, eta, u, sampler.get_2d())
+        else {
+            return Scatter::Absorbed;
+        };
+        let (f, pdf) = self.eval_rough(wo, wi, etas);
+        if pdf == 0. {
+            return Scatter::Absorbed;
         }
+        Scatter::Scattered(f / pdf, Ray::from(rec.p, frame.to_world(wi)))
+    }
+}
+
+unsafe impl Sync for Dielectric {}
+unsafe impl Send for Dielectric {}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let scatter = if !self.distribution.is_smooth() {
+            self.scatter_rough(ray, rec, sampler)
+        } else if self.dispersion.is_some() {
+            self.scatter_dispersive(ray, rec, sampler)
+        } else {
+            self.scatter_smooth(ray, rec, sampler)
+        };
+        match scatter {
+            Scatter::Scattered(attenuation, scattered) => {
+                Scatter::Scattered(attenuation * self.transmittance(ray, rec), scattered)
+            }
+            scatter => scatter,
+        }
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, wi: Vec3) -> Option<(Color, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        let wi = frame.to_local(wi);
+        let (f, pdf) = self.eval_rough(wo, wi, self.etas(ray, rec));
+        Some((f * self.transmittance(ray, rec), pdf))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::HitRecord,
+        materials::{Material, Scatter},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::{Dielectric, Dispersion};
+
+    const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+
+    #[test]
+    fn rough_sampling_matches_evaluation() {
+        let mut dispersive = Dielectric::rough(1.5, 0.4);
+        dispersive.set_dispersion(BK7);
+        let materials = [Dielectric::rough(1.5, 0.4), dispersive].map(Arc::new);
+        let origins = [Point3::from(-1., 1., 0.), Point3::from(-0.3, -1., 0.)];
+        for (material, origin) in materials.iter().flat_map(|m| origins.map(|o| (m, o))) {
+            let ray = Ray::from(origin, -origin);
+            let rec = HitRecord::from(
+                &ray,
+                1.,
+                Point3::new(),
+                Vec3::from(0., 1., 0.),
+                material.clone(),
+            );
+            let mut sampler = IndependentSampler::new(4);
+            sampler.start_pixel_sample((0, 0), 0);
+            let (mut transmitted, n) = (0, 20000);
+            for _ in 0..n {
+                if let Scatter::Scattered(weight, scattered) =
+                    material.scatter(&ray, &rec, &mut sampler)
+                {
+                    let (f, pdf) = material.eval(&ray, &rec, scattered.dir()).unwrap();
+                    assert!(pdf > 0.);
+                    assert!((f / pdf - weight).length() < 1e-6 * weight.length().max(1.));
+                    if scattered.dir().dot(&rec.normal) < 0. {
+                        transmitted += 1;
+                    }
+                }
+            }
+            // most light enters glass, some is trapped leaving it
+            assert!(transmitted > n / 2, "{}", transmitted);
+        }
+    }
+
+    #[test]
+    fn dispersion_splits_primaries() {
+        assert!((BK7.ior(587.6) - 1.5168).abs() < 1e-4);
+        let cauchy = Dispersion::Cauchy { a: 1.5, b: 0.004 };
+        assert!(cauchy.ior(450.) > cauchy.ior(650.));
+
+        let mut material = Dielectric::new(1.5);
+        material.set_dispersion(BK7);
+        let material = Arc::new(material);
+        let ray = Ray::from(Point3::from(-1., 1., 0.), Vec3::from(1., -1., 0.));
+        let rec = HitRecord::from(
+            &ray,
+            1.,
+            Point3::new(),
+            Vec3::from(0., 1., 0.),
+            material.clone(),
+        );
+        let mut sampler = IndependentSampler::new(4);
+        sampler.start_pixel_sample((0, 0), 0);
+        let n = 20000;
+        let (mut reflected, mut transmitted) = (Color::new(), Color::new());
+        let (mut red, mut blue) = (Vec3::new(), Vec3::new());
+        for _ in 0..n {
+            let Scatter::Scattered(weight, scattered) = material.scatter(&ray, &rec, &mut sampler)
+            else {
+                panic!("glass absorbs nothing");
+            };
+            if scattered.dir().y() > 0. {
+                reflected += weight;
+            } else {
+                transmitted += weight;
+                if weight.x() > 0. {
+                    red = scattered.dir().unit_vector();
+                } else if weight.z() > 0. {
+                    blue = scattered.dir().unit_vector();
+                }
+            }
+        }
+        // energy is conserved per primary, and blue bends more than red
+        let total = (reflected + transmitted) / n as f64;
+        assert!(
+            (total - Color::from(1., 1., 1.)).length() < 0.05,
+            "{}",
+            total
+        );
+        let reflectance = reflected.x() / n as f64;
+        assert!(reflectance > 0.02 && reflectance < 0.1, "{}", reflectance);
+        assert!(blue.x() < red.x() && blue.x() > 0.4, "{} {}", red, blue);
+    }
+
+    #[test]
+    fn tinted_glass_absorbs_by_distance() {
+        let mut material = Dielectric::new(1.5);
+        material.set_tint(Color::from(0.5, 0.25, 1.), 2.);
+        let material = Arc::new(material);
+        // a ray leaving the glass after 4 meters inside it
+        let ray = Ray::from(Point3::new(), Vec3::from(0., 2., 0.));
+        let rec = HitRecord::from(
+            &ray,
+            2.,
+            Point3::from(0., 4., 0.),
+            Vec3::from(0., 1., 0.),
+            material.clone(),
+        );
+        assert!(!rec.front_face);
+        let mut sampler = IndependentSampler::new(4);
+        sampler.start_pixel_sample((0, 0), 0);
+        let Scatter::Scattered(weight, _) = material.scatter(&ray, &rec, &mut sampler) else {
+            unreachable!()
+        };
+        assert!(
+            (weight - Color::from(0.25, 0.0625, 1.)).length() < 1e-9,
+            "{}",
+            weight
+        );
+        // entering the glass absorbs nothing
+        let ray = Ray::from(Point3::from(0., 8., 0.), Vec3::from(0., -1., 0.));
+        let rec = HitRecord::from(
+            &ray,
+            4.,
+            Point3::from(0., 4., 0.),
+            Vec3::from(0., 1., 0.),
+            material.clone(),
+        );
+        let Scatter::Scattered(weight, _) = material.scatter(&ray, &rec, &mut sampler) else {
+            unreachable!()
+        };
+        assert_eq!(weight, Color::from(1., 1., 1.));
     }
 }
@@ -1,6 +1,7 @@
 use super::HitRecord;
 use super::Material;
 use super::Ray;
+use super::Sampler;
 use super::Scatter;
 use super::{Color, Vec3};
 
@@ -22,14 +23,25 @@ unsafe impl Sync for Diffuse {}
 unsafe impl Send for Diffuse {}
 
 impl Material for Diffuse {
-    fn scatter(&self, _ray: &crate::ray::Ray, rec: &HitRecord) -> Scatter {
-        loop {
-            let scatter_dir = rec.normal + Vec3::random_unit_sphere().unit_vector();
-            if !scatter_dir.near_zero() {
-                let scattered_ray = Ray::from(rec.p, scatter_dir);
-                let attenuation = self.tint;
-                return Scatter::Scattered(attenuation, scattered_ray);
-            }
+    fn scatter(
+        &self,
+        ray: &crate::ray::Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Scatter {
+        let (u, v) = sampler.get_2d();
+        let mut scatter_dir = rec.normal + Vec3::unit_from_sample(u, v);
+        if scatter_dir.near_zero() {
+            scatter_dir = rec.normal;
         }
+        let scattered_ray = Ray::from(rec.p, scatter_dir);
+        let attenuation = ray.reflectance(self.tint);
+        Scatter::Scattered(attenuation, scattered_ray)
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, wi: Vec3) -> Option<(Color, f64)> {
+        // scattered rays are cosine distributed
+        let cos = rec.normal.dot(&wi).max(0.) / std::f64::consts::PI;
+        Some((ray.reflectance(self.tint) * cos, cos))
     }
 }
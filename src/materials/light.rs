@@ -1,9 +1,29 @@
-use crate::{geometries::HitRecord, ray::Ray, utils::vec3::Color};
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    geometries::HitRecord,
+    lights::ies::{IesProfile, Photometry},
+    ray::Ray,
+    sampler::Sampler,
+    textures::{SolidColor, Texture},
+    utils::{spectrum::Blackbody, vec3::Color},
+};
 
 use super::{Material, Scatter};
 
+/// Diffuse emitter. The radiance leaving the surface is the texture color
+/// times `intensity`, from the front side only unless `two_sided` is set.
+///
+/// With a `profile`, whose nadir follows the surface normal, the intensity of
+/// every patch of the surface follows the measured distribution instead.
+/// With a `blackbody`, the color is taken to be a multiple of its color, and
+/// spectral rendering follows its spectrum.
 pub struct Light {
-    tint: Color,
+    texture: Arc<dyn Texture>,
+    pub intensity: f64,
+    pub two_sided: bool,
+    pub profile: Option<Arc<IesProfile>>,
+    pub blackbody: Option<Blackbody>,
 }
 
 impl Light {
@@ -12,7 +32,35 @@ impl Light {
     }
 
     pub fn from(tint: Color) -> Self {
-        Light { tint }
+        Self::textured(Arc::new(SolidColor::from(tint)))
+    }
+
+    /// Emitter of the color of a blackbody at `kelvin`.
+    #[allow(dead_code)]
+    pub fn blackbody(kelvin: f64) -> Self {
+        let blackbody = Blackbody::from(kelvin);
+        Light {
+            blackbody: Some(blackbody),
+            ..Self::from(blackbody.rgb())
+        }
+    }
+
+    pub fn textured(texture: Arc<dyn Texture>) -> Self {
+        Light {
+            texture,
+            intensity: 1.,
+            two_sided: false,
+            profile: None,
+            blackbody: None,
+        }
+    }
+
+    /// Sets `intensity` so that a surface of the given area emits `power`
+    /// watts, taking the average texture color as the spectrum.
+    pub fn set_power(&mut self, power: f64, area: f64) {
+        let sides = if self.two_sided { 2. } else { 1. };
+        let radiance = self.texture.average().luminance() * PI * area * sides;
+        self.intensity = if radiance > 0. { power / radiance } else { 0. };
     }
 }
 
@@ -20,7 +68,36 @@ unsafe impl Sync for Light {}
 unsafe impl Send for Light {}
 
 impl Material for Light {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Scatter {
-        Scatter::Light(self.tint)
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Scatter {
+        Scatter::Light(self.emitted(ray, rec))
+    }
+
+    fn emitted(&self, ray: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face && !self.two_sided {
+            return Color::new();
+        }
+        let emitted = self.texture.value(rec.uv) * self.intensity;
+        match &self.profile {
+            Some(profile) => {
+                // radiance is intensity over the projected area
+                let wo = -ray.dir().unit_vector();
+                let photometry = Photometry::from(profile.clone(), rec.normal);
+                emitted * (photometry.factor(wo) / wo.dot(&rec.normal).max(1e-2))
+            }
+            None => emitted,
+        }
+    }
+
+    fn illuminant(&self, ray: &Ray, emitted: Color) -> Color {
+        ray.emission(emitted, self.blackbody.as_ref())
+    }
+
+    fn emitted_power(&self, area: f64) -> f64 {
+        let sides = if self.two_sided { 2. } else { 1. };
+        self.texture.average().luminance() * self.intensity * PI * area * sides
+    }
+
+    fn emits_both_sides(&self) -> bool {
+        self.two_sided
     }
 }
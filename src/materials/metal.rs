@@ -2,6 +2,7 @@ use super::Color;
 use super::HitRecord;
 use super::Material;
 use super::Ray;
+use super::Sampler;
 use super::Scatter;
 use super::Vec3;
 
@@ -24,16 +25,18 @@ unsafe impl Sync for Metal {}
 unsafe impl Send for Metal {}
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Scatter {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
         let reflected = ray.reflect(&rec.normal);
+        let (u, v) = sampler.get_2d();
         if reflected.dot(&rec.normal) > 0. {
-            let scattered = Ray::from(
-                rec.p,
-                reflected + Vec3::random_unit_sphere().unit_vector() * self.fuzz,
-            );
-            let attenuation = self.tint;
+            let scattered = Ray::from(rec.p, reflected + Vec3::unit_from_sample(u, v) * self.fuzz);
+            let attenuation = ray.reflectance(self.tint);
             return Scatter::Scattered(attenuation, scattered);
         }
         Scatter::Absorbed
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
 }
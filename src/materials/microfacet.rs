@@ -0,0 +1,353 @@
+use std::f64::consts::PI;
+
+use crate::utils::vec3::Vec3;
+
+/// Roughness below which a microfacet surface is treated as perfectly
+/// smooth.
+const SMOOTH: f64 = 1e-3;
+
+/// Shading frame whose z axis is the surface normal.
+pub struct Frame {
+    s: Vec3,
+    t: Vec3,
+    n: Vec3,
+}
+
+impl Frame {
+    pub fn from(n: Vec3) -> Self {
+        let (s, t) = n.orthonormal_basis();
+        Frame { s, t, n }
+    }
+
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::from(v.dot(&self.s), v.dot(&self.t), v.dot(&self.n))
+    }
+
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        self.s * v.x() + self.t * v.y() + self.n * v.z()
+    }
+}
+
+/// Isotropic Trowbridge–Reitz (GGX) distribution of microfacet normals,
+/// in the shading frame.
+#[derive(Clone, Copy, Debug)]
+pub struct TrowbridgeReitz {
+    alpha: f64,
+}
+
+impl TrowbridgeReitz {
+    /// Distribution of a perceptual `roughness` in `[0, 1]`, whose square is
+    /// the width of the distribution.
+    pub fn from(roughness: f64) -> Self {
+        let roughness = roughness.clamp(0., 1.);
+        TrowbridgeReitz {
+            alpha: roughness * roughness,
+        }
+    }
+
+    /// Whether the surface reflects and refracts like a perfect mirror.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH
+    }
+
+    /// Density of microfacets with normal `wm` per unit area and solid angle.
+    pub fn d(&self, wm: Vec3) -> f64 {
+        let cos2 = wm.z() * wm.z();
+        if cos2 == 0. {
+            return 0.;
+        }
+        let tan2 = (1. - cos2).max(0.) / cos2;
+        let a2 = self.alpha * self.alpha;
+        let e = 1. + tan2 / a2;
+        1. / (PI * a2 * cos2 * cos2 * e * e)
+    }
+
+    fn lambda(&self, w: Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 == 0. {
+            return f64::INFINITY;
+        }
+        let tan2 = (1. - cos2).max(0.) / cos2;
+        ((1. + self.alpha * self.alpha * tan2).sqrt() - 1.) / 2.
+    }
+
+    /// Fraction of the microfacets seen from `w` that are not masked.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1. / (1. + self.lambda(w))
+    }
+
+    /// Fraction of the microfacets seen from both `wo` and `wi`.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1. / (1. + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals visible from `w`, which `sample_wm` follows.
+    pub fn pdf(&self, w: Vec3, wm: Vec3) -> f64 {
+        if w.z() == 0. {
+            return 0.;
+        }
+        self.g1(w) / w.z().abs() * self.d(wm) * w.dot(&wm).abs()
+    }
+
+    /// Samples a microfacet normal visible from `w`, after Heitz.
+    pub fn sample_wm(&self, w: Vec3, u: (f64, f64)) -> Vec3 {
+        let w = if w.z() < 0. { -w } else { w };
+        // stretch the view to the configuration of a unit hemisphere
+        let wh = Vec3::from(self.alpha * w.x(), self.alpha * w.y(), w.z()).unit_vector();
+        let len2 = wh.x() * wh.x() + wh.y() * wh.y();
+        let t1 = if len2 > 0. {
+            Vec3::from(-wh.y(), wh.x(), 0.) / len2.sqrt()
+        } else {
+            Vec3::from(1., 0., 0.)
+        };
+        let t2 = wh.cross(&t1);
+
+        // uniform point on the projected hemisphere, warped to the visible part
+        let r = u.0.sqrt();
+        let phi = 2. * PI * u.1;
+        let p1 = r * phi.cos();
+        let s = (1. + wh.z()) / 2.;
+        let p2 = (1. - s) * (1. - p1 * p1).max(0.).sqrt() + s * r * phi.sin();
+        let p3 = (1. - p1 * p1 - p2 * p2).max(0.).sqrt();
+        let nh = t1 * p1 + t2 * p2 + wh * p3;
+        Vec3::from(self.alpha * nh.x(), self.alpha * nh.y(), nh.z().max(1e-6)).unit_vector()
+    }
+
+    /// BSDF times cosine without the Fresnel term, and the density of
+    /// reflecting off a visible normal from `wo` to `wi`, with the normal.
+    pub fn eval_reflection(&self, wo: Vec3, wi: Vec3) -> Option<(f64, f64, Vec3)> {
+        if wo.z() <= 0. || wi.z() <= 0. {
+            return None;
+        }
+        let wm = (wo + wi).unit_vector();
+        let f = self.d(wm) * self.g(wo, wi) / (4. * wo.z());
+        let pdf = self.pdf(wo, wm) / (4. * wo.dot(&wm));
+        Some((f, pdf, wm))
+    }
+
+    /// Like `eval_reflection` for refracting from `wo` to `wi` below the
+    /// surface, `eta` as in `refract`.
+    pub fn eval_transmission(&self, wo: Vec3, wi: Vec3, eta: f64) -> Option<(f64, f64, Vec3)> {
+        if wo.z() <= 0. || wi.z() >= 0. {
+            return None;
+        }
+        let wm = wi * eta + wo;
+        if wm.length_squared() == 0. {
+            return None;
+        }
+        let wm = wm.unit_vector();
+        let wm = if wm.z() < 0. { -wm } else { wm };
+        if wm.dot(&wo) <= 0. || wm.dot(&wi) >= 0. {
+            return None;
+        }
+        let denom = wi.dot(&wm) + wo.dot(&wm) / eta;
+        let dwm_dwi = wi.dot(&wm).abs() / (denom * denom);
+        // radiance is compressed into the smaller solid angle
+        let f = self.d(wm) * self.g(wo, wi) * dwm_dwi * wo.dot(&wm) / wo.z() / (eta * eta);
+        let pdf = self.pdf(wo, wm) * dwm_dwi;
+        Some((f, pdf, wm))
+    }
+
+    /// Samples the reflection or refraction through a rough dielectric
+    /// interface, choosing by the Fresnel reflectance of the sampled normal.
+    pub fn sample_dielectric(&self, wo: Vec3, eta: f64, u: f64, u2: (f64, f64)) -> Option<Vec3> {
+        let wm = self.sample_wm(wo, u2);
+        if u < fresnel_dielectric(wo.dot(&wm), eta) {
+            Some(reflect(wo, wm)).filter(|wi| wi.z() > 0.)
+        } else {
+            refract(wo, wm, eta).filter(|wi| wi.z() < 0.)
+        }
+    }
+
+    /// BSDF times cosine and density of `sample_dielectric`.
+    pub fn eval_dielectric(&self, wo: Vec3, wi: Vec3, eta: f64) -> (f64, f64) {
+        let reflected = wi.z() > 0.;
+        let lobe = if reflected {
+            self.eval_reflection(wo, wi)
+        } else {
+            self.eval_transmission(wo, wi, eta)
+        };
+        let Some((f, pdf, wm)) = lobe else {
+            return (0., 0.);
+        };
+        let reflectance = fresnel_dielectric(wo.dot(&wm), eta);
+        let share = if reflected {
+            reflectance
+        } else {
+            1. - reflectance
+        };
+        (f * share, pdf * share)
+    }
+}
+
+pub fn reflect(wo: Vec3, n: Vec3) -> Vec3 {
+    -wo + n * (2. * wo.dot(&n))
+}
+
+/// Direction refracted from `wo` through a surface with normal `n` on the
+/// side of `wo`, with `eta` the ratio of the index of refraction across
+/// the surface to the one on the side of `wo`. `None` on total internal
+/// reflection.
+pub fn refract(wo: Vec3, n: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo.dot(&n);
+    let sin2_t = (1. - cos_i * cos_i).max(0.) / (eta * eta);
+    if sin2_t >= 1. {
+        return None;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    Some(-wo / eta + n * (cos_i / eta - cos_t))
+}
+
+/// Fresnel reflectance of unpolarised light arriving at `cos_i` to the
+/// normal of a dielectric interface, `eta` as in `refract`.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_i < 0. {
+        (-cos_i, 1. / eta)
+    } else {
+        (cos_i.min(1.), eta)
+    };
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1. {
+        return 1.;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.
+}
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn from(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    fn add(self, o: Complex) -> Self {
+        Complex::from(self.re + o.re, self.im + o.im)
+    }
+
+    fn sub(self, o: Complex) -> Self {
+        Complex::from(self.re - o.re, self.im - o.im)
+    }
+
+    fn mul(self, o: Complex) -> Self {
+        Complex::from(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+
+    fn div(self, o: Complex) -> Self {
+        let scale = 1. / (o.re * o.re + o.im * o.im);
+        Complex::from(
+            (self.re * o.re + self.im * o.im) * scale,
+            (self.im * o.re - self.re * o.im) * scale,
+        )
+    }
+
+    fn norm(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0. {
+            return Complex::from(0., 0.);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0. {
+            Complex::from(t1, t2)
+        } else {
+            Complex::from(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+/// Fresnel reflectance of a conductor with the complex index of refraction
+/// `eta + i k`, for light arriving at `cos_i` to the normal.
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i = Complex::from(cos_i.clamp(0., 1.), 0.);
+    let eta = Complex::from(eta, k);
+    let one = Complex::from(1., 0.);
+    let sin2_i = one.sub(cos_i.mul(cos_i));
+    let sin2_t = sin2_i.div(eta.mul(eta));
+    let cos_t = one.sub(sin2_t).sqrt();
+    let parallel = eta.mul(cos_i).sub(cos_t).div(eta.mul(cos_i).add(cos_t));
+    let perpendicular = cos_i.sub(eta.mul(cos_t)).div(cos_i.add(eta.mul(cos_t)));
+    (parallel.norm() + perpendicular.norm()) / 2.
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::{
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::Vec3,
+    };
+
+    use super::{fresnel_conductor, fresnel_dielectric, refract, TrowbridgeReitz};
+
+    #[test]
+    fn visible_normals_follow_density() {
+        let mut sampler = IndependentSampler::new(5);
+        sampler.start_pixel_sample((0, 0), 0);
+        for roughness in [0.4, 0.6, 0.9] {
+            let distribution = TrowbridgeReitz::from(roughness);
+            for wo in [Vec3::from(0., 0., 1.), Vec3::from(0.6, 0., 0.8)] {
+                // integrate the density and the mean normal over the
+                // hemisphere, against the mean of the sampled normals
+                let m = 1000;
+                let (mut total, mut mean) = (0., Vec3::new());
+                for s in 0..m * m {
+                    let u = ((s % m) as f64 + 0.5) / m as f64;
+                    let v = ((s / m) as f64 + 0.5) / m as f64;
+                    let wm = Vec3::unit_from_sample(u, v);
+                    if wm.z() > 0. && wm.dot(&wo) > 0. {
+                        let pdf = distribution.pdf(wo, wm) * 4. * PI / (m * m) as f64;
+                        total += pdf;
+                        mean += wm * pdf;
+                    }
+                }
+                assert!((total - 1.).abs() < 0.01, "{} {}", roughness, total);
+
+                let n = 100000;
+                let mut sampled = Vec3::new();
+                for _ in 0..n {
+                    let wm = distribution.sample_wm(wo, sampler.get_2d());
+                    assert!(wm.z() > 0.);
+                    sampled += wm / n as f64;
+                }
+                assert!((sampled - mean).length() < 0.01, "{} {}", sampled, mean);
+            }
+        }
+    }
+
+    #[test]
+    fn fresnel_limits() {
+        assert!((fresnel_dielectric(1., 1.5) - 0.04).abs() < 1e-9);
+        assert_eq!(fresnel_dielectric(1., 1.), 0.);
+        assert_eq!(fresnel_dielectric(0.1, 1. / 1.5), 1.);
+        assert!((fresnel_dielectric(-1., 1. / 1.5) - 0.04).abs() < 1e-9);
+        // a conductor without absorption is a dielectric
+        assert!((fresnel_conductor(0.7, 1.5, 0.) - fresnel_dielectric(0.7, 1.5)).abs() < 1e-9);
+        // gold reflects red more than blue at normal incidence
+        let (red, blue) = (
+            fresnel_conductor(1., 0.143, 3.983),
+            fresnel_conductor(1., 1.442, 1.603),
+        );
+        assert!(red > 0.9 && blue < 0.5, "{} {}", red, blue);
+        assert!(fresnel_conductor(0., 0.143, 3.983) > 0.999);
+
+        let wo = Vec3::from(0.6, 0., 0.8);
+        let wi = refract(wo, Vec3::from(0., 0., 1.), 1.5).unwrap();
+        assert!((wi.x() * 1.5 + 0.6).abs() < 1e-9 && wi.z() < 0.);
+        assert!(refract(wo, Vec3::from(0., 0., 1.), 0.5).is_none());
+    }
+}
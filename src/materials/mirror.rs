@@ -2,6 +2,7 @@ use super::Color;
 use super::HitRecord;
 use super::Material;
 use super::Ray;
+use super::Sampler;
 use super::Scatter;
 
 pub struct Mirror;
@@ -16,9 +17,13 @@ unsafe impl Sync for Mirror {}
 unsafe impl Send for Mirror {}
 
 impl Material for Mirror {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Scatter {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, _sampler: &mut dyn Sampler) -> Scatter {
         let attenuation = Color::from(1., 1., 1.);
         let dir = ray.reflect(&rec.normal);
-        return Scatter::Scattered(attenuation, Ray::from(rec.p, dir));
+        Scatter::Scattered(attenuation, Ray::from(rec.p, dir))
+    }
+
+    fn is_specular(&self) -> bool {
+        true
     }
 }
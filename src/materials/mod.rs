@@ -1,12 +1,17 @@
 use crate::geometries::HitRecord;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::utils::vec3::{Color, Vec3};
 
+pub mod conductor;
 pub mod dielectric;
 pub mod diffuse;
 pub mod light;
 pub mod metal;
+pub mod microfacet;
 pub mod mirror;
+pub mod phase;
+pub mod principled;
 
 pub enum Scatter {
     Absorbed,
@@ -15,5 +20,39 @@ pub enum Scatter {
 }
 
 pub trait Material: Send + Sync {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Scatter;
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter;
+
+    /// Whether scattered rays follow the mirror or refracted direction, which
+    /// puts the light they gather in the specular AOV passes.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// BSDF times the cosine term for light arriving from the unit direction
+    /// `wi`, and the density of `scatter` choosing `wi`. `None` for materials
+    /// that can not be lit by sampling light sources.
+    fn eval(&self, _ray: &Ray, _rec: &HitRecord, _wi: Vec3) -> Option<(Color, f64)> {
+        None
+    }
+
+    /// Radiance the surface emits back along `ray`.
+    fn emitted(&self, _ray: &Ray, _rec: &HitRecord) -> Color {
+        Color::new()
+    }
+
+    /// Radiance `emitted` by the surface in linear sRGB, as carried by `ray`.
+    fn illuminant(&self, ray: &Ray, emitted: Color) -> Color {
+        ray.illuminant(emitted)
+    }
+
+    /// Estimated power in watts emitted by `area` square meters of the
+    /// surface, used to choose between light sources.
+    fn emitted_power(&self, _area: f64) -> f64 {
+        0.
+    }
+
+    /// Whether the surface emits from its back face as well.
+    fn emits_both_sides(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,115 @@
+use std::f64::consts::PI;
+
+use super::microfacet::Frame;
+use super::{Color, HitRecord, Material, Ray, Sampler, Scatter, Vec3};
+
+/// Distribution of the directions light scatters to inside a medium, by
+/// the angle to the direction it travelled.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PhaseFunction {
+    Isotropic,
+    /// Scatters forward for an asymmetry in `(0, 1)` and backward below 0.
+    HenyeyGreenstein(f64),
+}
+
+impl PhaseFunction {
+    /// Density of scattering at the angle whose cosine is `cos`.
+    pub fn p(&self, cos: f64) -> f64 {
+        match *self {
+            PhaseFunction::Isotropic => 1. / (4. * PI),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let denom = 1. + g * g - 2. * g * cos;
+                (1. - g * g) / (4. * PI * denom * denom.max(0.).sqrt())
+            }
+        }
+    }
+
+    /// Direction scattered to by light travelling along the unit `dir`.
+    pub fn sample(&self, dir: Vec3, (u, v): (f64, f64)) -> Vec3 {
+        let cos = match *self {
+            PhaseFunction::HenyeyGreenstein(g) if g.abs() >= 1e-3 => {
+                let s = (1. - g * g) / (1. - g + 2. * g * u);
+                ((1. + g * g - s * s) / (2. * g)).clamp(-1., 1.)
+            }
+            _ => 1. - 2. * u,
+        };
+        let sin = (1. - cos * cos).max(0.).sqrt();
+        let phi = 2. * PI * v;
+        Frame::from(dir).to_world(Vec3::from(sin * phi.cos(), sin * phi.sin(), cos))
+    }
+}
+
+/// Scattering inside a medium, where the fraction `albedo` of the light
+/// that is not absorbed leaves by the phase function.
+pub struct Phase {
+    albedo: Color,
+    function: PhaseFunction,
+}
+
+impl Phase {
+    pub fn from(albedo: Color, function: PhaseFunction) -> Self {
+        Phase { albedo, function }
+    }
+}
+
+impl Material for Phase {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let wi = self
+            .function
+            .sample(ray.dir().unit_vector(), sampler.get_2d());
+        Scatter::Scattered(ray.reflectance(self.albedo), Ray::from(rec.p, wi))
+    }
+
+    fn eval(&self, ray: &Ray, _rec: &HitRecord, wi: Vec3) -> Option<(Color, f64)> {
+        let p = self.function.p(ray.dir().unit_vector().dot(&wi));
+        Some((ray.reflectance(self.albedo) * p, p))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::utils::vec3::Vec3;
+
+    use super::PhaseFunction;
+
+    #[test]
+    fn sampling_follows_density() {
+        let dir = Vec3::from(1., 2., -1.).unit_vector();
+        for function in [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein(0.7),
+            PhaseFunction::HenyeyGreenstein(-0.4),
+        ] {
+            // the density integrates to one over the sphere
+            let m = 2000;
+            let integral = (0..m)
+                .map(|i| function.p(-1. + 2. * (i as f64 + 0.5) / m as f64))
+                .sum::<f64>()
+                * 2.
+                / m as f64
+                * 2.
+                * PI;
+            assert!((integral - 1.).abs() < 1e-3, "{:?} {}", function, integral);
+
+            // and the mean cosine of sampled directions is the asymmetry
+            let n = 400;
+            let mean = (0..n * n)
+                .map(|s| {
+                    let u = ((s % n) as f64 + 0.5) / n as f64;
+                    let v = ((s / n) as f64 + 0.5) / n as f64;
+                    let wi = function.sample(dir, (u, v));
+                    assert!((wi.length() - 1.).abs() < 1e-9);
+                    wi.dot(&dir)
+                })
+                .sum::<f64>()
+                / (n * n) as f64;
+            let g = match function {
+                PhaseFunction::Isotropic => 0.,
+                PhaseFunction::HenyeyGreenstein(g) => g,
+            };
+            assert!((mean - g).abs() < 1e-3, "{:?} {}", function, mean);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::f64::consts::PI;
+
+use super::microfacet::{reflect, Frame, TrowbridgeReitz};
+use super::{Color, HitRecord, Material, Ray, Sampler, Scatter, Vec3};
+use crate::utils::obj::MtlMaterial;
+
+/// Roughness of the smoothest surfaces, whose lobes stay narrow but can
+/// still be evaluated.
+const MIN_ROUGHNESS: f64 = 0.05;
+
+/// Weight of `(1 - cos)^5` in the Schlick approximation.
+fn schlick_weight(cos: f64) -> f64 {
+    let m = (1. - cos).clamp(0., 1.);
+    m * m * m * m * m
+}
+
+fn mix(a: Color, b: Color, t: f64) -> Color {
+    a * (1. - t) + b * t
+}
+
+/// Disney style uber material, layering a diffuse base with sheen and
+/// subsurface, a GGX specular lobe, glass-like transmission and a clear
+/// coat. Every parameter but the index of refraction lies in `[0, 1]`.
+pub struct Principled {
+    pub base_color: Color,
+    /// Blends the dielectric base into a metal tinted by the base color.
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Reflectance at normal incidence of the dielectric base, 0.5 being 4%.
+    pub specular: f64,
+    /// Tints the dielectric specular towards the base color.
+    pub specular_tint: f64,
+    /// Retro-reflective rim of cloth.
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// Second, white specular lobe on top.
+    pub clearcoat: f64,
+    /// Glossiness of the clear coat, 1 being the sharpest.
+    pub clearcoat_gloss: f64,
+    /// Blends the dielectric base into rough glass tinted by the base color.
+    pub transmission: f64,
+    /// Index of refraction of the transmissive part.
+    pub ior: f64,
+    /// Flattens the diffuse lobe like light scattered below the surface.
+    pub subsurface: f64,
+}
+
+/// Probabilities of sampling each lobe.
+struct Lobes {
+    diffuse: f64,
+    specular: f64,
+    transmission: f64,
+    clearcoat: f64,
+}
+
+impl Principled {
+    /// Rough dielectric of the given color.
+    pub fn from(base_color: Color) -> Self {
+        Principled {
+            base_color,
+            metallic: 0.,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.,
+            sheen: 0.,
+            sheen_tint: 0.5,
+            clearcoat: 0.,
+            clearcoat_gloss: 1.,
+            transmission: 0.,
+            ior: 1.5,
+            subsurface: 0.,
+        }
+    }
+
+    /// Principled material of an MTL material, converting the Phong
+    /// exponent and specular color of classic libraries to roughness and
+    /// specular, and treating dissolve as transmission.
+    pub fn from_mtl(mtl: &MtlMaterial) -> Self {
+        let mut material = Principled::from(mtl.diffuse);
+        material.roughness = mtl.roughness.unwrap_or_else(|| {
+            let alpha = (2. / (mtl.shininess.max(0.) + 2.)).sqrt();
+            alpha.sqrt()
+        });
+        if mtl.roughness.is_none() && mtl.metallic.is_none() {
+            material.specular = (mtl.specular.luminance() / 0.08).clamp(0., 1.);
+        }
+        material.metallic = mtl.metallic.unwrap_or(0.);
+        material.sheen = mtl.sheen.unwrap_or(0.);
+        material.clearcoat = mtl.clearcoat.unwrap_or(0.);
+        material.clearcoat_gloss = 1. - mtl.clearcoat_roughness.unwrap_or(0.);
+        material.transmission = 1. - mtl.dissolve.clamp(0., 1.);
+        material.ior = mtl.ior.unwrap_or(1.5);
+        material
+    }
+
+    fn distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::from(self.roughness.max(MIN_ROUGHNESS))
+    }
+
+    fn clearcoat_distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::from(0.3 - 0.28 * self.clearcoat_gloss.clamp(0., 1.))
+    }
+
+    /// Base color with its luminance removed.
+    fn tint(&self, base: Color) -> Color {
+        let luminance = self.base_color.luminance();
+        if luminance > 0. {
+            base / luminance
+        } else {
+            Color::from(1., 1., 1.)
+        }
+    }
+
+    fn diffuse_weight(&self) -> f64 {
+        (1. - self.metallic) * (1. - self.transmission)
+    }
+
+    fn transmission_weight(&self) -> f64 {
+        (1. - self.metallic) * self.transmission
+    }
+
+    fn lobes(&self) -> Lobes {
+        let diffuse = self.diffuse_weight();
+        let specular = (1. - self.transmission_weight()) * (0.25 + 0.75 * self.metallic);
+        let transmission = self.transmission_weight();
+        let clearcoat = 0.25 * self.clearcoat;
+        let total = diffuse + specular + transmission + clearcoat;
+        if total == 0. {
+            return Lobes {
+                diffuse: 0.,
+                specular: 1.,
+                transmission: 0.,
+                clearcoat: 0.,
+            };
+        }
+        Lobes {
+            diffuse: diffuse / total,
+            specular: specular / total,
+            transmission: transmission / total,
+            clearcoat: clearcoat / total,
+        }
+    }
+
+    /// Diffuse base with retro-reflection, subsurface flattening and sheen,
+    /// times the cosine.
+    fn diffuse(&self, wo: Vec3, wi: Vec3, base: Color) -> Color {
+        if wi.z() <= 0. {
+            return Color::new();
+        }
+        let wh = (wo + wi).unit_vector();
+        let cos_d = wi.dot(&wh);
+        let (fl, fv) = (schlick_weight(wi.z()), schlick_weight(wo.z()));
+        let rr = 2. * self.roughness * cos_d * cos_d;
+        let lambert = (1. - fl / 2.) * (1. - fv / 2.);
+        let retro = rr * (fl + fv + fl * fv * (rr - 1.));
+
+        let fss90 = self.roughness * cos_d * cos_d;
+        let fss = (1. + (fss90 - 1.) * fl) * (1. + (fss90 - 1.) * fv);
+        let ss = 1.25 * (fss * (1. / (wi.z() + wo.z()) - 0.5) + 0.5);
+        let diffuse = (lambert + retro) * (1. - self.subsurface) + ss * self.subsurface;
+
+        let sheen = mix(Color::from(1., 1., 1.), self.tint(base), self.sheen_tint)
+            * (self.sheen * schlick_weight(cos_d));
+        (base * (diffuse / PI) + sheen) * wi.z()
+    }
+
+    /// Reflectance at normal incidence of the specular lobe.
+    fn specular_color(&self, base: Color) -> Color {
+        let dielectric = mix(Color::from(1., 1., 1.), self.tint(base), self.specular_tint)
+            * (0.08 * self.specular);
+        mix(dielectric, base, self.metallic)
+    }
+
+    /// Every lobe times the cosine, and the density of sampling `wi`, with
+    /// the base color as carried by the ray.
+    fn evaluate(&self, wo: Vec3, wi: Vec3, eta: f64, base: Color) -> (Color, f64) {
+        let lobes = self.lobes();
+        let mut f = Color::new();
+        let mut pdf = 0.;
+
+        if wi.z() > 0. && self.diffuse_weight() > 0. {
+            f += self.diffuse(wo, wi, base) * self.diffuse_weight();
+            pdf += lobes.diffuse * wi.z() / PI;
+        }
+        if let Some((spec, spec_pdf, wm)) = self.distribution().eval_reflection(wo, wi) {
+            let f0 = self.specular_color(base);
+            let fresnel = mix(f0, Color::from(1., 1., 1.), schlick_weight(wo.dot(&wm)));
+            f += fresnel * (spec * (1. - self.transmission_weight()));
+            pdf += lobes.specular * spec_pdf;
+        }
+        if self.transmission_weight() > 0. {
+            let (glass, glass_pdf) = self.distribution().eval_dielectric(wo, wi, eta);
+            let tint = if wi.z() < 0. {
+                base
+            } else {
+                Color::from(1., 1., 1.)
+            };
+            f += tint * (glass * self.transmission_weight());
+            pdf += lobes.transmission * glass_pdf;
+        }
+        if self.clearcoat > 0. {
+            let distribution = self.clearcoat_distribution();
+            if let Some((coat, coat_pdf, wm)) = distribution.eval_reflection(wo, wi) {
+                let fresnel = 0.04 + 0.96 * schlick_weight(wo.dot(&wm));
+                let coat = coat * fresnel * 0.25 * self.clearcoat;
+                f += Color::from(coat, coat, coat);
+                pdf += lobes.clearcoat * coat_pdf;
+            }
+        }
+        (f, pdf)
+    }
+
+    /// Ratio of the index of refraction behind the surface to the one in
+    /// front of it.
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face {
+            self.ior
+        } else {
+            1. / self.ior
+        }
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Scatter {
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        if wo.z() <= 0. {
+            return Scatter::Absorbed;
+        }
+        let eta = self.eta(rec);
+        let lobes = self.lobes();
+        let mut u = sampler.get_1d();
+        let u2 = sampler.get_2d();
+
+        let wi = if u < lobes.diffuse {
+            let d = Vec3::from(0., 0., 1.) + Vec3::unit_from_sample(u2.0, u2.1);
+            if d.near_zero() {
+                Vec3::from(0., 0., 1.)
+            } else {
+                d.unit_vector()
+            }
+        } else if u < lobes.diffuse + lobes.specular {
+            reflect(wo, self.distribution().sample_wm(wo, u2))
+        } else if u < lobes.diffuse + lobes.specular + lobes.transmission {
+            u = (u - lobes.diffuse - lobes.specular) / lobes.transmission;
+            match self.distribution().sample_dielectric(wo, eta, u, u2) {
+                Some(wi) => wi,
+                None => return Scatter::Absorbed,
+            }
+        } else {
+            reflect(wo, self.clearcoat_distribution().sample_wm(wo, u2))
+        };
+
+        let (f, pdf) = self.evaluate(wo, wi, eta, ray.reflectance(self.base_color));
+        if pdf == 0. || f.near_zero() {
+            return Scatter::Absorbed;
+        }
+        Scatter::Scattered(f / pdf, Ray::from(rec.p, frame.to_world(wi)))
+    }
+
+    fn eval(&self, ray: &Ray, rec: &HitRecord, wi: Vec3) -> Option<(Color, f64)> {
+        let frame = Frame::from(rec.normal);
+        let wo = frame.to_local(-ray.dir().unit_vector());
+        if wo.z() <= 0. {
+            return Some((Color::new(), 0.));
+        }
+        let base = ray.reflectance(self.base_color);
+        Some(self.evaluate(wo, frame.to_local(wi), self.eta(rec), base))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use crate::{
+        geometries::HitRecord,
+        materials::{microfacet::fresnel_dielectric, Material, Scatter},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::Principled;
+
+    fn materials() -> Vec<Principled> {
+        let base = Color::from(0.8, 0.4, 0.2);
+        let mut plastic = Principled::from(base);
+        plastic.clearcoat = 1.;
+        plastic.sheen = 0.5;
+        let mut metal = Principled::from(base);
+        metal.metallic = 1.;
+        metal.roughness = 0.3;
+        let mut glass = Principled::from(Color::from(0.9, 0.9, 1.));
+        glass.transmission = 1.;
+        glass.roughness = 0.2;
+        let mut skin = Principled::from(base);
+        skin.subsurface = 1.;
+        skin.roughness = 0.8;
+        vec![plastic, metal, glass, skin]
+    }
+
+    #[test]
+    fn sampling_matches_evaluation() {
+        let ray = Ray::from(Point3::from(-1., 1., 0.5), Vec3::from(1., -1., -0.5));
+        for material in materials() {
+            let material: Arc<dyn Material> = Arc::new(material);
+            let rec = HitRecord::from(
+                &ray,
+                1.,
+                Point3::new(),
+                Vec3::from(0., 1., 0.),
+                material.clone(),
+            );
+            let mut sampler = IndependentSampler::new(9);
+            sampler.start_pixel_sample((0, 0), 0);
+            let n = 50000;
+            let mut albedo = Color::new();
+            for _ in 0..n {
+                if let Scatter::Scattered(weight, scattered) =
+                    material.scatter(&ray, &rec, &mut sampler)
+                {
+                    let (f, pdf) = material.eval(&ray, &rec, scattered.dir()).unwrap();
+                    assert!((f / pdf - weight).length() < 1e-6 * weight.length().max(1.));
+                    albedo += weight / n as f64;
+                }
+            }
+            // no more light leaves than arrives
+            assert!(albedo.x() < 1.05 && albedo.x() > 0.2, "{}", albedo);
+
+            // the density integrates to at most one over the sphere
+            let m = 600;
+            let mut total = 0.;
+            for s in 0..m * m {
+                let u = ((s % m) as f64 + 0.5) / m as f64;
+                let v = ((s / m) as f64 + 0.5) / m as f64;
+                let wi = Vec3::unit_from_sample(u, v);
+                total += material.eval(&ray, &rec, wi).unwrap().1;
+            }
+            let integral = total * 4. * PI / (m * m) as f64;
+            assert!(integral < 1.02 && integral > 0.8, "{}", integral);
+        }
+    }
+
+    #[test]
+    fn parameters_blend_lobes() {
+        // a black dielectric only reflects its specular
+        let mut black = Principled::from(Color::new());
+        black.roughness = 0.05;
+        let ray = Ray::from(Point3::from(0., 1., 0.), Vec3::from(0., -1., 0.));
+        let black: Arc<dyn Material> = Arc::new(black);
+        let rec = HitRecord::from(
+            &ray,
+            1.,
+            Point3::new(),
+            Vec3::from(0., 1., 0.),
+            black.clone(),
+        );
+        let mut sampler = IndependentSampler::new(1);
+        sampler.start_pixel_sample((0, 0), 0);
+        let n = 20000;
+        let mut albedo = 0.;
+        for _ in 0..n {
+            if let Scatter::Scattered(weight, _) = black.scatter(&ray, &rec, &mut sampler) {
+                albedo += weight.y() / n as f64;
+            }
+        }
+        let expected = fresnel_dielectric(1., 1.5);
+        assert!((albedo - expected).abs() < 0.01, "{}", albedo);
+    }
+}
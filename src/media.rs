@@ -0,0 +1,319 @@
+use std::sync::Arc;
+
+use crate::{
+    geometries::{HitRecord, HitType, Hittable},
+    materials::{
+        phase::{Phase, PhaseFunction},
+        Material,
+    },
+    ray::Ray,
+    sampler::Sampler,
+    utils::vec3::{Color, Vec3},
+};
+
+/// Most pairs of crossings of a boundary followed along a ray.
+const MAX_CROSSINGS: usize = 64;
+
+/// Region of space a medium fills.
+enum Extent {
+    /// The inside of a closed boundary.
+    Inside(Arc<dyn Hittable>),
+    /// Everything closer to the origin than a distance, as an atmosphere.
+    Within(f64),
+}
+
+/// Participating medium of constant density, filling the inside of a
+/// closed boundary or, without one, the scene up to a distance.
+pub struct Medium {
+    extent: Extent,
+    /// Extinction coefficient per meter.
+    density: f64,
+    phase: Arc<dyn Material>,
+}
+
+impl Medium {
+    pub fn from(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        albedo: Color,
+        function: PhaseFunction,
+    ) -> Self {
+        Self::new(Extent::Inside(boundary), density, albedo, function)
+    }
+
+    /// Medium filling the scene up to `max_distance` from the origin, where
+    /// rays leaving the scene exit it towards the sky.
+    pub fn atmosphere(
+        density: f64,
+        albedo: Color,
+        function: PhaseFunction,
+        max_distance: f64,
+    ) -> Self {
+        assert!(
+            max_distance > 0. && max_distance.is_finite(),
+            "an atmosphere needs a finite extent"
+        );
+        Self::new(Extent::Within(max_distance), density, albedo, function)
+    }
+
+    fn new(extent: Extent, density: f64, albedo: Color, function: PhaseFunction) -> Self {
+        // sampling distances divides by the density
+        assert!(
+            density > 0. && density.is_finite(),
+            "a medium needs a positive density"
+        );
+        Medium {
+            extent,
+            density,
+            phase: Arc::new(Phase::from(albedo, function)),
+        }
+    }
+
+    /// Ranges of the parameters of `ray` up to `t_max` inside the medium.
+    fn intervals(&self, ray: &Ray, t_max: f64) -> Vec<(f64, f64)> {
+        let crossings = match &self.extent {
+            Extent::Inside(boundary) => Self::crossings(boundary.as_ref(), ray),
+            Extent::Within(radius) => {
+                let a = ray.dir().length_squared();
+                let half_b = ray.dir().dot(&ray.origin());
+                let c = ray.origin().length_squared() - radius * radius;
+                let discriminant = half_b * half_b - a * c;
+                if discriminant < 0. {
+                    return Vec::new();
+                }
+                let sqrt_d = discriminant.sqrt();
+                vec![((-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a)]
+            }
+        };
+        crossings
+            .into_iter()
+            .map(|(enter, exit)| (enter.max(0.), exit.min(t_max)))
+            .filter(|(t0, t1)| t0 < t1)
+            .collect()
+    }
+
+    /// Parameters where the line of `ray` enters and leaves the closed
+    /// `boundary`, which it alternately does at every hit.
+    fn crossings(boundary: &dyn Hittable, ray: &Ray) -> Vec<(f64, f64)> {
+        let mut crossings = Vec::new();
+        let mut t = f64::NEG_INFINITY;
+        while let HitType::Hit(enter) = boundary.hit(ray, t, f64::INFINITY) {
+            let HitType::Hit(exit) = boundary.hit(ray, enter.t + 1e-4, f64::INFINITY) else {
+                break;
+            };
+            crossings.push((enter.t, exit.t));
+            t = exit.t + 1e-4;
+            if crossings.len() == MAX_CROSSINGS {
+                break;
+            }
+        }
+        crossings
+    }
+
+    /// Fraction of the light crossing the medium along `ray` up to `t_max`.
+    pub fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let inside: f64 = self
+            .intervals(ray, t_max)
+            .iter()
+            .map(|(t0, t1)| t1 - t0)
+            .sum();
+        (-self.density * inside * ray.dir().length()).exp()
+    }
+
+    /// Point where `ray` scatters in the medium before `t_max`, at a
+    /// distance drawn in proportion to the transmittance up to it.
+    pub fn sample(&self, ray: &Ray, t_max: f64, u: f64) -> Option<HitRecord> {
+        // the drawn distance is spent crossing the medium interval by interval
+        let mut depth = -(1. - u).ln() / (self.density * ray.dir().length());
+        for (t0, t1) in self.intervals(ray, t_max) {
+            let t = t0 + depth;
+            if t < t1 {
+                // media have no surface, so that lights are chosen for all around
+                return Some(HitRecord::from(
+                    ray,
+                    t,
+                    ray.at(t),
+                    Vec3::new(),
+                    self.phase.clone(),
+                ));
+            }
+            depth -= t1 - t0;
+        }
+        None
+    }
+}
+
+/// Nearest point where `ray` scatters in any of the media before `t_max`.
+pub fn sample(
+    media: &[Arc<Medium>],
+    ray: &Ray,
+    t_max: f64,
+    sampler: &mut dyn Sampler,
+) -> Option<HitRecord> {
+    media
+        .iter()
+        .filter_map(|medium| medium.sample(ray, t_max, sampler.get_1d()))
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+}
+
+/// Fraction of the light crossing every medium along `ray` up to `t_max`.
+pub fn transmittance(media: &[Arc<Medium>], ray: &Ray, t_max: f64) -> f64 {
+    media
+        .iter()
+        .map(|medium| medium.transmittance(ray, t_max))
+        .product()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        lights::{sky::Sky, Lighting},
+        materials::{diffuse::Diffuse, phase::PhaseFunction},
+        ray::Ray,
+        sampler::{independent::IndependentSampler, Sampler},
+        utils::vec3::{Color, Point3, Vec3},
+    };
+
+    use super::Medium;
+
+    #[test]
+    fn scattering_matches_transmittance() {
+        let boundary = Arc::new(Sphere::from(
+            Point3::new(),
+            1.,
+            Arc::new(Diffuse::new(1., 1., 1.)),
+        ));
+        let medium = Medium::from(
+            boundary,
+            0.5,
+            Color::from(1., 1., 1.),
+            PhaseFunction::Isotropic,
+        );
+        let ray = Ray::from(Point3::from(0., 0., -3.), Vec3::from(0., 0., 2.));
+        // the ray crosses two meters of the sphere
+        let expected = (-1_f64).exp();
+        assert!((medium.transmittance(&ray, f64::INFINITY) - expected).abs() < 1e-9);
+        // a surface inside the sphere cuts the crossing short
+        assert!((medium.transmittance(&ray, 1.5) - (-0.5_f64).exp()).abs() < 1e-9);
+        let miss = Ray::from(Point3::from(0., 2., -3.), Vec3::from(0., 0., 1.));
+        assert_eq!(medium.transmittance(&miss, f64::INFINITY), 1.);
+
+        // a ray through two separate parts of a boundary crosses both
+        let mut pair = HittableList::new();
+        for z in [-2., 2.] {
+            pair.add(Arc::new(Sphere::from(
+                Point3::from(0., 0., z),
+                0.5,
+                Arc::new(Diffuse::new(1., 1., 1.)),
+            )));
+        }
+        let smoke = Medium::from(
+            Arc::new(pair),
+            0.5,
+            Color::from(1., 1., 1.),
+            PhaseFunction::Isotropic,
+        );
+        let through = Ray::from(Point3::from(0., 0., -4.), Vec3::from(0., 0., 1.));
+        let expected = (-1_f64).exp();
+        assert!((smoke.transmittance(&through, f64::INFINITY) - expected).abs() < 1e-9);
+        assert!((smoke.transmittance(&through, 4.) - (-0.5_f64).exp()).abs() < 1e-9);
+        let beyond = smoke.sample(&through, f64::INFINITY, 1. - (-0.75_f64).exp());
+        assert!((beyond.unwrap().p.z() - 2.).abs() < 1e-9);
+
+        let mut sampler = IndependentSampler::new(3);
+        sampler.start_pixel_sample((0, 0), 0);
+        let n = 100000;
+        let passed = (0..n)
+            .filter(
+                |_| match medium.sample(&ray, f64::INFINITY, sampler.get_1d()) {
+                    Some(rec) => {
+                        assert!(rec.p.length() <= 1. + 1e-9);
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .count();
+        let passed = passed as f64 / n as f64;
+        assert!((passed - expected).abs() < 0.01, "{}", passed);
+
+        let fog = Medium::atmosphere(0.1, Color::from(1., 1., 1.), PhaseFunction::Isotropic, 10.);
+        // escaping rays leave the atmosphere ten meters from the origin
+        let expected = (-1.3_f64).exp();
+        assert!((fog.transmittance(&ray, f64::INFINITY) - expected).abs() < 1e-9);
+        assert!((fog.transmittance(&ray, 5.) - (-1_f64).exp()).abs() < 1e-9);
+        let outside = Ray::from(Point3::from(0., 0., 20.), Vec3::from(0., 1., 0.));
+        assert_eq!(fog.transmittance(&outside, f64::INFINITY), 1.);
+    }
+
+    #[test]
+    fn fogged_sky_stays_lit() {
+        let world = HittableList::new();
+        let sky = Sky::new(Vec3::from(0., 1., 1.), 3., Color::from(0.3, 0.3, 0.3));
+        let media = [Arc::new(Medium::atmosphere(
+            0.05,
+            Color::from(0.5, 0.5, 0.5),
+            PhaseFunction::HenyeyGreenstein(0.7),
+            100.,
+        ))];
+        let lighting = Lighting {
+            environment: Some(&sky),
+            media: &media,
+            ..Lighting::constant(Color::new())
+        };
+        let mut sampler = IndependentSampler::new(7);
+        sampler.start_pixel_sample((0, 0), 0);
+        let n = 1000;
+        let mut radiance = 0.;
+        for _ in 0..n {
+            let ray = Ray::from(Point3::new(), Vec3::from(0., 0.3, 1.));
+            radiance += ray.color(&world, &lighting, 20, &mut sampler).y();
+        }
+        assert!(radiance / n as f64 > 0.01, "{}", radiance / n as f64);
+    }
+
+    #[test]
+    fn furnace_conserves_energy() {
+        let boundary = Arc::new(Sphere::from(
+            Point3::new(),
+            1.,
+            Arc::new(Diffuse::new(1., 1., 1.)),
+        ));
+        let world = HittableList::new();
+        let mut sampler = IndependentSampler::new(5);
+        sampler.start_pixel_sample((0, 0), 0);
+        for (albedo, function) in [
+            (1., PhaseFunction::Isotropic),
+            (1., PhaseFunction::HenyeyGreenstein(0.8)),
+            (0.5, PhaseFunction::HenyeyGreenstein(-0.3)),
+        ] {
+            let medium = Arc::new(Medium::from(
+                boundary.clone(),
+                3.,
+                Color::from(albedo, albedo, albedo),
+                function,
+            ));
+            let media = [medium];
+            let lighting = Lighting {
+                media: &media,
+                ..Lighting::constant(Color::from(1., 1., 1.))
+            };
+            let n = 4000;
+            let mut radiance = 0.;
+            for _ in 0..n {
+                let ray = Ray::from(Point3::from(0., 0., -3.), Vec3::from(0., 0.2, 1.));
+                radiance += ray.color(&world, &lighting, 200, &mut sampler).x();
+            }
+            let radiance = radiance / n as f64;
+            // without absorption the medium only redirects the background
+            if albedo == 1. {
+                assert!((radiance - 1.).abs() < 1e-9, "{:?} {}", function, radiance);
+            } else {
+                assert!(radiance < 0.95 && radiance > 0.2, "{}", radiance);
+            }
+        }
+    }
+}
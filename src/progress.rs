@@ -0,0 +1,201 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Snapshot of a running render pass.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub samples: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.tiles_total == 0 {
+            return 1.;
+        }
+        self.tiles_done as f64 / self.tiles_total as f64
+    }
+
+    pub fn samples_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0. {
+            return 0.;
+        }
+        self.samples as f64 / seconds
+    }
+
+    /// Estimated time left, extrapolated from the tiles finished so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.tiles_done == 0 {
+            return None;
+        }
+        let left = (self.tiles_total - self.tiles_done) as f64 / self.tiles_done as f64;
+        Some(self.elapsed.mul_f64(left))
+    }
+}
+
+/// Receives progress updates from the render threads.
+pub trait ProgressObserver: Send + Sync {
+    /// Called every time a tile is finished.
+    fn on_progress(&self, progress: &Progress);
+
+    /// Called once the pass has finished or was cancelled.
+    fn on_finish(&self, _progress: &Progress) {}
+}
+
+/// Shared flag telling the render threads to stop. Clones refer to the same
+/// flag, so a token can be handed to another thread to cancel a render.
+#[derive(Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken(Arc::new(AtomicBool::new(false)))
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Terminal progress bar redrawn in place on stderr.
+pub struct ProgressBar {
+    width: usize,
+    last_draw: Mutex<Option<Instant>>,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        ProgressBar {
+            width: 40,
+            last_draw: Mutex::new(None),
+        }
+    }
+
+    fn draw(&self, progress: &Progress) {
+        let filled = (progress.fraction() * self.width as f64) as usize;
+        let eta = match progress.eta() {
+            Some(eta) => format_duration(eta),
+            None => String::from("--:--"),
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:3}% {}/{} tiles, {:.2} Msamples/s, ETA {}",
+            "#".repeat(filled),
+            " ".repeat(self.width - filled),
+            (progress.fraction() * 100.) as u32,
+            progress.tiles_done,
+            progress.tiles_total,
+            progress.samples_per_second() / 1e6,
+            eta
+        );
+        let _ = stderr.flush();
+    }
+}
+
+impl ProgressObserver for ProgressBar {
+    fn on_progress(&self, progress: &Progress) {
+        // redrawing for every tile would flood slow terminals
+        let mut last_draw = self.last_draw.lock().unwrap();
+        if last_draw.is_some_and(|t| t.elapsed() < Duration::from_millis(100)) {
+            return;
+        }
+        *last_draw = Some(Instant::now());
+        self.draw(progress);
+    }
+
+    fn on_finish(&self, progress: &Progress) {
+        self.draw(progress);
+        eprintln!();
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::scene::{Config, Scene};
+
+    use super::{CancellationToken, Progress, ProgressObserver};
+
+    struct Recorder {
+        updates: Mutex<Vec<Progress>>,
+        cancel_after: Option<(usize, CancellationToken)>,
+    }
+
+    impl ProgressObserver for Recorder {
+        fn on_progress(&self, progress: &Progress) {
+            self.updates.lock().unwrap().push(progress.clone());
+            if let Some((tiles, token)) = &self.cancel_after {
+                if progress.tiles_done == *tiles {
+                    token.cancel();
+                }
+            }
+        }
+    }
+
+    fn render(cancel_after: Option<usize>) -> (Scene, Vec<Progress>) {
+        let mut scene = Scene::new();
+        scene.set_config(Config {
+            height: 9,
+            samples: 2,
+            tile_size: 4,
+            threads: 1,
+            ..Config::default()
+        });
+        let recorder = Arc::new(Recorder {
+            updates: Mutex::new(vec![]),
+            cancel_after: cancel_after.map(|tiles| (tiles, scene.cancellation_token())),
+        });
+        scene.set_observer(recorder.clone());
+        scene.render_path().unwrap();
+        let updates = recorder.updates.lock().unwrap().clone();
+        (scene, updates)
+    }
+
+    #[test]
+    fn progress_reaches_total() {
+        let (_, updates) = render(None);
+
+        // 16 x 9 pixels in 4 x 4 tiles
+        assert_eq!(updates.len(), 12);
+        for (n, progress) in updates.iter().enumerate() {
+            assert_eq!(progress.tiles_done, n + 1);
+            assert_eq!(progress.tiles_total, 12);
+        }
+        let last = updates.last().unwrap();
+        assert_eq!(last.samples, 16 * 9 * 2);
+        assert_eq!(last.eta(), Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn cancellation_stops_workers() {
+        let (scene, updates) = render(Some(3));
+
+        assert!(scene.cancellation_token().is_cancelled());
+        assert_eq!(updates.len(), 3);
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    adaptive::PixelStats, checkpoint::Checkpoint, film::Film, scene::Scene, utils::vec3::Color,
+};
+
+/// Settings for progressive rendering: the whole image is refined in passes
+/// of `samples_per_pass` samples per pixel until the time budget runs out,
+/// the noise target is met or `Config::samples` samples per pixel are taken.
+pub struct ProgressiveConfig {
+    pub samples_per_pass: u32,
+    pub time_budget: Option<Duration>,
+    /// Mean standard error of the gamma corrected pixel luminance to stop at.
+    pub noise_target: Option<f64>,
+    /// Overwrite the output image with the current state this often.
+    pub write_interval: Option<Duration>,
+}
+
+impl ProgressiveConfig {
+    pub fn default() -> Self {
+        ProgressiveConfig {
+            samples_per_pass: 4,
+            time_budget: None,
+            noise_target: None,
+            write_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// State of the image after a finished pass.
+pub struct Pass {
+    pub index: u32,
+    pub samples_per_pixel: u32,
+    pub noise: f64,
+    pub elapsed: Duration,
+}
+
+/// Renders passes until a stop criterion is met, calling `on_pass` with the
+/// film and pixel statistics after every pass. Starts from the state saved in
+/// `resume` if given. Returns the final filtered image.
+pub fn render(
+    scene: &Scene,
+    params: &ProgressiveConfig,
+    resume: Option<Checkpoint>,
+    mut on_pass: impl FnMut(&Pass, &Film, &[Mutex<PixelStats>]),
+) -> Result<Vec<Vec<Color>>, String> {
+    let config = scene.config();
+    let width = config.width();
+    let per_pass = params.samples_per_pass.max(1);
+    let start = Instant::now();
+
+    let film = scene.film();
+    let stats: Vec<Mutex<PixelStats>> = (0..width * config.height)
+        .map(|_| Mutex::new(PixelStats::new()))
+        .collect();
+    let mut samples_per_pixel = 0;
+    let mut index = 0;
+    if let Some(checkpoint) = resume {
+        film.restore(&checkpoint.film);
+        for (pixel, raw) in stats.iter().zip(checkpoint.stats) {
+            *pixel.lock().unwrap() = PixelStats::from_raw(raw);
+        }
+        samples_per_pixel = checkpoint.samples_per_pixel;
+        index = checkpoint.passes;
+    }
+    while samples_per_pixel < config.samples && !scene.is_cancelled() {
+        let pass_start = Instant::now();
+        let first = samples_per_pixel;
+        let last = (first + per_pass).min(config.samples);
+        scene.for_each_tile(|tile| {
+            let mut sampler = config.sampler.build(config.seed, config.samples);
+            let mut traced = 0;
+            for j in tile.y0..tile.y1 {
+                if scene.is_cancelled() {
+                    break;
+                }
+                for i in tile.x0..tile.x1 {
+                    let mut pixel = stats[j * width + i].lock().unwrap();
+                    for s in first..last {
+                        let (color, position) = scene.sample_pixel(&mut *sampler, i, j, s);
+                        pixel.add(color);
+                        film.add_sample(position, color);
+                    }
+                }
+                traced += ((tile.x1 - tile.x0) * (last - first) as usize) as u64;
+            }
+            traced
+        })?;
+        samples_per_pixel = last;
+
+        let noise =
+            stats.iter().map(|p| p.lock().unwrap().error()).sum::<f64>() / stats.len() as f64;
+        let pass = Pass {
+            index,
+            samples_per_pixel,
+            noise,
+            elapsed: start.elapsed(),
+        };
+        on_pass(&pass, &film, &stats);
+        index += 1;
+
+        if params.noise_target.is_some_and(|target| noise <= target) {
+            break;
+        }
+        // stop if another pass like this one would overrun the budget
+        if let Some(budget) = params.time_budget {
+            if pass.elapsed + pass_start.elapsed() > budget {
+                break;
+            }
+        }
+    }
+    Ok(film.image())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{
+        geometries::{sphere::Sphere, HittableList},
+        materials::diffuse::Diffuse,
+        scene::{Config, Scene},
+        utils::vec3::Point3,
+    };
+
+    use super::{render, ProgressiveConfig};
+
+    fn scene(with_sphere: bool) -> Scene {
+        let mut world = HittableList::new();
+        if with_sphere {
+            world.add(Arc::new(Sphere::from(
+                Point3::from(0., 0., -1.),
+                0.5,
+                Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+            )));
+        }
+        let mut scene = Scene::new();
+        scene.set_world(world);
+        scene.set_config(Config {
+            height: 9,
+            samples: 10,
+            ..Config::default()
+        });
+        scene
+    }
+
+    fn passes(scene: &Scene, params: &ProgressiveConfig) -> Vec<u32> {
+        let mut passes = vec![];
+        render(scene, params, None, |pass, _, _| {
+            passes.push(pass.samples_per_pixel)
+        })
+        .unwrap();
+        passes
+    }
+
+    #[test]
+    fn stops_at_sample_cap() {
+        let params = ProgressiveConfig::default();
+        assert_eq!(passes(&scene(true), &params), vec![4, 8, 10]);
+    }
+
+    #[test]
+    fn stops_on_time_budget() {
+        let params = ProgressiveConfig {
+            time_budget: Some(Duration::ZERO),
+            ..ProgressiveConfig::default()
+        };
+        assert_eq!(passes(&scene(true), &params), vec![4]);
+    }
+
+    #[test]
+    fn stops_on_noise_target() {
+        let params = ProgressiveConfig {
+            noise_target: Some(1e-3),
+            ..ProgressiveConfig::default()
+        };
+        // only background: no variance after the first pass
+        assert_eq!(passes(&scene(false), &params), vec![4]);
+    }
+
+    #[test]
+    fn passes_match_single_render() {
+        let scene = scene(true);
+        let progressive =
+            render(&scene, &ProgressiveConfig::default(), None, |_, _, _| {}).unwrap();
+        assert_eq!(progressive, scene.render_path().unwrap());
+    }
+}
@@ -1,17 +1,38 @@
+use std::sync::Arc;
+
 use crate::{
-    geometries::{HitType, Hittable},
+    aov::AovSample,
+    geometries::{HitRecord, HitType, Hittable},
+    lights::{power_heuristic, Environment, LightSource, Lighting},
     materials::Scatter,
-    utils::vec3::{Color, Point3, Vec3},
+    media::{self, Medium},
+    sampler::Sampler,
+    utils::{
+        spectrum::{Blackbody, Wavelengths},
+        vec3::{Color, Point3, Vec3},
+    },
 };
 
 pub struct Ray {
     origin: Point3,
     dir: Vec3,
+    /// Set in spectral mode, when colors along the path hold the quantities
+    /// at these wavelengths rather than at the RGB primaries.
+    wavelengths: Option<Wavelengths>,
 }
 
 impl Ray {
     pub fn from(origin: Point3, dir: Vec3) -> Self {
-        Ray { origin, dir }
+        Ray {
+            origin,
+            dir,
+            wavelengths: None,
+        }
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Option<Wavelengths>) -> Self {
+        self.wavelengths = wavelengths;
+        self
     }
 
     pub fn at(&self, t: f64) -> Point3 {
@@ -25,40 +46,199 @@ impl Ray {
     pub fn dir(&self) -> Vec3 {
         self.dir
     }
+
+    pub fn wavelengths(&self) -> Option<&Wavelengths> {
+        self.wavelengths.as_ref()
+    }
+
+    /// A reflectance given in linear sRGB, as carried by the ray.
+    pub fn reflectance(&self, rgb: Color) -> Color {
+        self.wavelengths.map_or(rgb, |w| w.reflectance(rgb))
+    }
+
+    /// Radiance given in linear sRGB, as carried by the ray.
+    pub fn illuminant(&self, rgb: Color) -> Color {
+        self.wavelengths.map_or(rgb, |w| w.illuminant(rgb))
+    }
+
+    /// Radiance given in linear sRGB, as carried by the ray, of an emitter
+    /// whose spectrum is that of `blackbody` if given.
+    pub fn emission(&self, rgb: Color, blackbody: Option<&Blackbody>) -> Color {
+        match (&self.wavelengths, blackbody) {
+            (Some(w), Some(blackbody)) => w.blackbody(rgb, blackbody),
+            _ => self.illuminant(rgb),
+        }
+    }
 }
 
 impl Ray {
-    pub fn color(&self, world: &dyn Hittable, background: Color, depth: u32) -> Color {
-        if depth == 0 {
-            return Vec3::new();
-        }
+    pub fn color(
+        &self,
+        world: &dyn Hittable,
+        lighting: &Lighting,
+        depth: u32,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        self.trace(world, lighting, depth, sampler, None)
+    }
+
+    /// Follows the path of the ray for at most `depth` bounces and returns the
+    /// radiance it carries back, filling in `aov` along the way if given.
+    ///
+    /// Surfaces that can be lit sample every light source directly, or a
+    /// single one chosen by the light selector, and with an environment they
+    /// also sample it. Lights that scattered rays can hit weight both ways of
+    /// reaching them with multiple importance sampling. Rays crossing media
+    /// may scatter inside them before reaching a surface, and the light
+    /// sampled directly is dimmed by the media in between.
+    ///
+    /// Rays carrying wavelengths trace them through the scene and return
+    /// the linear sRGB of the radiance found.
+    pub fn trace(
+        &self,
+        world: &dyn Hittable,
+        lighting: &Lighting,
+        depth: u32,
+        sampler: &mut dyn Sampler,
+        mut aov: Option<&mut AovSample>,
+    ) -> Color {
+        let wavelengths = self.wavelengths;
+        let rgb = |radiance: Color| wavelengths.map_or(radiance, |w| w.to_rgb(radiance));
+        let mut ray = Ray::from(self.origin, self.dir).with_wavelengths(wavelengths);
+        let mut throughput = Color::from(1., 1., 1.);
+        let mut radiance = Vec3::new();
+        // density of the last scattering and the normal at its origin if
+        // light sources were also sampled
+        let mut scattered_from = None;
 
-        if let HitType::Hit(rec) = world.hit(&self, 0.001, f64::INFINITY) {
-            let scattered = rec.material.scatter(&self, &rec);
-            if let Scatter::Scattered(attenuation, scattered_ray) = scattered {
-                return attenuation * scattered_ray.color(world, background, depth - 1);
+        for bounce in 0..depth {
+            let mut hit = world.hit(&ray, 0.001, f64::INFINITY);
+            if !lighting.media.is_empty() {
+                let t_max = match &hit {
+                    HitType::Hit(rec) => rec.t,
+                    HitType::NoHit => f64::INFINITY,
+                };
+                if let Some(rec) = media::sample(lighting.media, &ray, t_max, sampler) {
+                    hit = HitType::Hit(rec);
+                }
             }
-            let ret = match scattered {
-                Scatter::Scattered(attenuation, scattered_ray) => {
-                    attenuation * scattered_ray.color(world, background, depth - 1)
+            let emitted = match hit {
+                HitType::Hit(rec) => {
+                    let scattered = rec.material.scatter(&ray, &rec, sampler);
+                    if bounce == 0 {
+                        if let Some(aov) = aov.as_deref_mut() {
+                            aov.record_hit(&ray, &rec, &scattered);
+                        }
+                    }
+                    match scattered {
+                        Scatter::Scattered(attenuation, scattered_ray) => {
+                            scattered_from = None;
+                            let dir = scattered_ray.dir().unit_vector();
+                            if let Some((_, pdf)) = rec.material.eval(&ray, &rec, dir) {
+                                let mut direct = Vec3::new();
+                                match lighting.selector {
+                                    Some(selector) => {
+                                        let u = sampler.get_1d();
+                                        if let Some((index, probability)) =
+                                            selector.select(rec.p, rec.normal, u)
+                                        {
+                                            let light = lighting.lights[index].as_ref();
+                                            direct += sample_light(
+                                                light,
+                                                probability,
+                                                world,
+                                                lighting.media,
+                                                &ray,
+                                                &rec,
+                                                sampler,
+                                            );
+                                        }
+                                    }
+                                    None => {
+                                        for light in lighting.lights {
+                                            direct += sample_light(
+                                                light.as_ref(),
+                                                1.,
+                                                world,
+                                                lighting.media,
+                                                &ray,
+                                                &rec,
+                                                sampler,
+                                            );
+                                        }
+                                    }
+                                }
+                                if let Some(env) = lighting.environment {
+                                    direct += sample_environment(
+                                        env,
+                                        world,
+                                        lighting.media,
+                                        &ray,
+                                        &rec,
+                                        sampler,
+                                    );
+                                }
+                                scattered_from = Some((pdf, rec.normal));
+                                let direct = throughput * direct;
+                                radiance += direct;
+                                if let Some(aov) = aov.as_deref_mut() {
+                                    aov.record_light(bounce + 1, rgb(direct));
+                                }
+                            }
+                            throughput *= attenuation;
+                            ray = scattered_ray.with_wavelengths(wavelengths);
+                            continue;
+                        }
+                        Scatter::Light(tint) => {
+                            let tint = rec.material.illuminant(&ray, tint);
+                            let light = lighting
+                                .lights
+                                .iter()
+                                .position(|light| light.object() == Some(rec.object));
+                            match (scattered_from, light) {
+                                (Some((pdf, normal)), Some(index)) => {
+                                    let origin = ray.origin();
+                                    let probability = lighting
+                                        .selector
+                                        .map_or(1., |s| s.probability(origin, normal, index));
+                                    let light_pdf =
+                                        lighting.lights[index].pdf(origin, &rec) * probability;
+                                    tint * power_heuristic(pdf, light_pdf)
+                                }
+                                _ => tint,
+                            }
+                        }
+                        Scatter::Absorbed => break,
+                    }
+                }
+                HitType::NoHit => {
+                    if bounce == 0 {
+                        if let Some(aov) = aov.as_deref_mut() {
+                            aov.record_miss(lighting.escaped(&ray, true));
+                        }
+                    }
+                    let weight = match (scattered_from, lighting.environment) {
+                        (Some((pdf, _)), Some(env)) => {
+                            power_heuristic(pdf, env.pdf(ray.dir().unit_vector()))
+                        }
+                        _ => 1.,
+                    };
+                    ray.illuminant(lighting.escaped(&ray, bounce == 0)) * weight
                 }
-                Scatter::Light(tint) => tint,
-                Scatter::Absorbed => Vec3::new(),
             };
-            return ret;
+            let contribution = throughput * emitted;
+            radiance += contribution;
+            if let Some(aov) = aov {
+                aov.record_light(bounce, rgb(contribution));
+            }
+            break;
         }
-
-        // let unit_dir = self.dir.unit_vector();
-        // map y-component to 1 (top of viewport) and 0 (bottom of viewport)
-        // let t = 0.5 * (unit_dir.y() + 1.);
-        // return gradient from blue (top) and white (bottom)
-        // Color::from(0.3, 0.3, 0.9) * t + Color::from(0.5, 0.7, 1.) * (1. - t)
-        background
+        rgb(radiance)
     }
 
     pub fn reflect(&self, normal: &Vec3) -> Vec3 {
         let in_ray = self.dir.unit_vector();
-        return in_ray - *normal * in_ray.dot(normal) * 2.;
+        in_ray - *normal * in_ray.dot(normal) * 2.
     }
 
     pub fn refract(&self, normal: &Vec3, eta_over_etap: f64) -> Vec3 {
@@ -69,3 +249,65 @@ impl Ray {
         out_para + out_perp
     }
 }
+
+/// Light reaching the hit point directly from a light source chosen with
+/// the given probability, weighted against the chance of scattering into it
+/// if rays can hit it.
+fn sample_light(
+    light: &dyn LightSource,
+    probability: f64,
+    world: &dyn Hittable,
+    media: &[Arc<Medium>],
+    ray: &Ray,
+    rec: &HitRecord,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let Some(sample) = light.sample(rec.p, sampler.get_2d()) else {
+        return Vec3::new();
+    };
+    let Some((f, scatter_pdf)) = rec.material.eval(ray, rec, sample.wi) else {
+        return Vec3::new();
+    };
+    let pdf = sample.pdf * probability;
+    if f.near_zero() || pdf == 0. {
+        return Vec3::new();
+    }
+    let shadow = Ray::from(rec.p, sample.wi);
+    if let HitType::Hit(_) = world.hit(&shadow, 0.001, sample.distance * (1. - 1e-6)) {
+        return Vec3::new();
+    }
+    let transmittance = media::transmittance(media, &shadow, sample.distance);
+    let weight = match light.object() {
+        Some(_) => power_heuristic(pdf, scatter_pdf),
+        None => 1.,
+    };
+    f * light.illuminant(ray, sample.radiance) * (weight * transmittance / pdf)
+}
+
+/// Light reaching the hit point directly from the environment, weighted
+/// against the chance of scattering into the same direction.
+fn sample_environment(
+    env: &dyn Environment,
+    world: &dyn Hittable,
+    media: &[Arc<Medium>],
+    ray: &Ray,
+    rec: &HitRecord,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let Some((dir, light, light_pdf)) = env.sample(sampler.get_2d()) else {
+        return Vec3::new();
+    };
+    let Some((f, scatter_pdf)) = rec.material.eval(ray, rec, dir) else {
+        return Vec3::new();
+    };
+    if f.near_zero() {
+        return Vec3::new();
+    }
+    let shadow = Ray::from(rec.p, dir);
+    if let HitType::Hit(_) = world.hit(&shadow, 0.001, f64::INFINITY) {
+        return Vec3::new();
+    }
+    let transmittance = media::transmittance(media, &shadow, f64::INFINITY);
+    f * ray.illuminant(light)
+        * (power_heuristic(light_pdf, scatter_pdf) * transmittance / light_pdf)
+}
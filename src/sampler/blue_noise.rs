@@ -0,0 +1,189 @@
+use std::sync::OnceLock;
+
+use super::{fixed_to_float, owen_scramble, sobol_2d, stream_seed, to_unit_float, Sampler};
+
+const MASK_SIZE: usize = 64;
+
+/// Blue-noise dithered sampler (Georgiev and Fajardo 2016).
+///
+/// All pixels share one scrambled Sobol sequence, which every pixel offsets
+/// (Cranley-Patterson rotation) by a value read from a blue-noise mask. The
+/// mask is shifted differently per dimension, so the remaining error is
+/// distributed as high frequency noise across neighbouring pixels.
+pub struct BlueNoiseSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    index: u32,
+    dimension: u64,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64) -> Self {
+        BlueNoiseSampler {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn offset(&self, hash: u64) -> f64 {
+        let mask = blue_noise_mask();
+        let dx = (hash as usize) % MASK_SIZE;
+        let dy = ((hash >> 32) as usize) % MASK_SIZE;
+        let x = (self.pixel.0 + dx) % MASK_SIZE;
+        let y = (self.pixel.1 + dy) % MASK_SIZE;
+        mask[y * MASK_SIZE + x]
+    }
+
+    fn get_2d_for(&mut self, dimensions: u64) -> (f64, f64) {
+        let hash = stream_seed(self.seed, self.dimension);
+        self.dimension += dimensions;
+        let (x, y) = sobol_2d(self.index);
+        let u = fixed_to_float(owen_scramble(x, hash as u32)) + self.offset(hash);
+        let v = fixed_to_float(owen_scramble(y, (hash >> 32) as u32))
+            + self.offset(stream_seed(hash, 1));
+        (u.fract(), v.fract())
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.get_2d_for(1).0
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        self.get_2d_for(2)
+    }
+}
+
+/// Returns the shared tileable blue-noise mask, generated on first use.
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| void_and_cluster(MASK_SIZE, 1.5))
+}
+
+/// Generates a `size` x `size` toroidal blue-noise threshold mask with values
+/// in `(0, 1)` using Ulichney's void-and-cluster method.
+fn void_and_cluster(size: usize, sigma: f64) -> Vec<f64> {
+    let n = size * size;
+
+    // energy contribution of a point at toroidal offset (dx, dy)
+    let mut kernel = vec![0.; n];
+    for dy in 0..size {
+        for dx in 0..size {
+            let x = dx.min(size - dx) as f64;
+            let y = dy.min(size - dy) as f64;
+            kernel[dy * size + dx] = (-(x * x + y * y) / (2. * sigma * sigma)).exp();
+        }
+    }
+
+    let toggle = |energy: &mut [f64], p: usize, sign: f64| {
+        let (px, py) = (p % size, p / size);
+        for qy in 0..size {
+            for qx in 0..size {
+                let dx = (qx + size - px) % size;
+                let dy = (qy + size - py) % size;
+                energy[qy * size + qx] += sign * kernel[dy * size + dx];
+            }
+        }
+    };
+    // tightest cluster among set points, or largest void among unset ones
+    let extremum = |energy: &[f64], pattern: &[bool], cluster: bool| -> usize {
+        let mut best: Option<usize> = None;
+        for p in (0..n).filter(|&p| pattern[p] == cluster) {
+            let better = match best {
+                None => true,
+                Some(b) if cluster => energy[p] > energy[b],
+                Some(b) => energy[p] < energy[b],
+            };
+            if better {
+                best = Some(p);
+            }
+        }
+        best.unwrap()
+    };
+
+    // initial binary pattern with a tenth of the points set
+    let mut pattern = vec![false; n];
+    let mut energy = vec![0.; n];
+    let ones = n / 10;
+    let mut state = 0;
+    let mut set = 0;
+    while set < ones {
+        state = stream_seed(state, 0);
+        let p = (to_unit_float(state) * n as f64) as usize;
+        if !pattern[p] {
+            pattern[p] = true;
+            toggle(&mut energy, p, 1.);
+            set += 1;
+        }
+    }
+
+    // spread the points out by moving the tightest cluster into the largest void
+    loop {
+        let cluster = extremum(&energy, &pattern, true);
+        pattern[cluster] = false;
+        toggle(&mut energy, cluster, -1.);
+        let void = extremum(&energy, &pattern, false);
+        pattern[void] = true;
+        toggle(&mut energy, void, 1.);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+
+    // phase 1: rank the initial points by removing tightest clusters
+    let mut reduced = pattern.clone();
+    let mut reduced_energy = energy.clone();
+    for r in (0..ones).rev() {
+        let cluster = extremum(&reduced_energy, &reduced, true);
+        reduced[cluster] = false;
+        toggle(&mut reduced_energy, cluster, -1.);
+        rank[cluster] = r;
+    }
+
+    // phase 2: fill the largest voids until every point is ranked
+    for r in ones..n {
+        let void = extremum(&energy, &pattern, false);
+        pattern[void] = true;
+        toggle(&mut energy, void, 1.);
+        rank[void] = r;
+    }
+
+    rank.iter().map(|&r| (r as f64 + 0.5) / n as f64).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{blue_noise_mask, MASK_SIZE};
+
+    #[test]
+    fn mask_is_a_permutation_of_thresholds() {
+        let mask = blue_noise_mask();
+        let mut ranks: Vec<usize> = mask
+            .iter()
+            .map(|v| (v * mask.len() as f64) as usize)
+            .collect();
+        ranks.sort();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+
+        // neighbours in blue noise differ much more than in white noise
+        let mut neighbour_diff = 0.;
+        for y in 0..MASK_SIZE {
+            for x in 0..MASK_SIZE {
+                let right = mask[y * MASK_SIZE + (x + 1) % MASK_SIZE];
+                neighbour_diff += (mask[y * MASK_SIZE + x] - right).abs();
+            }
+        }
+        assert!(neighbour_diff / mask.len() as f64 > 0.4);
+    }
+}
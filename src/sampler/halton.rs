@@ -0,0 +1,72 @@
+use super::{permutation_element, pixel_seed, stream_seed, IndependentSampler, Sampler};
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Halton sequence sampler with per-pixel Owen scrambling.
+///
+/// Dimension `d` uses the radical inverse in the `d`-th prime base; dimensions
+/// past the prime table fall back to independent samples.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+    fallback: IndependentSampler,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            pixel: seed,
+            index: 0,
+            dimension: 0,
+            fallback: IndependentSampler::new(seed),
+        }
+    }
+}
+
+/// Radical inverse of `a` in `base` with every digit permuted based on the
+/// digits before it, i.e. a random nested (Owen) scramble.
+fn owen_scrambled_radical_inverse(base: u32, mut a: u64, hash: u64) -> f64 {
+    let base64 = base as u64;
+    let inv_base = 1. / base as f64;
+    let mut inv_base_m = 1.;
+    let mut reversed = 0u128;
+    while 1. - (base as f64 - 1.) * inv_base_m < 1. {
+        let next = a / base64;
+        let digit_value = (a - next * base64) as u32;
+        let digit_hash = stream_seed(hash, reversed as u64);
+        let digit = permutation_element(digit_value, base, digit_hash as u32);
+        reversed = reversed * base as u128 + digit as u128;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+    (reversed as f64 * inv_base_m).min(1. - f64::EPSILON / 2.)
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32) {
+        self.pixel = pixel_seed(self.seed, pixel);
+        self.index = index;
+        self.dimension = 0;
+        self.fallback.start_pixel_sample(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                let hash = stream_seed(self.pixel, dimension);
+                owen_scrambled_radical_inverse(base, self.index as u64, hash)
+            }
+            None => self.fallback.get_1d(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use super::{pixel_seed, stream_seed, to_unit_float, Sampler};
+
+/// Uniform white-noise sampler.
+///
+/// Values are a hash of the seed, the pixel, the sample index and the
+/// dimension, so a sample never depends on which thread traced it or in
+/// which order the pixels were rendered.
+pub struct IndependentSampler {
+    seed: u64,
+    state: u64,
+    dimension: u64,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler {
+            seed,
+            state: seed,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32) {
+        self.state = stream_seed(pixel_seed(self.seed, pixel), index as u64);
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let value = to_unit_float(stream_seed(self.state, self.dimension));
+        self.dimension += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sampler::Sampler;
+
+    use super::IndependentSampler;
+
+    #[test]
+    fn same_pixel_sample_repeats() {
+        let mut a = IndependentSampler::new(3);
+        let mut b = IndependentSampler::new(3);
+
+        a.start_pixel_sample((4, 2), 7);
+        let first: Vec<f64> = (0..16).map(|_| a.get_1d()).collect();
+        a.start_pixel_sample((5, 2), 7);
+        a.get_1d();
+        a.start_pixel_sample((4, 2), 7);
+        let again: Vec<f64> = (0..16).map(|_| a.get_1d()).collect();
+        b.start_pixel_sample((4, 2), 7);
+        let other: Vec<f64> = (0..16).map(|_| b.get_1d()).collect();
+
+        assert_eq!(first, again);
+        assert_eq!(first, other);
+        assert!(first.iter().all(|&u| (0. ..1.).contains(&u)));
+    }
+}
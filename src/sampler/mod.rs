@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+
+use self::{
+    blue_noise::BlueNoiseSampler, halton::HaltonSampler, independent::IndependentSampler,
+    sobol::SobolSampler, stratified::StratifiedSampler,
+};
+
+pub mod blue_noise;
+pub mod halton;
+pub mod independent;
+pub mod sobol;
+pub mod stratified;
+
+/// Source of the sample values consumed while tracing a single camera path.
+///
+/// Every random decision (pixel jitter, BSDF sampling, Russian roulette, ...)
+/// draws its numbers from a `Sampler` instead of a global RNG, so integrators
+/// such as MLT can control the primary sample space of a path.
+pub trait Sampler {
+    /// Resets the sampler to the first dimension of the `index`-th sample of `pixel`.
+    fn start_pixel_sample(&mut self, _pixel: (usize, usize), _index: u32) {}
+
+    /// Returns the next sample value in `[0, 1)`.
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let u = self.get_1d();
+        let v = self.get_1d();
+        (u, v)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerType {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerType {
+    /// Creates a pixel sampler taking `samples` samples per pixel.
+    pub fn build(&self, seed: u64, samples: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(seed, samples)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(seed, samples)),
+            SamplerType::BlueNoise => Box::new(BlueNoiseSampler::new(seed)),
+        }
+    }
+}
+
+/// Derives an independent seed for the given stream from the base seed (splitmix64).
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Maps the 53 high bits of a hash to a float in `[0, 1)`.
+pub fn to_unit_float(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1. / (1u64 << 53) as f64)
+}
+
+fn pixel_seed(seed: u64, pixel: (usize, usize)) -> u64 {
+    stream_seed(stream_seed(seed, pixel.0 as u64), pixel.1 as u64)
+}
+
+/// Element `i` of a pseudo-random permutation of `0..n` selected by `p` (Kensler 2013).
+fn permutation_element(mut i: u32, n: u32, p: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % n
+}
+
+/// Hash-based Owen scrambling of a 32 bit fixed point value (Laine-Karras).
+fn owen_scramble(v: u32, seed: u32) -> u32 {
+    let mut v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x05526c56);
+    v ^= v.wrapping_mul(0x53a22864);
+    v.reverse_bits()
+}
+
+fn fixed_to_float(v: u32) -> f64 {
+    v as f64 / (1u64 << 32) as f64
+}
+
+/// The first two dimensions of the Sobol sequence as 32 bit fixed point values.
+fn sobol_2d(mut index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut v = 1 << 31;
+    while index != 0 {
+        if index & 1 == 1 {
+            y ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::SamplerType;
+
+    /// Mean squared error of the quarter disk area estimated by every pixel,
+    /// using the 2D sample that follows `skip` 1D samples.
+    fn disk_error(sampler_type: SamplerType, skip: usize) -> f64 {
+        let samples = 16;
+        let mut sampler = sampler_type.build(5, samples);
+        let mut error = 0.;
+        for pixel in 0..256 {
+            let mut estimate = 0.;
+            for s in 0..samples {
+                sampler.start_pixel_sample((pixel % 16, pixel / 16), s);
+                for _ in 0..skip {
+                    sampler.get_1d();
+                }
+                let (u, v) = sampler.get_2d();
+                assert!((0. ..1.).contains(&u) && (0. ..1.).contains(&v));
+                if u * u + v * v < 1. {
+                    estimate += 1.;
+                }
+            }
+            let diff = estimate / samples as f64 - std::f64::consts::FRAC_PI_4;
+            error += diff * diff;
+        }
+        error / 256.
+    }
+
+    #[test]
+    fn samples_past_the_count_do_not_repeat() {
+        for sampler_type in [
+            SamplerType::Independent,
+            SamplerType::Stratified,
+            SamplerType::Halton,
+            SamplerType::Sobol,
+            SamplerType::BlueNoise,
+        ] {
+            let mut sampler = sampler_type.build(5, 4);
+            let mut points = vec![];
+            for s in 0..12 {
+                sampler.start_pixel_sample((3, 7), s);
+                let point = (sampler.get_1d(), sampler.get_2d());
+                assert!(!points.contains(&point), "{:?} at {}", sampler_type, s);
+                points.push(point);
+            }
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_reduces_noise() {
+        for skip in [0, 5] {
+            let random = disk_error(SamplerType::Independent, skip);
+            for sampler_type in [
+                SamplerType::Stratified,
+                SamplerType::Halton,
+                SamplerType::Sobol,
+                SamplerType::BlueNoise,
+            ] {
+                let error = disk_error(sampler_type, skip);
+                assert!(
+                    error < random * 0.6,
+                    "{:?} at dimension {}: {} vs random {}",
+                    sampler_type,
+                    skip,
+                    error,
+                    random
+                );
+            }
+        }
+    }
+}
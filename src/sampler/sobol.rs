@@ -0,0 +1,63 @@
+use super::{
+    fixed_to_float, owen_scramble, permutation_element, pixel_seed, sobol_2d, stream_seed, Sampler,
+};
+
+/// Padded Owen-scrambled Sobol sampler.
+///
+/// Every pair of dimensions takes its values from the first two Sobol
+/// dimensions, with the sample order shuffled and the bits Owen scrambled
+/// independently per pixel and dimension. Works best with a power of two
+/// sample count. Samples past the count are drawn from further rounds of
+/// the same points, each scrambled anew.
+pub struct SobolSampler {
+    seed: u64,
+    samples: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, samples: u32) -> Self {
+        SobolSampler {
+            seed,
+            samples: samples.max(1),
+            pixel: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next_point(&mut self) -> ((u32, u32), u64) {
+        let mut hash = stream_seed(self.pixel, self.dimension);
+        let round = self.index / self.samples;
+        if round > 0 {
+            // stream 1 already scrambles the second coordinate
+            hash = stream_seed(hash, round as u64 + 1);
+        }
+        let index = permutation_element(self.index % self.samples, self.samples, hash as u32);
+        (sobol_2d(index), hash)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32) {
+        self.pixel = pixel_seed(self.seed, pixel);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let ((x, _), hash) = self.next_point();
+        self.dimension += 1;
+        fixed_to_float(owen_scramble(x, (hash >> 32) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let ((x, y), hash) = self.next_point();
+        self.dimension += 2;
+        let u = fixed_to_float(owen_scramble(x, (hash >> 32) as u32));
+        let v = fixed_to_float(owen_scramble(y, stream_seed(hash, 1) as u32));
+        (u, v)
+    }
+}
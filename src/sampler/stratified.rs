@@ -0,0 +1,65 @@
+use super::{permutation_element, pixel_seed, stream_seed, to_unit_float, Sampler};
+
+/// Jittered stratified sampler.
+///
+/// Each dimension (or pair of dimensions) is split into one stratum per pixel
+/// sample; the strata are visited in a different random order for every
+/// dimension so the dimensions stay decorrelated.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples: u32,
+    x_strata: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples: u32) -> Self {
+        let samples = samples.max(1);
+        // the most square grid with exactly `samples` cells
+        let mut x_strata = (samples as f64).sqrt() as u32;
+        while !samples.is_multiple_of(x_strata) {
+            x_strata -= 1;
+        }
+        StratifiedSampler {
+            seed,
+            samples,
+            x_strata,
+            pixel: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next_stratum(&mut self) -> (u32, u64) {
+        let hash = stream_seed(self.pixel, self.dimension);
+        let stratum = permutation_element(self.index % self.samples, self.samples, hash as u32);
+        (stratum, stream_seed(hash, self.index as u64))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: u32) {
+        self.pixel = pixel_seed(self.seed, pixel);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter) = self.next_stratum();
+        self.dimension += 1;
+        (stratum as f64 + to_unit_float(jitter)) / self.samples as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, jitter) = self.next_stratum();
+        self.dimension += 2;
+        let y_strata = self.samples / self.x_strata;
+        let x = stratum % self.x_strata;
+        let y = stratum / self.x_strata;
+        let u = (x as f64 + to_unit_float(jitter)) / self.x_strata as f64;
+        let v = (y as f64 + to_unit_float(stream_seed(jitter, 1))) / y_strata as f64;
+        (u, v)
+    }
+}
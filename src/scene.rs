@@ -1,16 +1,28 @@
 use std::{
-    sync::{mpsc, Arc},
+    io,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
     time::Instant,
 };
 
 use crate::{
+    adaptive::{self, AdaptiveConfig},
+    aov::{self, AovConfig, AovSample, AovType, Aovs},
     camera::Camera,
-    geometries::{Hittable, HittableList},
+    checkpoint::{Checkpoint, CheckpointConfig},
+    denoise::{self, DenoiseConfig, Features},
+    film::Film,
+    filter::FilterType,
+    geometries::{Hittable, HittableList, Shape},
+    integrators::{mlt, Integrator},
+    lights::{area::AreaLight, Environment, LightSampling, LightSelector, LightSource, Lighting},
     materials::Material,
-    utils::{
-        ppm::PPM,
-        vec3::{Color, Vec3},
-    },
+    media::Medium,
+    progress::{CancellationToken, Progress, ProgressObserver},
+    progressive::{self, ProgressiveConfig},
+    sampler::{stream_seed, Sampler, SamplerType},
+    tiles::{self, Tile, TileOrder},
+    utils::{color::WhiteBalance, ppm::PPM, spectrum::Wavelengths, vec3::Color},
 };
 
 pub struct Config {
@@ -20,13 +32,48 @@ pub struct Config {
     pub samples: u32,
     pub depth: u32,
     pub background: Color,
+    pub integrator: Integrator,
+    pub sampler: SamplerType,
+    pub seed: u64,
+    pub adaptive: Option<AdaptiveConfig>,
+    pub filter: FilterType,
+    pub filter_radius: f64,
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Number of render threads, 0 uses every core.
+    pub threads: usize,
+    pub progressive: Option<ProgressiveConfig>,
+    /// Checkpoint path traced renders without adaptive sampling.
+    pub checkpoint: Option<CheckpointConfig>,
+    /// AOV passes written with path traced renders that are neither
+    /// progressive nor adaptive.
+    pub aov: Option<AovConfig>,
+    /// Denoise the final image of path traced renders.
+    pub denoise: Option<DenoiseConfig>,
+    /// Color temperature in Kelvin that appears white in the saved images.
+    pub white_balance: Option<f64>,
+    pub light_sampling: LightSampling,
+    /// Trace wavelengths rather than RGB, with RGB colors upsampled to
+    /// spectra.
+    pub spectral: bool,
 }
 
 pub struct Scene {
     camera: Camera,
     world: HittableList,
     materials: Vec<Arc<dyn Material>>,
+    environment: Option<Arc<dyn Environment>>,
+    lights: Vec<Arc<dyn LightSource>>,
+    media: Vec<Arc<Medium>>,
+    /// Built from `lights` on first use.
+    selector: OnceLock<Option<Box<dyn LightSelector>>>,
+    /// Built with `Config::threads` on first use.
+    pool: OnceLock<Result<rayon::ThreadPool, String>>,
     config: Config,
+    /// Hash of the description the scene was built from, if any.
+    description_hash: Option<u64>,
+    observer: Option<Arc<dyn ProgressObserver>>,
+    cancel: CancellationToken,
 }
 
 impl Config {
@@ -37,6 +84,22 @@ impl Config {
         let depth = 20;
         let aspect_ratio = 16. / 9.;
         let background = Color::from(0.3, 0.3, 0.8);
+        let integrator = Integrator::Path;
+        let sampler = SamplerType::Independent;
+        let seed = 0;
+        let adaptive = None;
+        let filter = FilterType::Box;
+        let filter_radius = filter.default_radius();
+        let tile_size = 32;
+        let tile_order = TileOrder::Spiral;
+        let threads = 0;
+        let progressive = None;
+        let checkpoint = None;
+        let aov = None;
+        let denoise = None;
+        let white_balance = None;
+        let light_sampling = LightSampling::All;
+        let spectral = false;
         Config {
             name,
             height,
@@ -44,8 +107,33 @@ impl Config {
             samples,
             depth,
             background,
+            integrator,
+            sampler,
+            seed,
+            adaptive,
+            filter,
+            filter_radius,
+            tile_size,
+            tile_order,
+            threads,
+            progressive,
+            checkpoint,
+            aov,
+            denoise,
+            white_balance,
+            light_sampling,
+            spectral,
         }
     }
+
+    /// Wavelengths for a camera ray in spectral mode.
+    pub fn wavelengths(&self, sampler: &mut dyn Sampler) -> Option<Wavelengths> {
+        self.spectral.then(|| Wavelengths::sample(sampler.get_1d()))
+    }
+
+    pub fn width(&self) -> usize {
+        (self.height as f64 * self.aspect_ratio) as usize
+    }
 }
 
 impl Scene {
@@ -59,7 +147,15 @@ impl Scene {
             camera,
             world,
             materials,
+            environment: None,
+            lights: vec![],
+            media: vec![],
+            selector: OnceLock::new(),
+            pool: OnceLock::new(),
             config,
+            description_hash: None,
+            observer: None,
+            cancel: CancellationToken::new(),
         }
     }
 
@@ -73,7 +169,15 @@ impl Scene {
             camera,
             world,
             materials,
+            environment: None,
+            lights: vec![],
+            media: vec![],
+            selector: OnceLock::new(),
+            pool: OnceLock::new(),
             config,
+            description_hash: None,
+            observer: None,
+            cancel: CancellationToken::new(),
         }
     }
 
@@ -87,6 +191,8 @@ impl Scene {
 
     pub fn set_config(&mut self, conf: Config) {
         self.config = conf;
+        self.selector = OnceLock::new();
+        self.pool = OnceLock::new();
     }
 
     pub fn set_camera(&mut self, cam: Camera) {
@@ -97,6 +203,68 @@ impl Scene {
         self.world = world;
     }
 
+    /// Lights the scene with `environment` instead of `Config::background`.
+    pub fn set_environment(&mut self, environment: Arc<dyn Environment>) {
+        self.environment = Some(environment);
+    }
+
+    pub fn add_light(&mut self, light: Arc<dyn LightSource>) {
+        self.lights.push(light);
+        self.selector = OnceLock::new();
+    }
+
+    /// Adds an emissive shape to the world and samples it as a light.
+    pub fn add_area_light(&mut self, shape: Arc<dyn Shape>) {
+        self.world.add(shape.clone());
+        let object = self.world.size();
+        self.add_light(Arc::new(AreaLight::from(shape, object)));
+    }
+
+    /// Fills the inside of the medium's boundary, or the whole scene, with
+    /// the medium.
+    pub fn add_medium(&mut self, medium: Arc<Medium>) {
+        self.media.push(medium);
+    }
+
+    pub fn lighting(&self) -> Lighting<'_> {
+        Lighting {
+            background: self.config.background,
+            environment: self.environment.as_deref(),
+            lights: &self.lights,
+            selector: self
+                .selector
+                .get_or_init(|| self.config.light_sampling.selector(&self.lights))
+                .as_deref(),
+            media: &self.media,
+        }
+    }
+
+    pub fn set_observer(&mut self, observer: Arc<dyn ProgressObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Token that stops the render threads when cancelled. Once cancelled,
+    /// every later render of this scene stops immediately as well.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_description_hash(&mut self, hash: u64) {
+        self.description_hash = Some(hash);
+    }
+
+    pub fn description_hash(&self) -> Option<u64> {
+        self.description_hash
+    }
+
     pub fn set_materials(&mut self, materials: Vec<Arc<dyn Material>>) {
         self.materials = materials;
     }
@@ -104,7 +272,7 @@ impl Scene {
     pub fn print_info(&self) {
         println!(
             "Rendering scene:\nResolution: {} x {}\nSamples: {}\nDepth: {}\nObjects: {}",
-            (self.config.height as f64 * self.config.aspect_ratio) as usize,
+            self.config.width(),
             self.config.height,
             self.config.samples,
             self.config.depth,
@@ -112,70 +280,385 @@ impl Scene {
         );
     }
 
-    pub fn render(&self) {
+    pub fn render(&self) -> Result<(), String> {
         self.print_info();
         let start = Instant::now();
-        let height = self.config.height;
-        let width = (self.config.height as f64 * self.config.aspect_ratio) as usize;
-        let samples = self.config.samples;
-        let depth = self.config.depth;
-        let world = &self.world;
-        let camera = &self.camera;
-
-        let milestone = height / 10;
-
-        let mut image = vec![vec![Vec3::new(); width]; height];
-        let a = &mut image;
-        rayon::scope(|s| {
-            let (tx, rx) = mpsc::channel::<(usize, Vec<Vec3>)>();
-            s.spawn(move |_| {
-                let mut counter = 0;
-                for (y, row) in rx.iter() {
-                    counter += 1;
-                    if counter % milestone == 0 {
-                        println!("Status: {}%", counter / milestone * 10);
+        match &self.config.integrator {
+            Integrator::Path => {
+                if let Some(params) = &self.config.progressive {
+                    self.render_progressive(params)?;
+                } else if self.config.checkpoint.is_some() {
+                    // checkpoints are taken between passes
+                    let params = ProgressiveConfig {
+                        write_interval: None,
+                        ..ProgressiveConfig::default()
+                    };
+                    self.render_progressive(&params)?;
+                } else if let Some(params) = &self.config.adaptive {
+                    let (image, samples) = adaptive::render(self, params)?;
+                    let spent = samples.iter().flatten().map(|&n| n as u64).sum::<u64>();
+                    println!(
+                        "Adaptive sampling: {:.1} samples per pixel",
+                        spent as f64 / (self.config.width() * self.config.height) as f64
+                    );
+                    self.save_final(image)?;
+                    if params.samples_map {
+                        self.save_samples_map(samples);
                     }
-                    a[y] = row;
+                } else if let Some(params) = &self.config.aov {
+                    let aovs = Aovs::new(self.config.width(), self.config.height, &params.passes);
+                    let image = self.render_path_with(Some(&aovs))?;
+                    if let Err(e) =
+                        aov::write(&self.config.name, &image, &aovs.images(), params.output)
+                    {
+                        eprintln!("Could not write AOVs: {}", e);
+                    }
+                    self.save_final(image)?;
+                } else {
+                    let image = self.render_path()?;
+                    self.save_final(image)?;
                 }
-            });
+            }
+            Integrator::Mlt(params) => {
+                let image = self.thread_pool()?.install(|| {
+                    mlt::render(
+                        &self.camera,
+                        &self.world,
+                        &self.lighting(),
+                        &self.config,
+                        params,
+                        &self.cancel,
+                    )
+                });
+                self.save(image, 1);
+            }
+        }
+        if self.is_cancelled() {
+            println!("Render cancelled, saved the partial image");
+        }
+        println!("Took: {}s", start.elapsed().as_secs());
+        Ok(())
+    }
 
-            for j in 0..height {
-                let tx = tx.clone();
-                s.spawn(move |_| {
-                    let mut row = vec![];
-                    for i in 0..width {
-                        let mut pixel = Color::new();
-                        for _ in 0..samples {
-                            let x = (i as f64 + rand::random::<f64>()) / (width as f64 - 1.);
-                            let y = (j as f64 + rand::random::<f64>()) / (height as f64 - 1.);
-                            let r = camera.get_ray(x, y);
-                            pixel += r.color(world, self.config.background, depth);
-                        }
-                        row.push(pixel);
+    fn render_progressive(&self, params: &ProgressiveConfig) -> Result<(), String> {
+        let checkpoint = self.config.checkpoint.as_ref();
+        let resume = checkpoint
+            .filter(|c| c.resume)
+            .and_then(|c| self.load_checkpoint(&c.path));
+        let mut last_write = Instant::now();
+        let mut last_checkpoint = Instant::now();
+
+        let image = progressive::render(self, params, resume, |pass, film, stats| {
+            println!(
+                "Pass {}: {} samples per pixel, noise {:.4}, {}s",
+                pass.index,
+                pass.samples_per_pixel,
+                pass.noise,
+                pass.elapsed.as_secs()
+            );
+            if params
+                .write_interval
+                .is_some_and(|interval| last_write.elapsed() >= interval)
+            {
+                self.save(film.image(), 1);
+                last_write = Instant::now();
+            }
+            // a cancelled pass is incomplete and must not be checkpointed
+            if let Some(checkpoint) = checkpoint {
+                if !self.is_cancelled() && last_checkpoint.elapsed() >= checkpoint.interval {
+                    let state = Checkpoint::capture(
+                        self,
+                        pass.samples_per_pixel,
+                        pass.index + 1,
+                        film,
+                        stats,
+                    );
+                    if let Err(e) = state.write(&checkpoint.path) {
+                        eprintln!("Could not write checkpoint: {}", e);
                     }
+                    last_checkpoint = Instant::now();
+                }
+            }
+        })?;
+        self.save_final(image)
+    }
+
+    fn load_checkpoint(&self, path: &Path) -> Option<Checkpoint> {
+        match Checkpoint::read(path, self.config.width(), self.config.height) {
+            Ok(checkpoint) if checkpoint.matches(self) => {
+                println!(
+                    "Resuming from {} at {} samples per pixel",
+                    path.display(),
+                    checkpoint.samples_per_pixel
+                );
+                Some(checkpoint)
+            }
+            Ok(_) => {
+                eprintln!(
+                    "Checkpoint {} belongs to a different render, starting over",
+                    path.display()
+                );
+                None
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                eprintln!("Could not read checkpoint {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Traces sample `index` of pixel (i, j), counting rows from the bottom.
+    /// Returns the radiance and the film position of the sample.
+    pub fn sample_pixel(
+        &self,
+        sampler: &mut dyn Sampler,
+        i: usize,
+        j: usize,
+        index: u32,
+    ) -> (Color, (f64, f64)) {
+        self.sample_pixel_aov(sampler, i, j, index, None)
+    }
+
+    /// Like `sample_pixel`, also filling in the AOVs of the sample.
+    pub fn sample_pixel_aov(
+        &self,
+        sampler: &mut dyn Sampler,
+        i: usize,
+        j: usize,
+        index: u32,
+        mut aov: Option<&mut AovSample>,
+    ) -> (Color, (f64, f64)) {
+        let width = self.config.width();
+        let height = self.config.height;
+        sampler.start_pixel_sample((i, j), index);
+        let (u, v) = sampler.get_2d();
+        let x = i as f64 + u;
+        let y = j as f64 + v;
+        let r = self
+            .camera
+            .get_ray(x / (width as f64 - 1.), y / (height as f64 - 1.))
+            .with_wavelengths(self.config.wavelengths(sampler));
+        let color = r.trace(
+            &self.world,
+            &self.lighting(),
+            self.config.depth,
+            sampler,
+            aov.as_deref_mut(),
+        );
+        if let Some(aov) = aov {
+            aov.material_id = aov.material.as_ref().map_or(0, |material| {
+                self.materials
+                    .iter()
+                    .position(|m| std::ptr::addr_eq(Arc::as_ptr(m), Arc::as_ptr(material)))
+                    .map_or(0, |index| index + 1)
+            });
+        }
+        (color, (x, y))
+    }
+
+    pub fn film(&self) -> Film {
+        let filter = self.config.filter.build(self.config.filter_radius);
+        Film::new(self.config.width(), self.config.height, filter)
+    }
+
+    pub fn thread_pool(&self) -> Result<&rayon::ThreadPool, String> {
+        self.pool
+            .get_or_init(|| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(self.config.threads)
+                    .build()
+                    .map_err(|e| format!("could not start the render threads: {}", e))
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Runs `render_tile` on every tile of the image. Tiles are queued in the
+    /// configured order and picked up by idle threads of the work-stealing
+    /// pool, which write their samples straight into a shared `Film`.
+    ///
+    /// `render_tile` returns the number of samples it traced, which is
+    /// reported to the progress observer. Tiles not yet started when the
+    /// render is cancelled are skipped.
+    pub fn for_each_tile(&self, render_tile: impl Fn(&Tile) -> u64 + Sync) -> Result<(), String> {
+        let tiles = tiles::tiles(
+            self.config.width(),
+            self.config.height,
+            self.config.tile_size,
+            self.config.tile_order,
+        );
+        let start = Instant::now();
+        let progress = Mutex::new(Progress {
+            tiles_done: 0,
+            tiles_total: tiles.len(),
+            samples: 0,
+            elapsed: start.elapsed(),
+        });
 
-                    tx.send((j, row)).unwrap();
+        let render_tile = &render_tile;
+        let progress_ref = &progress;
+        self.thread_pool()?.scope_fifo(|s| {
+            for tile in &tiles {
+                s.spawn_fifo(move |_| {
+                    if self.is_cancelled() {
+                        return;
+                    }
+                    let samples = render_tile(tile);
+                    let mut progress = progress_ref.lock().unwrap();
+                    progress.tiles_done += 1;
+                    progress.samples += samples;
+                    progress.elapsed = start.elapsed();
+                    if let Some(observer) = &self.observer {
+                        observer.on_progress(&progress);
+                    }
                 });
             }
         });
 
-        let file_name = format!("{}.ppm", self.config.name);
+        if let Some(observer) = &self.observer {
+            observer.on_finish(&progress.into_inner().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Path traces `Config::samples` samples per pixel and returns the
+    /// filtered image, rows ordered bottom to top.
+    pub fn render_path(&self) -> Result<Vec<Vec<Color>>, String> {
+        self.render_path_with(None)
+    }
+
+    /// `render_path` that also accumulates the AOVs of every sample.
+    pub fn render_path_with(&self, aovs: Option<&Aovs>) -> Result<Vec<Vec<Color>>, String> {
+        let film = self.film();
+        self.for_each_tile(|tile| self.render_tile(tile, &film, aovs))?;
+        Ok(film.image())
+    }
+
+    /// Traces `Config::samples` samples for every pixel of `tile` into `film`,
+    /// returning the number of samples traced.
+    pub fn render_tile(&self, tile: &Tile, film: &Film, aovs: Option<&Aovs>) -> u64 {
+        let samples = self.config.samples;
+        let mut sampler = self.config.sampler.build(self.config.seed, samples);
+        let mut traced = 0;
+        for j in tile.y0..tile.y1 {
+            if self.is_cancelled() {
+                break;
+            }
+            for i in tile.x0..tile.x1 {
+                for s in 0..samples {
+                    let Some(aovs) = aovs else {
+                        let (color, position) = self.sample_pixel(&mut *sampler, i, j, s);
+                        film.add_sample(position, color);
+                        continue;
+                    };
+                    let mut aov = AovSample::new();
+                    let (color, position) =
+                        self.sample_pixel_aov(&mut *sampler, i, j, s, Some(&mut aov));
+                    film.add_sample(position, color);
+                    aovs.add_sample(position, s, &aov);
+                }
+            }
+            traced += ((tile.x1 - tile.x0) * samples as usize) as u64;
+        }
+        traced
+    }
+
+    /// Denoises the finished image if enabled, then saves it.
+    fn save_final(&self, image: Vec<Vec<Color>>) -> Result<(), String> {
+        let Some(params) = &self.config.denoise else {
+            self.save(image, 1);
+            return Ok(());
+        };
+        if self.is_cancelled() {
+            self.save(image, 1);
+            return Ok(());
+        }
+        if params.keep_noisy {
+            self.save_as(&format!("{}_noisy", self.config.name), image.clone(), 1);
+        }
+        let start = Instant::now();
+        let features = self.render_features(params.feature_samples)?;
+        let image = denoise::denoise(&image, &features, params);
+        println!("Denoising took: {}ms", start.elapsed().as_millis());
+        self.save(image, 1);
+        Ok(())
+    }
+
+    /// Renders the albedo and normal AOVs guiding the denoiser.
+    pub fn render_features(&self, samples: u32) -> Result<Features, String> {
+        let aovs = Aovs::new(
+            self.config.width(),
+            self.config.height,
+            &[AovType::Albedo, AovType::Normal],
+        );
+        let seed = stream_seed(self.config.seed, u64::MAX);
+        self.for_each_tile(|tile| {
+            let mut sampler = self.config.sampler.build(seed, samples);
+            for j in tile.y0..tile.y1 {
+                for i in tile.x0..tile.x1 {
+                    for s in 0..samples {
+                        let mut aov = AovSample::new();
+                        let (_, position) =
+                            self.sample_pixel_aov(&mut *sampler, i, j, s, Some(&mut aov));
+                        aovs.add_sample(position, s, &aov);
+                    }
+                }
+            }
+            (tile.pixels() * samples as usize) as u64
+        })?;
+        let mut images = aovs.images().into_iter().map(|(_, image)| image);
+        Ok(Features {
+            albedo: images.next().unwrap(),
+            normal: images.next().unwrap(),
+        })
+    }
+
+    pub fn save(&self, image: Vec<Vec<Color>>, samples: u32) {
+        self.save_as(&self.config.name, image, samples);
+    }
+
+    /// Writes `<name>.ppm`.
+    fn save_as(&self, name: &str, mut image: Vec<Vec<Color>>, samples: u32) {
+        let width = self.config.width();
+        let file_name = format!("{}.ppm", name);
         let mut file = PPM::from(file_name, width as u32, self.config.height as u32);
+        let balance = self.config.white_balance.map(WhiteBalance::from);
         image.reverse();
         for row in image {
             for pixel in row {
-                let rgb = pixel.to_rgb(self.config.samples);
+                let pixel = balance.as_ref().map_or(pixel, |b| b.apply(pixel));
+                let rgb = pixel.to_rgb(samples);
                 file.push(rgb.0, rgb.1, rgb.2);
             }
         }
         file.write().unwrap();
-        println!("Took: {}s", start.elapsed().as_secs());
+    }
+
+    /// Writes the samples taken per pixel as a grayscale image, white being
+    /// the most sampled pixel.
+    fn save_samples_map(&self, mut samples: Vec<Vec<u32>>) {
+        let file_name = format!("{}_samples.ppm", self.config.name);
+        let mut file = PPM::from(
+            file_name,
+            self.config.width() as u32,
+            self.config.height as u32,
+        );
+        let max = samples.iter().flatten().copied().max().unwrap_or(1).max(1);
+        samples.reverse();
+        for row in samples {
+            for count in row {
+                let v = (count as f64 / max as f64 * 255.) as u8;
+                file.push(v, v, v);
+            }
+        }
+        file.write().unwrap();
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::sync::{Arc, OnceLock};
 
     use crate::{
         camera::Camera,
@@ -186,7 +669,106 @@ mod test {
         utils::vec3::{Color, Point3, Vec3},
     };
 
-    use super::Scene;
+    use super::{Config, Scene};
+
+    #[test]
+    fn test_seed_reproduces_image() {
+        let mut scene = Scene::new();
+        scene.set_world(random_scene());
+        scene.set_config(Config {
+            height: 18,
+            samples: 4,
+            seed: 11,
+            ..Config::default()
+        });
+
+        let render_with = |scene: &mut Scene, threads, tile_size| {
+            scene.config.threads = threads;
+            scene.config.tile_size = tile_size;
+            scene.pool = OnceLock::new();
+            scene.render_path().unwrap()
+        };
+        let single = render_with(&mut scene, 1, 32);
+        assert_eq!(single, render_with(&mut scene, 4, 5));
+
+        scene.config.seed = 12;
+        assert_ne!(single, render_with(&mut scene, 1, 32));
+    }
+
+    #[test]
+    fn thread_pool_is_built_once() {
+        let mut scene = Scene::new();
+        let pool = scene.thread_pool().unwrap();
+        assert!(std::ptr::eq(pool, scene.thread_pool().unwrap()));
+        scene.set_config(Config {
+            threads: 3,
+            ..Config::default()
+        });
+        assert_eq!(scene.thread_pool().unwrap().current_num_threads(), 3);
+    }
+
+    #[test]
+    fn spectral_matches_rgb() {
+        let mut scene = Scene::new();
+        scene.set_camera(Camera::new(
+            Point3::from(0., 0.5, 2.),
+            Point3::from(0., 0., -1.),
+            Vec3::from(0., 1., 0.),
+            70.,
+            16. / 9.,
+        ));
+        let spheres: [(Point3, f64, Arc<dyn Material + Sync + Send>); 4] = [
+            (
+                Point3::from(0., -100.5, -1.),
+                100.,
+                Arc::new(Diffuse::new(0.5, 0.5, 0.5)),
+            ),
+            (
+                Point3::from(-1., 0., -1.),
+                0.5,
+                Arc::new(Diffuse::new(0.8, 0.2, 0.1)),
+            ),
+            (
+                Point3::from(1., 0., -1.),
+                0.5,
+                Arc::new(Metal::from(Color::from(0.2, 0.4, 0.8), 0.2)),
+            ),
+            (
+                Point3::from(0., -0.2, -0.5),
+                0.3,
+                Arc::new(Dielectric::new(1.5)),
+            ),
+        ];
+        for (center, radius, material) in spheres {
+            scene.add_object(Arc::new(Sphere::from(center, radius, material)));
+        }
+        let mut light = Light::blackbody(3000.);
+        light.intensity = 4.;
+        scene.add_area_light(Arc::new(Sphere::from(
+            Point3::from(0., 1.5, -1.),
+            0.5,
+            Arc::new(light),
+        )));
+        scene.set_config(Config {
+            height: 12,
+            samples: 64,
+            ..Config::default()
+        });
+        let mean = |scene: &Scene| {
+            let image = scene.render_path().unwrap();
+            let pixels = image.iter().flatten().count() as f64;
+            image.iter().flatten().fold(Color::new(), |sum, &c| sum + c) / pixels
+        };
+        let rgb = mean(&scene);
+        scene.config.spectral = true;
+        let spectral = mean(&scene);
+        assert!(
+            (spectral - rgb).length() < 0.05 * rgb.length(),
+            "{} {}",
+            rgb,
+            spectral
+        );
+    }
 
     #[test]
     fn test_scene() {
@@ -202,7 +784,7 @@ mod test {
         scene.set_world(world);
         scene.set_camera(camera);
 
-        scene.render();
+        scene.render().unwrap();
     }
 
     pub fn random_scene() -> HittableList {
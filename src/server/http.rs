@@ -0,0 +1,146 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+};
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 64 << 20;
+/// Longest request or header line accepted, in bytes.
+const MAX_LINE: u64 = 8 << 10;
+/// Most header lines accepted.
+const MAX_HEADERS: usize = 100;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn read(stream: &TcpStream) -> io::Result<Self> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        read_line(&mut reader, &mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(invalid("bad request line"));
+        };
+        let method = method.to_string();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let path = path.to_string();
+        let query = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (k.to_string(), v.to_string())
+            })
+            .collect();
+
+        let mut length = 0;
+        for headers in 0.. {
+            if read_line(&mut reader, &mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Err(invalid("too many headers"));
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        if length > MAX_BODY {
+            return Err(invalid("body too large"));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        Ok(Request {
+            method,
+            path,
+            query,
+            body,
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a line into the cleared `line`, failing on lines over `MAX_LINE`.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    line.clear();
+    let read = reader.take(MAX_LINE).read_line(line)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(invalid("line too long"));
+    }
+    Ok(read)
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: body.into_bytes(),
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, serde_json::json!({ "error": message }).to_string())
+    }
+
+    pub fn write(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            _ => "Error",
+        };
+        let header = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        );
+        stream.write_all(header.as_bytes())?;
+        stream.write_all(&self.body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_line, MAX_LINE};
+
+    #[test]
+    fn rejects_long_lines() {
+        let mut line = String::new();
+        let mut data = "GET / HTTP/1.1\r\nHost: a\r\n".as_bytes();
+        assert_eq!(read_line(&mut data, &mut line).unwrap(), 16);
+        assert_eq!(read_line(&mut data, &mut line).unwrap(), 9);
+        assert_eq!(line, "Host: a\r\n");
+
+        let long = "a".repeat(MAX_LINE as usize + 1);
+        assert!(read_line(&mut long.as_bytes(), &mut line).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::{
+    progress::{CancellationToken, Progress, ProgressObserver},
+    progressive::{self, ProgressiveConfig},
+    scene::Scene,
+    utils::vec3::Color,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobStatus {
+    pub id: usize,
+    pub name: String,
+    pub state: State,
+    /// Fraction of the sample budget traced so far.
+    pub progress: f64,
+    pub passes: u32,
+    pub samples_per_pixel: u32,
+    pub samples: u32,
+    pub noise: Option<f64>,
+    /// Seconds spent rendering.
+    pub elapsed: f64,
+    /// Why the job failed.
+    pub error: Option<String>,
+}
+
+pub struct Job {
+    cancel: CancellationToken,
+    status: Mutex<JobStatus>,
+    image: Mutex<Option<Vec<Vec<Color>>>>,
+    white_balance: Option<f64>,
+}
+
+impl Job {
+    pub fn status(&self) -> JobStatus {
+        lock(&self.status).clone()
+    }
+
+    /// Image after the last finished pass, rows ordered bottom to top.
+    pub fn image(&self) -> Option<Vec<Vec<Color>>> {
+        lock(&self.image).clone()
+    }
+
+    /// Color temperature that appears white in the downloaded images.
+    pub fn white_balance(&self) -> Option<f64> {
+        self.white_balance
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+        let mut status = lock(&self.status);
+        if status.state == State::Queued {
+            status.state = State::Cancelled;
+        }
+    }
+}
+
+/// Reports the tiles of the running pass as job progress.
+struct JobObserver {
+    job: Arc<Job>,
+    pass_samples: u32,
+}
+
+impl ProgressObserver for JobObserver {
+    fn on_progress(&self, progress: &Progress) {
+        let mut status = lock(&self.job.status);
+        let pass_samples = self
+            .pass_samples
+            .min(status.samples - status.samples_per_pixel);
+        let traced = status.samples_per_pixel as f64 + progress.fraction() * pass_samples as f64;
+        status.progress = traced / status.samples.max(1) as f64;
+    }
+}
+
+/// Render jobs run one after another, in submission order, each using the
+/// whole thread pool of its scene.
+pub struct Jobs {
+    jobs: Mutex<Vec<Arc<Job>>>,
+    queue: Mutex<VecDeque<(Arc<Job>, Scene, ProgressiveConfig)>>,
+    queued: Condvar,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Jobs {
+            jobs: Mutex::new(vec![]),
+            queue: Mutex::new(VecDeque::new()),
+            queued: Condvar::new(),
+        }
+    }
+
+    pub fn submit(&self, scene: Scene, params: ProgressiveConfig) -> usize {
+        let mut jobs = lock(&self.jobs);
+        let id = jobs.len();
+        let job = Arc::new(Job {
+            cancel: scene.cancellation_token(),
+            status: Mutex::new(JobStatus {
+                id,
+                name: scene.config().name.clone(),
+                state: State::Queued,
+                progress: 0.,
+                passes: 0,
+                samples_per_pixel: 0,
+                samples: scene.config().samples,
+                noise: None,
+                elapsed: 0.,
+                error: None,
+            }),
+            image: Mutex::new(None),
+            white_balance: scene.config().white_balance,
+        });
+        jobs.push(job.clone());
+        lock(&self.queue).push_back((job, scene, params));
+        self.queued.notify_one();
+        id
+    }
+
+    pub fn get(&self, id: usize) -> Option<Arc<Job>> {
+        lock(&self.jobs).get(id).cloned()
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        lock(&self.jobs).iter().map(|j| j.status()).collect()
+    }
+
+    /// Renders queued jobs forever.
+    pub fn run(&self) {
+        loop {
+            let (job, mut scene, params) = {
+                let mut queue = lock(&self.queue);
+                loop {
+                    if let Some(next) = queue.pop_front() {
+                        break next;
+                    }
+                    queue = self
+                        .queued
+                        .wait(queue)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            };
+            if job.cancel.is_cancelled() {
+                continue;
+            }
+            lock(&job.status).state = State::Running;
+            scene.set_observer(Arc::new(JobObserver {
+                job: job.clone(),
+                pass_samples: params.samples_per_pass.max(1),
+            }));
+
+            let start = Instant::now();
+            // a scene that panics fails its job instead of the runner
+            let rendered = panic::catch_unwind(AssertUnwindSafe(|| {
+                progressive::render(&scene, &params, None, |pass, film, _| {
+                    *lock(&job.image) = Some(film.image());
+                    let mut status = lock(&job.status);
+                    status.passes = pass.index + 1;
+                    status.samples_per_pixel = pass.samples_per_pixel;
+                    status.noise = Some(pass.noise);
+                    status.progress = pass.samples_per_pixel as f64 / status.samples.max(1) as f64;
+                    status.elapsed = pass.elapsed.as_secs_f64();
+                })
+            }))
+            .unwrap_or_else(|_| Err(String::from("the render panicked")))
+            .map(|image| *lock(&job.image) = Some(image));
+
+            let mut status = lock(&job.status);
+            status.elapsed = start.elapsed().as_secs_f64();
+            if let Err(e) = rendered {
+                status.state = State::Failed;
+                status.error = Some(e);
+            } else if job.cancel.is_cancelled() {
+                status.state = State::Cancelled;
+            } else {
+                // time budgets and noise targets may end the job early
+                status.state = State::Done;
+                status.progress = 1.;
+            }
+        }
+    }
+}
+
+/// Locks `mutex` even if a panicking render poisoned it, as jobs stay
+/// readable after their render fails.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use crate::{
+        progressive::ProgressiveConfig,
+        scene::{Config, Scene},
+    };
+
+    use super::{Jobs, State};
+
+    fn scene(aspect_ratio: f64) -> Scene {
+        let mut scene = Scene::new();
+        scene.set_config(Config {
+            height: 4,
+            aspect_ratio,
+            samples: 2,
+            ..Config::default()
+        });
+        scene
+    }
+
+    fn wait_for(jobs: &Jobs, id: usize) -> State {
+        for _ in 0..500 {
+            let state = jobs.get(id).unwrap().status().state;
+            if state != State::Queued && state != State::Running {
+                return state;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("job {} never finished", id);
+    }
+
+    #[test]
+    fn panicking_job_fails_alone() {
+        let jobs = Arc::new(Jobs::new());
+        let runner = jobs.clone();
+        thread::spawn(move || runner.run());
+
+        // zero pixels wide
+        let broken = jobs.submit(scene(0.1), ProgressiveConfig::default());
+        let fine = jobs.submit(scene(1.), ProgressiveConfig::default());
+        assert_eq!(wait_for(&jobs, broken), State::Failed);
+        assert!(jobs.get(broken).unwrap().status().error.is_some());
+        assert_eq!(wait_for(&jobs, fine), State::Done);
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    description::SceneDescription,
+    progressive::ProgressiveConfig,
+    utils::{color::WhiteBalance, exr::Exr, png::Png, vec3::Color},
+};
+
+use self::{
+    http::{Request, Response},
+    jobs::Jobs,
+};
+
+pub mod http;
+pub mod jobs;
+
+/// Serves the render job API on `listener`:
+///
+/// - `POST /jobs` queues the scene description in the body, which may not
+///   load files; progressive settings come from the `samples_per_pass`,
+///   `time_budget` (seconds) and `noise_target` query parameters
+/// - `GET /jobs` and `GET /jobs/{id}` return the status of the jobs
+/// - `GET /jobs/{id}/image.png` and `GET /jobs/{id}/image.exr` return the
+///   image after the last finished pass
+/// - `DELETE /jobs/{id}` cancels the job
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let jobs = Arc::new(Jobs::new());
+    let runner = jobs.clone();
+    thread::spawn(move || runner.run());
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let jobs = jobs.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &jobs) {
+                eprintln!("Request failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, jobs: &Jobs) -> io::Result<()> {
+    let response = match Request::read(&stream) {
+        Ok(request) => route(&request, jobs),
+        Err(e) => Response::error(400, &e.to_string()),
+    };
+    response.write(&mut stream)
+}
+
+fn route(request: &Request, jobs: &Jobs) -> Response {
+    let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["jobs"]) => submit(request, jobs),
+        ("GET", ["jobs"]) => Response::json(200, serde_json::to_string(&jobs.statuses()).unwrap()),
+        (method, ["jobs", id, rest @ ..]) => {
+            let Some(job) = id.parse().ok().and_then(|id| jobs.get(id)) else {
+                return Response::error(404, "no such job");
+            };
+            match (method, rest) {
+                ("GET", []) => Response::json(200, serde_json::to_string(&job.status()).unwrap()),
+                ("DELETE", []) => {
+                    job.cancel();
+                    Response::json(200, serde_json::to_string(&job.status()).unwrap())
+                }
+                ("GET", [file @ ("image.png" | "image.exr")]) => match job.image() {
+                    Some(image) if *file == "image.png" => Response {
+                        status: 200,
+                        content_type: "image/png",
+                        body: png(&balance(image, job.white_balance())),
+                    },
+                    Some(image) => Response {
+                        status: 200,
+                        content_type: "image/x-exr",
+                        body: exr(&balance(image, job.white_balance())),
+                    },
+                    None => Response::error(409, "no pass has finished yet"),
+                },
+                (_, [] | ["image.png" | "image.exr"]) => Response::error(405, "method not allowed"),
+                _ => Response::error(404, "not found"),
+            }
+        }
+        (_, ["jobs"]) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn submit(request: &Request, jobs: &Jobs) -> Response {
+    let json = String::from_utf8_lossy(&request.body);
+    let description = match SceneDescription::from_json(&json) {
+        Ok(description) => description,
+        Err(e) => return Response::error(400, &e),
+    };
+    // clients must not learn which files the server has
+    if !description.files().is_empty() {
+        return Response::error(400, "submitted scenes cannot load files");
+    }
+    let mut params = ProgressiveConfig {
+        write_interval: None,
+        ..ProgressiveConfig::default()
+    };
+    let parse = |name| request.param(name).map(|v| v.parse::<f64>());
+    match (
+        parse("samples_per_pass"),
+        parse("time_budget"),
+        parse("noise_target"),
+    ) {
+        (Some(Err(_)), _, _) | (_, Some(Err(_)), _) | (_, _, Some(Err(_))) => {
+            return Response::error(400, "query parameters must be numbers");
+        }
+        (samples_per_pass, time_budget, noise_target) => {
+            if let Some(Ok(n)) = samples_per_pass {
+                params.samples_per_pass = n as u32;
+            }
+            if let Some(Ok(t)) = time_budget {
+                match Duration::try_from_secs_f64(t) {
+                    Ok(budget) => params.time_budget = Some(budget),
+                    Err(_) => return Response::error(400, "time_budget is out of range"),
+                }
+            }
+            params.noise_target = noise_target.map(|t| t.unwrap());
+        }
+    }
+
+    let scene = match description.build() {
+        Ok(scene) => scene,
+        Err(e) => return Response::error(400, &e),
+    };
+    let id = jobs.submit(scene, params);
+    Response::json(201, serde_json::json!({ "id": id }).to_string())
+}
+
+/// The image with its white point at the `white_balance` temperature.
+fn balance(mut image: Vec<Vec<Color>>, white_balance: Option<f64>) -> Vec<Vec<Color>> {
+    if let Some(balance) = white_balance.map(WhiteBalance::from) {
+        for pixel in image.iter_mut().flatten() {
+            *pixel = balance.apply(*pixel);
+        }
+    }
+    image
+}
+
+fn png(image: &[Vec<Color>]) -> Vec<u8> {
+    let mut png = Png::from(image[0].len() as u32, image.len() as u32);
+    for row in image.iter().rev() {
+        for pixel in row {
+            let rgb = pixel.to_rgb(1);
+            png.push(rgb.0, rgb.1, rgb.2);
+        }
+    }
+    png.encode()
+}
+
+fn exr(image: &[Vec<Color>]) -> Vec<u8> {
+    let mut exr = Exr::from(image[0].len() as u32, image.len() as u32);
+    exr.push_layer("", image);
+    exr.encode()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use super::serve;
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let status = String::from_utf8_lossy(&response[9..12]).parse().unwrap();
+        (status, response[split + 4..].to_vec())
+    }
+
+    fn state(addr: SocketAddr, id: u64) -> serde_json::Value {
+        let (_, body) = request(addr, "GET", &format!("/jobs/{}", id), "");
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn scene(height: usize, samples: u32) -> String {
+        format!(
+            r#"{{
+                "settings": {{ "height": {}, "samples": {} }},
+                "camera": {{ "look_from": [0, 0, 0], "look_at": [0, 0, -1],
+                            "vup": [0, 1, 0], "vertical_fov": 90 }},
+                "materials": [{{ "type": "diffuse", "albedo": [0.5, 0.5, 0.5] }}],
+                "objects": [{{ "type": "sphere", "center": [0, 0, -1], "radius": 0.5, "material": 0 }}]
+            }}"#,
+            height, samples
+        )
+    }
+
+    fn wait_for(addr: SocketAddr, id: u64, state_name: &str) -> serde_json::Value {
+        for _ in 0..1000 {
+            let status = state(addr, id);
+            if status["state"] == state_name {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("job {} never became {}", id, state_name);
+    }
+
+    #[test]
+    fn job_api() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        assert_eq!(request(addr, "POST", "/jobs", "{}").0, 400);
+        assert_eq!(request(addr, "GET", "/jobs/7", "").0, 404);
+        for budget in ["inf", "NaN", "1e20", "-1"] {
+            let path = format!("/jobs?time_budget={}", budget);
+            assert_eq!(request(addr, "POST", &path, &scene(10, 4)).0, 400);
+        }
+
+        let model = scene(10, 4).replace(
+            r#""objects""#,
+            r#""models": [{ "path": "/etc/passwd" }], "objects""#,
+        );
+        let (status, body) = request(addr, "POST", "/jobs", &model);
+        assert_eq!(status, 400);
+        assert!(!String::from_utf8_lossy(&body).contains("passwd"));
+
+        let (status, body) = request(addr, "POST", "/jobs?samples_per_pass=2", &scene(10, 4));
+        assert_eq!(status, 201);
+        let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+        let done = wait_for(addr, id, "done");
+        assert_eq!(done["samples_per_pixel"], 4);
+        assert_eq!(done["passes"], 2);
+
+        let (status, png) = request(addr, "GET", &format!("/jobs/{}/image.png", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(&png[1..4], b"PNG");
+        let (status, exr) = request(addr, "GET", &format!("/jobs/{}/image.exr", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(exr[..4], [0x76, 0x2f, 0x31, 0x01]);
+
+        assert_eq!(
+            request(addr, "POST", "/jobs", &scene(100, 1_000_000)).0,
+            400
+        );
+        let (_, body) = request(addr, "POST", "/jobs", &scene(100, 60_000));
+        let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+        wait_for(addr, id, "running");
+        assert_eq!(request(addr, "DELETE", &format!("/jobs/{}", id), "").0, 200);
+        let cancelled = wait_for(addr, id, "cancelled");
+        assert!(cancelled["samples_per_pixel"].as_u64().unwrap() < 60_000);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{io, path::Path};
+
+use crate::utils::{hdr, vec3::Color};
+
+/// Color varying over a surface.
+pub trait Texture: Send + Sync {
+    /// Color at the surface parameterisation `uv`, both in `[0, 1]`.
+    fn value(&self, uv: (f64, f64)) -> Color;
+
+    /// Mean color over the whole `[0, 1]²` domain.
+    fn average(&self) -> Color;
+}
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn from(color: Color) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _uv: (f64, f64)) -> Color {
+        self.color
+    }
+
+    fn average(&self) -> Color {
+        self.color
+    }
+}
+
+/// Image stretched over the surface, with v = 0 at the bottom row.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Texture from pixels listed row by row from the top.
+    pub fn from(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads a Radiance `.hdr` image.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let (width, height, pixels) = hdr::read(path)?;
+        Ok(Self::from(width, height, pixels))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, (u, v): (f64, f64)) -> Color {
+        let i = ((u.clamp(0., 1.) * self.width as f64) as usize).min(self.width - 1);
+        let j = (((1. - v.clamp(0., 1.)) * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+
+    fn average(&self) -> Color {
+        self.pixels.iter().fold(Color::new(), |sum, &p| sum + p) / self.pixels.len() as f64
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+/// Order in which the tiles of an image are handed to the render threads.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileOrder {
+    /// Rows of tiles from the top of the image down.
+    Scanline,
+    /// Outward from the center of the image.
+    Spiral,
+    /// Along a Hilbert curve, keeping consecutive tiles adjacent.
+    Hilbert,
+}
+
+/// Pixel rectangle `[x0, x1) x [y0, y1)`, rows counted from the bottom.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Tile {
+    pub fn pixels(&self) -> usize {
+        (self.x1 - self.x0) * (self.y1 - self.y0)
+    }
+}
+
+/// Splits a `width` x `height` image into tiles of at most `size` x `size`
+/// pixels, listed in the given order.
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let nx = width.div_ceil(size);
+    let ny = height.div_ceil(size);
+
+    let coords: Vec<(usize, usize)> = match order {
+        TileOrder::Scanline => (0..ny)
+            .rev()
+            .flat_map(|ty| (0..nx).map(move |tx| (tx, ty)))
+            .collect(),
+        TileOrder::Spiral => spiral(nx, ny),
+        TileOrder::Hilbert => hilbert(nx, ny),
+    };
+
+    coords
+        .into_iter()
+        .map(|(tx, ty)| Tile {
+            x0: tx * size,
+            y0: ty * size,
+            x1: ((tx + 1) * size).min(width),
+            y1: ((ty + 1) * size).min(height),
+        })
+        .collect()
+}
+
+/// Walks a square spiral around the center tile, keeping the tiles that lie
+/// inside the grid.
+fn spiral(nx: usize, ny: usize) -> Vec<(usize, usize)> {
+    let total = nx * ny;
+    let mut coords = Vec::with_capacity(total);
+    let (mut x, mut y) = (((nx as i64) - 1) / 2, ((ny as i64) - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg = 1;
+    let mut direction = 0;
+    let push = |x: i64, y: i64, coords: &mut Vec<(usize, usize)>| {
+        if x >= 0 && y >= 0 && (x as usize) < nx && (y as usize) < ny {
+            coords.push((x as usize, y as usize));
+        }
+    };
+    push(x, y, &mut coords);
+    while coords.len() < total {
+        // legs grow by one every two turns: 1, 1, 2, 2, 3, 3, ...
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction];
+            for _ in 0..leg {
+                x += dx;
+                y += dy;
+                push(x, y, &mut coords);
+            }
+            direction = (direction + 1) % 4;
+        }
+        leg += 1;
+    }
+    coords
+}
+
+/// Hilbert curve over the smallest power of two grid covering the tiles,
+/// skipping cells outside the image.
+fn hilbert(nx: usize, ny: usize) -> Vec<(usize, usize)> {
+    let n = nx.max(ny).next_power_of_two();
+    (0..n * n)
+        .map(|d| hilbert_point(n, d))
+        .filter(|&(x, y)| x < nx && y < ny)
+        .collect()
+}
+
+/// Converts a distance along the Hilbert curve of an `n` x `n` grid to the
+/// cell coordinates.
+fn hilbert_point(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tiles, TileOrder};
+
+    #[test]
+    fn tiles_cover_image_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut covered = vec![0; 70 * 45];
+            for tile in tiles(70, 45, 16, order) {
+                for y in tile.y0..tile.y1 {
+                    for x in tile.x0..tile.x1 {
+                        covered[y * 70 + x] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&c| c == 1), "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn spiral_starts_at_center() {
+        let order = tiles(80, 48, 16, TileOrder::Spiral);
+        assert_eq!((order[0].x0, order[0].y0), (32, 16));
+        assert_eq!(order.len(), 15);
+    }
+
+    #[test]
+    fn hilbert_tiles_are_adjacent() {
+        let order = tiles(128, 128, 16, TileOrder::Hilbert);
+        for pair in order.windows(2) {
+            let dx = pair[0].x0.abs_diff(pair[1].x0);
+            let dy = pair[0].y0.abs_diff(pair[1].y0);
+            assert_eq!(dx + dy, 16);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::vec3::{Color, Vec3};
+
+/// Range and step in nanometers over which spectra are integrated.
+const LAMBDA_MIN: f64 = 360.;
+const LAMBDA_MAX: f64 = 830.;
+const LAMBDA_STEP: f64 = 1.;
+
+/// Reference white of sRGB in XYZ.
+const D65: [f64; 3] = [0.95047, 1., 1.08883];
+
+type Matrix = [[f64; 3]; 3];
+
+const XYZ_TO_SRGB: Matrix = [
+    [3.2406, -1.5372, -0.4986],
+    [-0.9689, 1.8758, 0.0415],
+    [0.0557, -0.2040, 1.0570],
+];
+
+const SRGB_TO_XYZ: Matrix = [
+    [0.4124, 0.3576, 0.1805],
+    [0.2126, 0.7152, 0.0722],
+    [0.0193, 0.1192, 0.9505],
+];
+
+/// Bradford cone response, used for chromatic adaptation.
+const BRADFORD: Matrix = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+const BRADFORD_INVERSE: Matrix = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+fn apply(m: &Matrix, v: Vec3) -> Vec3 {
+    Vec3::from(
+        m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+        m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+        m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+    )
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+/// Linear sRGB of a CIE XYZ color.
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    apply(&XYZ_TO_SRGB, xyz)
+}
+
+pub fn rgb_to_xyz(rgb: Color) -> Vec3 {
+    apply(&SRGB_TO_XYZ, rgb)
+}
+
+/// CIE 1931 2° color matching functions at `lambda` nanometers, using the
+/// multi-lobe fit of Wyman, Sloan and Shirley.
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let g = |mu: f64, below: f64, above: f64| {
+        let t = (lambda - mu) / if lambda < mu { below } else { above };
+        (-0.5 * t * t).exp()
+    };
+    Vec3::from(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Spectral radiance of a blackbody at `kelvin` in W/(m² sr nm), by Planck's
+/// law.
+pub fn planck(lambda: f64, kelvin: f64) -> f64 {
+    const H: f64 = 6.62607015e-34;
+    const C: f64 = 299792458.;
+    const K: f64 = 1.380649e-23;
+    if kelvin <= 0. {
+        return 0.;
+    }
+    let l = lambda * 1e-9;
+    2. * H * C * C / l.powi(5) / ((H * C / (l * K * kelvin)).exp() - 1.) * 1e-9
+}
+
+/// XYZ of a spectrum given by its value at every wavelength in nanometers.
+pub fn spectrum_to_xyz(spectrum: impl Fn(f64) -> f64) -> Vec3 {
+    let steps = ((LAMBDA_MAX - LAMBDA_MIN) / LAMBDA_STEP) as usize;
+    (0..=steps).fold(Vec3::new(), |sum, i| {
+        let lambda = LAMBDA_MIN + i as f64 * LAMBDA_STEP;
+        sum + cie_xyz(lambda) * (spectrum(lambda) * LAMBDA_STEP)
+    })
+}
+
+/// Radiance of a blackbody at `kelvin` in linear sRGB, in W/(m² sr)
+/// weighted by the color matching functions.
+pub fn blackbody_radiance(kelvin: f64) -> Color {
+    xyz_to_rgb(spectrum_to_xyz(|lambda| planck(lambda, kelvin)))
+}
+
+/// Color of a blackbody at `kelvin` in linear sRGB, scaled to a luminance
+/// of 1.
+pub fn blackbody(kelvin: f64) -> Color {
+    let xyz = spectrum_to_xyz(|lambda| planck(lambda, kelvin));
+    if xyz.y() <= 0. {
+        return Color::new();
+    }
+    xyz_to_rgb(xyz / xyz.y())
+}
+
+/// Chromatic adaptation turning the color of a blackbody at a given
+/// temperature into the neutral white of sRGB, keeping luminance.
+pub struct WhiteBalance {
+    matrix: Matrix,
+}
+
+impl WhiteBalance {
+    pub fn from(kelvin: f64) -> Self {
+        let white = rgb_to_xyz(blackbody(kelvin));
+        let source = apply(&BRADFORD, white);
+        let target = apply(&BRADFORD, Vec3::from(D65[0], D65[1], D65[2]));
+        let scale: Matrix = [
+            [target.x() / source.x(), 0., 0.],
+            [0., target.y() / source.y(), 0.],
+            [0., 0., target.z() / source.z()],
+        ];
+        let adapt = multiply(&BRADFORD_INVERSE, &multiply(&scale, &BRADFORD));
+        WhiteBalance {
+            matrix: multiply(&XYZ_TO_SRGB, &multiply(&adapt, &SRGB_TO_XYZ)),
+        }
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        apply(&self.matrix, color)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::vec3::Color;
+
+    use super::{blackbody, blackbody_radiance, spectrum_to_xyz, WhiteBalance};
+
+    #[test]
+    fn blackbody_colors() {
+        // the matching functions integrate to the CIE normalisation
+        let y: f64 = spectrum_to_xyz(|_| 1.).y();
+        assert!((y - 106.857).abs() < 1.5, "{}", y);
+
+        let candle = blackbody(1900.);
+        let daylight = blackbody(6504.);
+        let sky = blackbody(12000.);
+        assert!(candle.x() > candle.y() && candle.y() > candle.z());
+        assert!(sky.z() > sky.x());
+        for c in [candle, daylight, sky] {
+            assert!((c.luminance() - 1.).abs() < 0.02, "{}", c);
+        }
+        assert!((daylight.x() - daylight.z()).abs() < 0.05, "{}", daylight);
+
+        // Stefan–Boltzmann: radiance grows with the fourth power of temperature,
+        // somewhat faster in the visible range
+        let ratio = blackbody_radiance(6000.).luminance() / blackbody_radiance(3000.).luminance();
+        assert!(ratio > 16., "{}", ratio);
+        let absolute = blackbody_radiance(3000.);
+        assert!((absolute / absolute.luminance() - blackbody(3000.)).length() < 0.02);
+    }
+
+    #[test]
+    fn white_balance_neutralises_temperature() {
+        for kelvin in [2700., 4000., 9000.] {
+            let balanced = WhiteBalance::from(kelvin).apply(blackbody(kelvin));
+            assert!(
+                (balanced - Color::from(1., 1., 1.)).length() < 0.02,
+                "{}",
+                balanced
+            );
+        }
+        // a blackbody at 6504K is close to, but not exactly, the D65 white
+        let nearly = WhiteBalance::from(6504.).apply(Color::from(0.2, 0.5, 0.7));
+        assert!(
+            (nearly - Color::from(0.2, 0.5, 0.7)).length() < 0.05,
+            "{}",
+            nearly
+        );
+    }
+}
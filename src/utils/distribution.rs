@@ -0,0 +1,154 @@
+/// Piecewise constant 1D distribution over `[0, 1)` proportional to `func`.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Falls back to a uniform distribution if `func` is zero everywhere.
+    /// `func` needs at least one value.
+    pub fn new(func: &[f64]) -> Self {
+        assert!(
+            !func.is_empty(),
+            "a distribution needs at least one segment"
+        );
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.);
+        for (i, f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f.abs() / n as f64);
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0. {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Distribution1D {
+            func: func.iter().map(|f| f.abs()).collect(),
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Integral of the function over `[0, 1)`.
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Returns a point in `[0, 1)`, its density and the segment holding it.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self.find(u);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0. {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.
+        };
+        let x = ((offset as f64 + du) / self.count() as f64).min(1. - f64::EPSILON);
+        (x, self.density(offset), offset)
+    }
+
+    /// Returns a segment and its probability.
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let offset = self.find(u);
+        (offset, self.probability(offset))
+    }
+
+    /// Density of the continuous distribution on segment `i`.
+    pub fn density(&self, i: usize) -> f64 {
+        if self.integral > 0. {
+            self.func[i] / self.integral
+        } else {
+            1.
+        }
+    }
+
+    /// Probability of choosing segment `i`.
+    pub fn probability(&self, i: usize) -> f64 {
+        self.cdf[i + 1] - self.cdf[i]
+    }
+
+    /// Last segment whose cdf is at most `u`, skipping empty segments.
+    fn find(&self, u: f64) -> usize {
+        let i = self.cdf.partition_point(|&c| c <= u).saturating_sub(1);
+        let mut i = i.min(self.count() - 1);
+        while self.probability(i) == 0. && i > 0 {
+            i -= 1;
+        }
+        i
+    }
+}
+
+/// Piecewise constant 2D distribution over `[0, 1)^2`, sampled through the
+/// marginal density of v and the density of u conditioned on v.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `func` holds `nv` rows of `nu` values.
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> =
+            func.chunks(nu).take(nv).map(Distribution1D::new).collect();
+        let marginal: Vec<f64> = conditional.iter().map(|d| d.integral()).collect();
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal),
+        }
+    }
+
+    /// Returns a point (u, v) and its density.
+    pub fn sample(&self, (u0, u1): (f64, f64)) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, (u, v): (f64, f64)) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        let conditional = &self.conditional[row];
+        let column = ((u * conditional.count() as f64) as usize).min(conditional.count() - 1);
+        let marginal = self.marginal.integral();
+        if marginal == 0. {
+            return 1.;
+        }
+        conditional.func[column] / marginal
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distribution1D, Distribution2D};
+
+    #[test]
+    fn sample_density_matches_pdf() {
+        let func = [0., 1., 3., 0., 2., 2., 0., 0., 4.];
+        let distribution = Distribution2D::new(&func, 3, 3);
+        let mut counts = [0; 9];
+        let n = 90000;
+        for s in 0..n {
+            let u = ((s % 300) as f64 + 0.5) / 300.;
+            let v = ((s / 300) as f64 + 0.5) / 300.;
+            let ((x, y), pdf) = distribution.sample((u, v));
+            assert!((pdf - distribution.pdf((x, y))).abs() < 1e-9);
+            counts[(y * 3.) as usize * 3 + (x * 3.) as usize] += 1;
+        }
+        for (count, f) in counts.iter().zip(func) {
+            let expected = f / 12. * n as f64;
+            assert!((*count as f64 - expected).abs() < n as f64 * 0.01);
+        }
+
+        let discrete = Distribution1D::new(&[1., 0., 3.]);
+        assert_eq!(discrete.sample_discrete(0.3), (2, 0.75));
+        assert_eq!(discrete.sample_discrete(0.1), (0, 0.25));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fs::File, io::Write};
+
+use super::vec3::Color;
+
+/// Uncompressed scanline OpenEXR image with any number of 32 bit float
+/// channels. Layers are stored as channels named `layer.R`, `layer.G`, ...
+pub struct Exr {
+    width: u32,
+    height: u32,
+    // channel name and its values, rows from the top
+    channels: Vec<(String, Vec<f32>)>,
+}
+
+impl Exr {
+    pub fn from(width: u32, height: u32) -> Self {
+        Exr {
+            width,
+            height,
+            channels: vec![],
+        }
+    }
+
+    /// Adds a channel with one value per pixel, rows ordered from the top.
+    pub fn push_channel(&mut self, name: &str, values: Vec<f32>) {
+        assert_eq!(values.len(), (self.width * self.height) as usize);
+        self.channels.push((String::from(name), values));
+    }
+
+    /// Adds the R, G and B channels of `layer` (the default layer if empty)
+    /// from an image with rows ordered bottom to top.
+    pub fn push_layer(&mut self, layer: &str, image: &[Vec<Color>]) {
+        let prefix = if layer.is_empty() {
+            String::new()
+        } else {
+            format!("{}.", layer)
+        };
+        let channel = |c: fn(&Color) -> f64| {
+            image
+                .iter()
+                .rev()
+                .flat_map(|row| row.iter().map(move |p| c(p) as f32))
+                .collect()
+        };
+        self.push_channel(&format!("{}R", prefix), channel(Color::x));
+        self.push_channel(&format!("{}G", prefix), channel(Color::y));
+        self.push_channel(&format!("{}B", prefix), channel(Color::z));
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        // channels are stored in alphabetical order
+        let mut channels: Vec<&(String, Vec<f32>)> = self.channels.iter().collect();
+        channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut list = vec![];
+        for (name, _) in &channels {
+            list.extend(name.as_bytes());
+            list.push(0);
+            // FLOAT pixels, linear, reserved bytes, no subsampling
+            list.extend(2i32.to_le_bytes());
+            list.extend([0, 0, 0, 0]);
+            list.extend(1i32.to_le_bytes());
+            list.extend(1i32.to_le_bytes());
+        }
+        list.push(0);
+        let window: Vec<u8> = [0, 0, self.width as i32 - 1, self.height as i32 - 1]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+
+        let mut exr = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+        attribute(&mut exr, "channels", "chlist", &list);
+        attribute(&mut exr, "compression", "compression", &[0]);
+        attribute(&mut exr, "dataWindow", "box2i", &window);
+        attribute(&mut exr, "displayWindow", "box2i", &window);
+        attribute(&mut exr, "lineOrder", "lineOrder", &[0]);
+        attribute(&mut exr, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+        attribute(&mut exr, "screenWindowCenter", "v2f", &[0; 8]);
+        attribute(&mut exr, "screenWindowWidth", "float", &1f32.to_le_bytes());
+        exr.push(0);
+
+        let width = self.width as usize;
+        let line_size = 8 + 4 * width * channels.len();
+        let table_end = exr.len() + 8 * self.height as usize;
+        for y in 0..self.height as usize {
+            exr.extend(((table_end + y * line_size) as u64).to_le_bytes());
+        }
+        for y in 0..self.height as usize {
+            exr.extend((y as i32).to_le_bytes());
+            exr.extend(((line_size - 8) as i32).to_le_bytes());
+            for (_, values) in &channels {
+                for v in &values[y * width..(y + 1) * width] {
+                    exr.extend(v.to_le_bytes());
+                }
+            }
+        }
+        exr
+    }
+
+    pub fn write(&self, name: &str) -> std::io::Result<()> {
+        File::create(name)?.write_all(&self.encode())
+    }
+}
+
+fn attribute(exr: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    exr.extend(name.as_bytes());
+    exr.push(0);
+    exr.extend(kind.as_bytes());
+    exr.push(0);
+    exr.extend((value.len() as i32).to_le_bytes());
+    exr.extend(value);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::vec3::Color;
+
+    use super::Exr;
+
+    #[test]
+    fn lays_out_scanlines() {
+        let mut exr = Exr::from(2, 3);
+        exr.push_layer("", &vec![vec![Color::from(1., 2., 3.); 2]; 3]);
+        exr.push_channel("Z", vec![0.5; 6]);
+        let bytes = exr.encode();
+
+        // the last scanline ends the file and holds B, G, R and Z
+        let line = 8 + 4 * 2 * 4;
+        let last = &bytes[bytes.len() - line..];
+        assert_eq!(i32::from_le_bytes(last[..4].try_into().unwrap()), 2);
+        let value = |i: usize| f32::from_le_bytes(last[8 + 4 * i..12 + 4 * i].try_into().unwrap());
+        assert_eq!([value(0), value(2), value(4), value(6)], [3., 2., 1., 0.5]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use super::vec3::Color;
+
+/// Most pixels of an image read, as in a 16384 by 8192 environment map.
+const MAX_PIXELS: usize = 1 << 27;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a Radiance RGBE (`.hdr`) image, returning its width, height and
+/// pixels row by row from the top.
+pub fn read(path: &Path) -> io::Result<(usize, usize, Vec<Color>)> {
+    decode(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn decode(reader: &mut impl BufRead) -> io::Result<(usize, usize, Vec<Color>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid("not a Radiance HDR image"));
+    }
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("truncated header"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid("unsupported pixel format"));
+            }
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let size: Vec<&str> = line.split_whitespace().collect();
+    let (height, width) = match size.as_slice() {
+        ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+        _ => return Err(invalid("unsupported image orientation")),
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        return Err(invalid("bad image size"));
+    };
+    if width == 0 || height == 0 || width.checked_mul(height).is_none_or(|n| n > MAX_PIXELS) {
+        return Err(invalid("image size out of range"));
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|&rgbe| to_color(rgbe)));
+    }
+    Ok((width, height, pixels))
+}
+
+fn read_scanline(reader: &mut impl Read, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    let mut start = [0; 4];
+    reader.read_exact(&mut start)?;
+    let rle = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+    if !rle {
+        scanline[0] = start;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    // the four channels are run length encoded one after another
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0];
+            reader.read_exact(&mut count)?;
+            let (run, count) = if count[0] > 128 {
+                (true, count[0] as usize - 128)
+            } else {
+                (false, count[0] as usize)
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid("bad run length"));
+            }
+            let mut value = [0];
+            if run {
+                reader.read_exact(&mut value)?;
+            }
+            for pixel in &mut scanline[x..x + count] {
+                if !run {
+                    reader.read_exact(&mut value)?;
+                }
+                pixel[channel] = value[0];
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+fn to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::new();
+    }
+    let f = 2f64.powi(e as i32 - 136);
+    Color::from(
+        (r as f64 + 0.5) * f,
+        (g as f64 + 0.5) * f,
+        (b as f64 + 0.5) * f,
+    )
+}
+
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let v = color.x().max(color.y()).max(color.z());
+    if v < 1e-32 {
+        return [0; 4];
+    }
+    let e = v.log2().floor() as i32 + 1;
+    let scale = 256. / 2f64.powi(e);
+    let channel = |c: f64| (c.max(0.) * scale).min(255.) as u8;
+    [
+        channel(color.x()),
+        channel(color.y()),
+        channel(color.z()),
+        (e + 128) as u8,
+    ]
+}
+
+/// Writes an uncompressed Radiance RGBE image, rows from the top.
+#[allow(dead_code)]
+pub fn write(path: &Path, width: usize, height: usize, pixels: &[Color]) -> io::Result<()> {
+    let mut data = format!(
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )
+    .into_bytes();
+    for pixel in pixels {
+        data.extend(to_rgbe(*pixel));
+    }
+    File::create(path)?.write_all(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::vec3::Color;
+
+    use super::{decode, read, write};
+
+    #[test]
+    fn decodes_flat_and_run_length_scanlines() {
+        let pixels = [Color::from(1., 0.5, 0.25), Color::from(100., 0., 3.)];
+        let path = std::env::temp_dir().join("raytracing_test.hdr");
+        write(&path, 2, 1, &pixels).unwrap();
+        let (width, height, read_back) = read(&path).unwrap();
+        assert_eq!((width, height), (2, 1));
+        for (a, b) in pixels.iter().zip(read_back) {
+            assert!((*a - b).length() < a.length() * 0.01);
+        }
+
+        // 8 pixels: a run of 8 for r, g and e, literals for b
+        let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend([2, 2, 0, 8]);
+        data.extend([128 + 8, 128]);
+        data.extend([128 + 8, 64]);
+        data.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        data.extend([128 + 8, 129]);
+        let (_, _, pixels) = decode(&mut data.as_slice()).unwrap();
+        assert_eq!(pixels.len(), 8);
+        assert!((pixels[3] - Color::from(1.00390625, 0.50390625, 0.02734375)).length() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_out_of_range_sizes() {
+        for size in [
+            "-Y 0 +X 8",
+            "-Y 100000 +X 100000",
+            "-Y 4294967296 +X 4294967296",
+        ] {
+            let data = format!("#?RADIANCE\n\n{}\n", size);
+            assert!(decode(&mut data.as_bytes()).is_err(), "{}", size);
+        }
+    }
+}
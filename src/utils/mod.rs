@@ -1,3 +1,11 @@
+pub mod color;
+pub mod distribution;
+pub mod exr;
+pub mod hdr;
+#[allow(dead_code)]
 pub mod image;
+pub mod obj;
+pub mod png;
 pub mod ppm;
+pub mod spectrum;
 pub mod vec3;
@@ -0,0 +1,270 @@
+use std::{fs, io, path::Path};
+
+use super::vec3::{Color, Point3};
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Material of an MTL library, with the physically based extension.
+#[derive(Clone, Debug)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// `Kd`
+    pub diffuse: Color,
+    /// `Ks`
+    pub specular: Color,
+    /// `Ns`, the Phong exponent.
+    pub shininess: f64,
+    /// `Ke`
+    pub emission: Color,
+    /// `Ni`
+    pub ior: Option<f64>,
+    /// `d`, or one minus `Tr`.
+    pub dissolve: f64,
+    /// `Pr`
+    pub roughness: Option<f64>,
+    /// `Pm`
+    pub metallic: Option<f64>,
+    /// `Ps`
+    pub sheen: Option<f64>,
+    /// `Pc`
+    pub clearcoat: Option<f64>,
+    /// `Pcr`
+    pub clearcoat_roughness: Option<f64>,
+}
+
+impl MtlMaterial {
+    pub fn new(name: &str) -> Self {
+        MtlMaterial {
+            name: String::from(name),
+            diffuse: Color::from(0.8, 0.8, 0.8),
+            specular: Color::new(),
+            shininess: 0.,
+            emission: Color::new(),
+            ior: None,
+            dissolve: 1.,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+        }
+    }
+}
+
+/// Triangles sharing a material.
+pub struct ObjGroup {
+    pub material: Option<String>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// Geometry of a Wavefront OBJ file with the materials of its libraries.
+/// Polygons are split into triangle fans; normals and texture coordinates
+/// are ignored.
+pub struct ObjModel {
+    pub vertices: Vec<Point3>,
+    pub groups: Vec<ObjGroup>,
+    pub materials: Vec<MtlMaterial>,
+}
+
+impl ObjModel {
+    pub fn material(&self, name: &str) -> Option<&MtlMaterial> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+}
+
+/// Reads an OBJ file and the material libraries it names, which are looked
+/// up next to it.
+pub fn read(path: &Path) -> io::Result<ObjModel> {
+    let (mut model, libraries) = parse(&fs::read_to_string(path)?)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for library in libraries {
+        model
+            .materials
+            .extend(parse_mtl(&fs::read_to_string(dir.join(library))?)?);
+    }
+    Ok(model)
+}
+
+fn numbers<const N: usize>(args: &[&str]) -> io::Result<[f64; N]> {
+    if args.len() < N {
+        return Err(invalid("missing values"));
+    }
+    let mut values = [0.; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| invalid("invalid number"))?;
+    }
+    Ok(values)
+}
+
+fn color(args: &[&str]) -> io::Result<Color> {
+    let [r, g, b] = numbers(args)?;
+    Ok(Color::from(r, g, b))
+}
+
+/// Parses the text of an OBJ file, returning the model without materials
+/// and the material libraries it names.
+pub fn parse(text: &str) -> io::Result<(ObjModel, Vec<String>)> {
+    let mut model = ObjModel {
+        vertices: vec![],
+        groups: vec![],
+        materials: vec![],
+    };
+    let mut libraries = vec![];
+    let mut material = None;
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&keyword, args)) = tokens.split_first() else {
+            continue;
+        };
+        match keyword {
+            "v" => {
+                let [x, y, z] = numbers(args)?;
+                model.vertices.push(Point3::from(x, y, z));
+            }
+            "f" => {
+                let count = model.vertices.len() as i64;
+                let indices = args
+                    .iter()
+                    .map(|arg| {
+                        let index: i64 = arg
+                            .split('/')
+                            .next()
+                            .and_then(|i| i.parse().ok())
+                            .ok_or_else(|| invalid("invalid face"))?;
+                        // negative indices count back from the last vertex
+                        let index = if index < 0 { count + index } else { index - 1 };
+                        if index < 0 || index >= count {
+                            return Err(invalid("face vertex out of range"));
+                        }
+                        Ok(index as usize)
+                    })
+                    .collect::<io::Result<Vec<usize>>>()?;
+                if indices.len() < 3 {
+                    return Err(invalid("face with less than three vertices"));
+                }
+                let same_material = model
+                    .groups
+                    .last()
+                    .is_some_and(|group| group.material == material);
+                if !same_material {
+                    model.groups.push(ObjGroup {
+                        material: material.clone(),
+                        triangles: vec![],
+                    });
+                }
+                let group = model.groups.last_mut().unwrap();
+                for i in 1..indices.len() - 1 {
+                    group
+                        .triangles
+                        .push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            "usemtl" => material = args.first().map(|name| String::from(*name)),
+            "mtllib" => libraries.extend(args.iter().map(|name| String::from(*name))),
+            _ => {}
+        }
+    }
+    Ok((model, libraries))
+}
+
+/// Parses the text of an MTL material library.
+pub fn parse_mtl(text: &str) -> io::Result<Vec<MtlMaterial>> {
+    let mut materials: Vec<MtlMaterial> = vec![];
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&keyword, args)) = tokens.split_first() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            let name = args.first().ok_or_else(|| invalid("unnamed material"))?;
+            materials.push(MtlMaterial::new(name));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        let value = || numbers::<1>(args).map(|[v]| v);
+        // weights of the principled lobes
+        let unit = || {
+            value().and_then(|v| {
+                (0. ..=1.)
+                    .contains(&v)
+                    .then_some(v)
+                    .ok_or_else(|| invalid("value outside [0, 1]"))
+            })
+        };
+        let ior = || {
+            value().and_then(|v| {
+                (v > 0. && v.is_finite())
+                    .then_some(v)
+                    .ok_or_else(|| invalid("index of refraction must be positive"))
+            })
+        };
+        match keyword {
+            "Kd" => material.diffuse = color(args)?,
+            "Ks" => material.specular = color(args)?,
+            "Ke" => material.emission = color(args)?,
+            "Ns" => material.shininess = value()?,
+            "Ni" => material.ior = Some(ior()?),
+            "d" => material.dissolve = unit()?,
+            "Tr" => material.dissolve = 1. - unit()?,
+            "Pr" => material.roughness = Some(unit()?),
+            "Pm" => material.metallic = Some(unit()?),
+            "Ps" => material.sheen = Some(unit()?),
+            "Pc" => material.clearcoat = Some(unit()?),
+            "Pcr" => material.clearcoat_roughness = Some(unit()?),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, parse_mtl};
+
+    #[test]
+    fn parses_polygons_and_materials() {
+        let obj = "
+            # a quad and a triangle
+            mtllib scene.mtl
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            usemtl red
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            usemtl lamp
+            f -4 -3 -1
+        ";
+        let (model, libraries) = parse(obj).unwrap();
+        assert_eq!(libraries, ["scene.mtl"]);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.groups.len(), 2);
+        assert_eq!(model.groups[0].triangles, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(model.groups[1].material.as_deref(), Some("lamp"));
+        assert_eq!(model.groups[1].triangles, [[0, 1, 3]]);
+        assert!(parse("v 0 0 0\nf 1 2 3").is_err());
+
+        let mtl = "
+            newmtl red
+            Kd 0.8 0.1 0.1
+            Pr 0.3
+            Pm 1
+            Tr 0.25
+            newmtl lamp
+            Ke 10 10 10
+        ";
+        let materials = parse_mtl(mtl).unwrap();
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].roughness, Some(0.3));
+        assert_eq!(materials[0].metallic, Some(1.));
+        assert_eq!(materials[0].dissolve, 0.75);
+        assert_eq!(materials[1].emission.x(), 10.);
+        assert_eq!(materials[1].diffuse.x(), 0.8);
+        assert!(parse_mtl("newmtl gold\nPm 2").is_err());
+        assert!(parse_mtl("newmtl glass\nNi 0").is_err());
+    }
+}
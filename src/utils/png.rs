@@ -0,0 +1,117 @@
+use std::{fs::File, io::Write};
+
+/// 8 bit RGB PNG image. Pixels are pushed row by row from the top and stored
+/// without compression.
+pub struct Png {
+    width: u32,
+    height: u32,
+    // filter type byte followed by the pixels of every row
+    data: Vec<u8>,
+}
+
+impl Png {
+    pub fn from(width: u32, height: u32) -> Self {
+        Png {
+            width,
+            height,
+            data: Vec::with_capacity((height * (1 + 3 * width)) as usize),
+        }
+    }
+
+    pub fn push(&mut self, r: u8, g: u8, b: u8) {
+        let row = 1 + 3 * self.width as usize;
+        if self.data.len().is_multiple_of(row) {
+            self.data.push(0);
+        }
+        self.data.extend([r, g, b]);
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(13);
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // 8 bit truecolor, deflate, adaptive filtering, no interlace
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib_stored(&self.data));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[allow(dead_code)]
+    pub fn write(&self, name: &str) -> std::io::Result<()> {
+        File::create(name)?.write_all(&self.encode())
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+    use super::{adler32, crc32, Png};
+
+    #[test]
+    fn encodes_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+
+        let mut png = Png::from(2, 1);
+        png.push(255, 0, 0);
+        png.push(0, 0, 255);
+        let bytes = png.encode();
+        assert_eq!(&bytes[1..4], b"PNG");
+        assert_eq!(&bytes[bytes.len() - 8..bytes.len() - 4], b"IEND");
+    }
+}
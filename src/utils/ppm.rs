@@ -1,5 +1,6 @@
 use std::{fs::File, io::Write};
 
+#[allow(clippy::upper_case_acronyms, dead_code)]
 pub struct PPM {
     name: String,
     width: u32,
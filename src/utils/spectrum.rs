@@ -0,0 +1,425 @@
+use std::sync::OnceLock;
+
+use super::{
+    color::{self, cie_xyz, planck, spectrum_to_xyz, xyz_to_rgb},
+    vec3::Color,
+};
+
+/// Wavelengths in nanometers standing for the red, green and blue primaries,
+/// at which quantities given in RGB are taken to be measured.
+pub const PRIMARIES: [f64; 3] = [610., 550., 465.];
+
+const LAMBDA_MIN: f64 = 360.;
+const LAMBDA_MAX: f64 = 830.;
+
+/// Relative spectral power of the CIE D65 illuminant from 380 to 780 nm in
+/// steps of 10 nm.
+const D65: [f64; 41] = [
+    49.98, 54.65, 82.75, 91.49, 93.43, 86.68, 104.86, 117.01, 117.81, 114.86, 115.92, 108.81,
+    109.35, 107.80, 104.79, 107.69, 104.41, 104.05, 100.00, 96.33, 95.79, 88.69, 90.01, 89.60,
+    87.70, 83.29, 83.70, 80.03, 80.21, 82.28, 78.28, 69.72, 71.61, 74.35, 61.60, 69.89, 75.09,
+    63.59, 46.42, 66.81, 63.38,
+];
+
+/// Samples per side of the coefficient table, and spacing in nanometers of
+/// the wavelengths its colors are fitted at.
+const RESOLUTION: usize = 16;
+const FIT_STEP: f64 = 5.;
+
+/// Largest component below which colors scale the spectrum of a brighter
+/// one, which the sigmoid follows more closely.
+const DARK: f64 = 0.2;
+
+fn d65(lambda: f64) -> f64 {
+    let x = ((lambda - 380.) / 10.).clamp(0., 40.);
+    let i = (x as usize).min(39);
+    let t = x - i as f64;
+    D65[i] * (1. - t) + D65[i + 1] * t
+}
+
+fn components(color: Color) -> [f64; 3] {
+    [color.x(), color.y(), color.z()]
+}
+
+/// Value at `lambda` of a quantity given at the primaries, linearly
+/// interpolated between them and constant beyond.
+pub fn between_primaries(values: Color, lambda: f64) -> f64 {
+    let [r, g, b] = components(values);
+    let [red, green, blue] = PRIMARIES;
+    if lambda >= red {
+        r
+    } else if lambda >= green {
+        g + (r - g) * (lambda - green) / (red - green)
+    } else if lambda >= blue {
+        b + (g - b) * (lambda - blue) / (green - blue)
+    } else {
+        b
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    0.5 + x / (2. * (1. + x * x).sqrt())
+}
+
+/// Quadratic in the wavelength mapped to `[0, 1]` over the visible range.
+fn polynomial(c: [f64; 3], lambda: f64) -> f64 {
+    let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+    (c[0] * t + c[1]) * t + c[2]
+}
+
+fn smoothstep(x: f64) -> f64 {
+    x * x * (3. - 2. * x)
+}
+
+/// Solves `a x = b` by Cramer's rule.
+fn solve(a: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(a);
+    if d.abs() < 1e-15 {
+        return None;
+    }
+    Some(std::array::from_fn(|k| {
+        let mut m = a;
+        for i in 0..3 {
+            m[i][k] = b[i];
+        }
+        det(m) / d
+    }))
+}
+
+/// Sigmoid polynomial spectra reproducing the reflectances of the sRGB
+/// gamut under D65, after Jakob and Hanika, "A Low-Dimensional Function
+/// Space for Efficient Spectral Upsampling".
+///
+/// Colors are tabulated by their largest component, over a grid of that
+/// component's value from `DARK` and of the ratios of the others to it,
+/// and the coefficients interpolated in between.
+struct RgbToSpectrum {
+    scale: Vec<f64>,
+    coefficients: Vec<[f64; 3]>,
+    /// Scale turning D65 into a spectrum of unit luminance.
+    illuminant: f64,
+    y_integral: f64,
+    /// Linear sRGB the unit D65 spectrum converts to.
+    white: Color,
+}
+
+impl RgbToSpectrum {
+    fn new() -> Self {
+        let steps = ((LAMBDA_MAX - LAMBDA_MIN) / FIT_STEP) as usize;
+        let lambdas: Vec<f64> = (0..=steps)
+            .map(|i| LAMBDA_MIN + i as f64 * FIT_STEP)
+            .collect();
+        // linear sRGB of every wavelength under D65, normalised so that the
+        // constant unit spectrum is white
+        let mut weights: Vec<Color> = lambdas
+            .iter()
+            .map(|&lambda| xyz_to_rgb(cie_xyz(lambda) * d65(lambda)))
+            .collect();
+        let total = weights.iter().fold(Color::new(), |sum, &w| sum + w);
+        for weight in &mut weights {
+            *weight /= total;
+        }
+
+        let scale: Vec<f64> = (0..RESOLUTION)
+            .map(|k| DARK + (1. - DARK) * smoothstep(k as f64 / (RESOLUTION - 1) as f64))
+            .collect();
+        let mut coefficients = vec![[0.; 3]; 3 * RESOLUTION.pow(3)];
+        let index = |l: usize, k: usize, j: usize, i: usize| {
+            ((l * RESOLUTION + k) * RESOLUTION + j) * RESOLUTION + i
+        };
+        for l in 0..3 {
+            for j in 0..RESOLUTION {
+                for i in 0..RESOLUTION {
+                    let x = i as f64 / (RESOLUTION - 1) as f64;
+                    let y = j as f64 / (RESOLUTION - 1) as f64;
+                    let fit = |k: usize, c: [f64; 3]| {
+                        let mut rgb = [0.; 3];
+                        rgb[l] = scale[k];
+                        rgb[(l + 1) % 3] = x * scale[k];
+                        rgb[(l + 2) % 3] = y * scale[k];
+                        Self::fit(&lambdas, &weights, rgb, c)
+                    };
+                    // fits start from their neighbour, away from a mid grey
+                    let start = RESOLUTION / 5;
+                    let mut c = [0.; 3];
+                    for k in start..RESOLUTION {
+                        c = fit(k, c);
+                        coefficients[index(l, k, j, i)] = c;
+                    }
+                    c = coefficients[index(l, start, j, i)];
+                    for k in (0..start).rev() {
+                        c = fit(k, c);
+                        coefficients[index(l, k, j, i)] = c;
+                    }
+                }
+            }
+        }
+
+        let y_integral = spectrum_to_xyz(|_| 1.).y();
+        let d65_xyz = spectrum_to_xyz(d65);
+        let illuminant = y_integral / d65_xyz.y();
+        RgbToSpectrum {
+            scale,
+            coefficients,
+            illuminant,
+            y_integral,
+            white: xyz_to_rgb(d65_xyz / d65_xyz.y()),
+        }
+    }
+
+    /// Gauss-Newton iterations from `c` towards the coefficients of `rgb`.
+    fn fit(lambdas: &[f64], weights: &[Color], rgb: [f64; 3], mut c: [f64; 3]) -> [f64; 3] {
+        for _ in 0..15 {
+            let mut residual = rgb.map(|v| -v);
+            let mut jacobian = [[0.; 3]; 3];
+            for (&lambda, &weight) in lambdas.iter().zip(weights) {
+                let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+                let x = polynomial(c, lambda);
+                let s = sigmoid(x);
+                let ds = 0.5 / (1. + x * x).powf(1.5);
+                let weight = components(weight);
+                for i in 0..3 {
+                    residual[i] += s * weight[i];
+                    for (k, dx) in [t * t, t, 1.].into_iter().enumerate() {
+                        jacobian[i][k] += ds * dx * weight[i];
+                    }
+                }
+            }
+            if residual.iter().map(|r| r * r).sum::<f64>() < 1e-12 {
+                break;
+            }
+            let Some(step) = solve(jacobian, residual) else {
+                break;
+            };
+            for k in 0..3 {
+                c[k] -= step[k];
+            }
+            // keeps saturated colors from running off
+            let max = c.iter().fold(0., |m: f64, v| m.max(v.abs()));
+            if max > 200. {
+                c = c.map(|v| v * 200. / max);
+            }
+        }
+        c
+    }
+
+    /// Coefficients of the spectrum of `rgb` and the factor scaling it.
+    fn coefficients(&self, rgb: Color) -> ([f64; 3], f64) {
+        let rgb = components(rgb).map(|v| v.clamp(0., 1.));
+        let l = (0..3).fold(0, |l, i| if rgb[i] > rgb[l] { i } else { l });
+        if rgb[l] == 0. {
+            return ([0.; 3], 0.);
+        }
+        let x = rgb[(l + 1) % 3] / rgb[l] * (RESOLUTION - 1) as f64;
+        let y = rgb[(l + 2) % 3] / rgb[l] * (RESOLUTION - 1) as f64;
+        let z = rgb[l].max(DARK);
+        let k = self
+            .scale
+            .partition_point(|&s| s <= z)
+            .clamp(1, RESOLUTION - 1)
+            - 1;
+        let (i, j) = (
+            (x as usize).min(RESOLUTION - 2),
+            (y as usize).min(RESOLUTION - 2),
+        );
+        let dz = ((z - self.scale[k]) / (self.scale[k + 1] - self.scale[k])).clamp(0., 1.);
+        let (dx, dy) = (x - i as f64, y - j as f64);
+
+        let mut c = [0.; 3];
+        for (ck, wz) in [(k, 1. - dz), (k + 1, dz)] {
+            for (cj, wy) in [(j, 1. - dy), (j + 1, dy)] {
+                for (ci, wx) in [(i, 1. - dx), (i + 1, dx)] {
+                    let corner = self.coefficients
+                        [((l * RESOLUTION + ck) * RESOLUTION + cj) * RESOLUTION + ci];
+                    for n in 0..3 {
+                        c[n] += corner[n] * wx * wy * wz;
+                    }
+                }
+            }
+        }
+        (c, rgb[l] / z)
+    }
+}
+
+fn table() -> &'static RgbToSpectrum {
+    static TABLE: OnceLock<RgbToSpectrum> = OnceLock::new();
+    TABLE.get_or_init(RgbToSpectrum::new)
+}
+
+/// Wavelengths in nanometers a path carries in spectral mode, each color
+/// channel holding the quantity at one of them.
+#[derive(Clone, Copy, Debug)]
+pub struct Wavelengths {
+    lambda: [f64; 3],
+    pdf: [f64; 3],
+}
+
+impl Wavelengths {
+    /// Three wavelengths stratified from one uniform sample, the hero and
+    /// two rotations of it, each drawn in proportion to how visible it is.
+    pub fn sample(u: f64) -> Self {
+        let lambda = [0., 1., 2.].map(|i| {
+            let u = (u + i / 3.).fract();
+            538. - 138.888889 * (0.85691062 - 1.82750197 * u).atanh()
+        });
+        let pdf = lambda.map(|l| {
+            if (LAMBDA_MIN..=LAMBDA_MAX).contains(&l) {
+                0.0039398042 / (0.0072 * (l - 538.)).cosh().powi(2)
+            } else {
+                0.
+            }
+        });
+        Wavelengths { lambda, pdf }
+    }
+
+    pub fn lambda(&self) -> [f64; 3] {
+        self.lambda
+    }
+
+    /// Values of a spectrum at the wavelengths.
+    pub fn evaluate(&self, spectrum: impl Fn(f64) -> f64) -> Color {
+        let [a, b, c] = self.lambda.map(spectrum);
+        Color::from(a, b, c)
+    }
+
+    /// Reflectance of a linear sRGB color in `[0, 1]` at the wavelengths.
+    pub fn reflectance(&self, rgb: Color) -> Color {
+        let (c, scale) = table().coefficients(rgb);
+        self.evaluate(|lambda| scale * sigmoid(polynomial(c, lambda)))
+    }
+
+    /// Spectral radiance at the wavelengths of light of a linear sRGB color,
+    /// white being D65.
+    pub fn illuminant(&self, rgb: Color) -> Color {
+        let table = table();
+        // unbounded colors are spectra at most one, scaled
+        let max = components(rgb).into_iter().fold(0., f64::max);
+        if max <= 0. {
+            return Color::new();
+        }
+        let (c, dark) = table.coefficients(rgb / (2. * max));
+        let scale = 2. * max * dark;
+        self.evaluate(|lambda| {
+            scale * sigmoid(polynomial(c, lambda)) * d65(lambda) * table.illuminant
+        })
+    }
+
+    /// Spectral radiance at the wavelengths of light of a linear sRGB color
+    /// proportional to the color of `blackbody`, following Planck's law.
+    pub fn blackbody(&self, rgb: Color, blackbody: &Blackbody) -> Color {
+        let upsampled = self.illuminant(blackbody.rgb);
+        if upsampled.near_zero() {
+            return self.illuminant(rgb);
+        }
+        let spectrum = self.evaluate(|lambda| planck(lambda, blackbody.kelvin) * blackbody.scale);
+        // the upsampled color of the blackbody gives way to its spectrum
+        self.illuminant(rgb) * spectrum / upsampled
+    }
+
+    /// Linear sRGB of the radiance carried at the wavelengths, estimating
+    /// the integral over the spectrum.
+    pub fn to_rgb(self, radiance: Color) -> Color {
+        let table = table();
+        let xyz = (0..3)
+            .filter(|&i| self.pdf[i] > 0.)
+            .fold(Color::new(), |sum, i| {
+                sum + cie_xyz(self.lambda[i]) * (components(radiance)[i] / self.pdf[i])
+            });
+        xyz_to_rgb(xyz / (3. * table.y_integral)) / table.white
+    }
+
+    /// Linear sRGB of a reflectance at the wavelengths, as seen under D65.
+    pub fn reflectance_to_rgb(&self, reflectance: Color) -> Color {
+        self.to_rgb(reflectance * self.illuminant(Color::from(1., 1., 1.)))
+    }
+}
+
+/// Blackbody at a temperature in Kelvin, scaled to a luminance of 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Blackbody {
+    kelvin: f64,
+    /// Scale of Planck's law to spectral radiance of unit luminance.
+    scale: f64,
+    rgb: Color,
+}
+
+impl Blackbody {
+    pub fn from(kelvin: f64) -> Self {
+        let luminance = spectrum_to_xyz(|lambda| planck(lambda, kelvin)).y();
+        let scale = if luminance > 0. {
+            spectrum_to_xyz(|_| 1.).y() / luminance
+        } else {
+            0.
+        };
+        Blackbody {
+            kelvin,
+            scale,
+            rgb: color::blackbody(kelvin),
+        }
+    }
+
+    /// Linear sRGB color, as given by `color::blackbody`.
+    pub fn rgb(&self) -> Color {
+        self.rgb
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::{color::planck, vec3::Color};
+
+    use super::{between_primaries, components, Blackbody, Wavelengths};
+
+    #[test]
+    fn upsampled_colors_round_trip() {
+        let n = 30000;
+        for rgb in [
+            Color::from(1., 1., 1.),
+            Color::from(0.8, 0.2, 0.1),
+            Color::from(0.1, 0.5, 0.3),
+            Color::from(0.05, 0.1, 0.9),
+            Color::from(0.02, 0.02, 0.02),
+            Color::from(0.1, 0.02, 0.01),
+        ] {
+            let (mut reflected, mut emitted) = (Color::new(), Color::new());
+            for s in 0..n {
+                let wavelengths = Wavelengths::sample((s as f64 + 0.5) / n as f64);
+                reflected += wavelengths.reflectance_to_rgb(wavelengths.reflectance(rgb));
+                emitted += wavelengths.to_rgb(wavelengths.illuminant(rgb * 4.));
+            }
+            let reflected = reflected / n as f64;
+            let emitted = emitted / (4. * n as f64);
+            assert!((reflected - rgb).length() < 0.01, "{} {}", rgb, reflected);
+            assert!((emitted - rgb).length() < 0.01, "{} {}", rgb, emitted);
+        }
+        // reflectances stay physical
+        let wavelengths = Wavelengths::sample(0.3);
+        let saturated = wavelengths.reflectance(Color::from(0., 0., 1.));
+        assert!(components(saturated)
+            .iter()
+            .all(|&v| (0. ..=1.).contains(&v)));
+        assert!((between_primaries(Color::from(3., 2., 1.), 580.) - 2.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn blackbody_follows_planck() {
+        let blackbody = Blackbody::from(2700.);
+        let n = 30000;
+        let mut emitted = Color::new();
+        for s in 0..n {
+            let wavelengths = Wavelengths::sample((s as f64 + 0.5) / n as f64);
+            let radiance = wavelengths.blackbody(blackbody.rgb() * 3., &blackbody);
+            // every wavelength is in the same proportion to Planck's law
+            let ratios = components(radiance / wavelengths.evaluate(|l| planck(l, 2700.)));
+            assert!(ratios.iter().all(|r| (r / ratios[0] - 1.).abs() < 1e-9));
+            emitted += wavelengths.to_rgb(radiance);
+        }
+        let emitted = emitted / (3. * n as f64);
+        assert!((emitted - blackbody.rgb()).length() < 0.02, "{}", emitted);
+    }
+}
@@ -1,4 +1,5 @@
 use rand::{self, Rng};
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
@@ -7,7 +8,7 @@ use std::{
 pub type Point3 = Vec3;
 pub type Color = Vec3;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Vec3(f64, f64, f64);
 
 impl Vec3 {
@@ -55,10 +56,10 @@ impl Vec3 {
         self / self.length()
     }
 
-    pub fn to_rgb(&self, samples: u32) -> (u8, u8, u8) {
+    pub fn to_rgb(self, samples: u32) -> (u8, u8, u8) {
         // gamma set to 2
 
-        let color = *self * (1. / samples as f64);
+        let color = self * (1. / samples as f64);
         let r = color.0.sqrt().clamp(0., 1.) * 255.;
         let g = color.1.sqrt().clamp(0., 1.) * 255.;
         let b = color.2.sqrt().clamp(0., 1.) * 255.;
@@ -67,7 +68,17 @@ impl Vec3 {
 
     pub fn near_zero(&self) -> bool {
         let epsilon = 1e-8;
-        return (self.0.abs() < epsilon) && (self.1.abs() < epsilon) && (self.2.abs() < epsilon);
+        (self.0.abs() < epsilon) && (self.1.abs() < epsilon) && (self.2.abs() < epsilon)
+    }
+
+    /// Whether the vector has a finite, non-zero length to normalize.
+    pub fn is_direction(&self) -> bool {
+        let length = self.length();
+        length > 0. && length.is_finite()
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
     }
 }
 
@@ -92,6 +103,26 @@ impl Vec3 {
             }
         }
     }
+
+    /// Maps a 2D sample in `[0, 1)^2` to a uniformly distributed unit vector.
+    pub fn unit_from_sample(u: f64, v: f64) -> Self {
+        let z = 1. - 2. * u;
+        let r = (1. - z * z).max(0.).sqrt();
+        let phi = 2. * std::f64::consts::PI * v;
+        Vec3(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    /// Two unit vectors completing this unit vector to an orthonormal basis.
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let a = if self.0.abs() > 0.9 {
+            Vec3(0., 1., 0.)
+        } else {
+            Vec3(1., 0., 0.)
+        };
+        let s = self.cross(&a).unit_vector();
+        let t = self.cross(&s);
+        (s, t)
+    }
 }
 
 impl Display for Vec3 {